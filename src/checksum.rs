@@ -0,0 +1,120 @@
+use crate::parsertree::ParserTree;
+use crate::structs::get_verify_error;
+
+/// Get the algorithm and the data of the `Checksum(algo="...", over="...")` attribute, if present
+fn get_checksum(field: &syn::Field) -> Option<(String, String)> {
+    for attr in &field.attrs {
+        if let Ok(syn::Meta::List(ref metalist)) = attr.parse_meta() {
+            if metalist.ident != "Checksum" {
+                continue;
+            }
+            let (mut algo, mut over) = (None, None);
+            for n in metalist.nested.iter() {
+                match n {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(ref namevalue)) => {
+                        let value = match &namevalue.lit {
+                            syn::Lit::Str(s) => s.value(),
+                            _ => panic!("Invalid 'Checksum' attribute type/value")
+                        };
+                        if namevalue.ident == "algo" { algo = Some(value); }
+                        else if namevalue.ident == "over" { over = Some(value); }
+                        else { panic!("Invalid 'Checksum' attribute: unknown key {}", namevalue.ident); }
+                    },
+                    _ => panic!("Invalid 'Checksum' attribute type/value")
+                }
+            }
+            match (algo, over) {
+                (Some(algo), Some(over)) => return Some((algo, over)),
+                _ => panic!("Nom-derive: the 'Checksum' attribute requires the 'algo' and 'over' values")
+            }
+        }
+    }
+    None
+}
+
+/// Code of the function computing the checksum of a `&[u8]`
+///
+/// The names of the built-in algorithms are reserved (even if the `checksums` feature is
+/// disabled), other names are paths to user functions.
+fn get_checksum_function(algo: &str) -> String {
+    let builtin = match algo {
+        // CRC-32 (IEEE 802.3), as used by zlib or Ethernet
+        "crc32" => {
+            "({ fn __crc32(data: &[u8]) -> u32 { \
+               let mut crc = !0u32; \
+               for b in data { \
+                 crc ^= u32::from(*b); \
+                 for _ in 0..8 { crc = if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 }; } \
+               } \
+               !crc } __crc32 })"
+        },
+        // CRC-16/CCITT-FALSE
+        "crc16-ccitt" => {
+            "({ fn __crc16(data: &[u8]) -> u16 { \
+               let mut crc = 0xffffu16; \
+               for b in data { \
+                 crc ^= u16::from(*b) << 8; \
+                 for _ in 0..8 { crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 }; } \
+               } \
+               crc } __crc16 })"
+        },
+        // sum of the bytes, modulo 256
+        "sum8" => {
+            "({ fn __sum8(data: &[u8]) -> u8 { data.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) } __sum8 })"
+        },
+        f => return f.to_owned(),
+    };
+    if cfg!(feature = "checksums") {
+        builtin.to_owned()
+    } else {
+        panic!("Nom-derive: the '{}' checksum algorithm requires the 'checksums' feature", algo)
+    }
+}
+
+/// Verify the value of the field, if it has the `Checksum` attribute
+pub(crate) fn add_checksum(field: &syn::Field, ident: &str, p: ParserTree) -> ParserTree {
+    match get_checksum(field) {
+        Some((algo, over)) => {
+            let f = get_checksum_function(&algo);
+            let check = format!("{} == {}(&({})[..])", ident, f, over);
+            ParserTree::Verify(Box::new(p), ident.to_owned(), check, get_verify_error(field))
+        },
+        None => p
+    }
+}
+
+/// Get the algorithm of the `SelfExcludingChecksum="algo"` attribute, if present
+pub(crate) fn get_self_excluding_checksum(field: &syn::Field) -> Option<String> {
+    for attr in &field.attrs {
+        if let Ok(syn::Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "SelfExcludingChecksum" {
+                return match &namevalue.lit {
+                    syn::Lit::Str(s) => Some(s.value()),
+                    _ => panic!("Invalid 'SelfExcludingChecksum' attribute type/value")
+                };
+            }
+        }
+    }
+    None
+}
+
+/// Parser returning the current offset (relative to the input of the parse function), without
+/// consuming input
+pub(crate) fn gen_offset() -> ParserTree {
+    ParserTree::Raw("call!(|i_| { let i_: &[u8] = i_; \
+                     let r : IResult<&[u8],usize> = Ok((i_, __input.len() - i_.len())); r })".to_owned())
+}
+
+/// Verify a checksum computed over all the bytes parsed so far, where the bytes of the
+/// checksum field (between the offsets `start` and `end`) are replaced by zeroes
+pub(crate) fn gen_self_excluding_check(field: &syn::Field, ident: &str, start: &str, end: &str) -> ParserTree {
+    let algo = get_self_excluding_checksum(field).expect("no 'SelfExcludingChecksum' attribute");
+    let f = get_checksum_function(&algo);
+    ParserTree::Raw(format!(
+        "call!(|i_| {{ let i_: &[u8] = i_; \
+           let mut __data = __input[..__input.len() - i_.len()].to_vec(); \
+           __data[{start}..{end}].iter_mut().for_each(|b| *b = 0); \
+           if {ident} == {f}(&__data) {{ Ok((i_, ())) }} \
+           else {{ Err(nom::Err::Error(error_position!(&__input[{start}..], {e}))) }} }})",
+        start=start, end=end, ident=ident, f=f, e=get_verify_error(field)))
+}
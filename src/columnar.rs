@@ -0,0 +1,58 @@
+use syn::export::Span;
+
+use crate::endian::{gen_input_bound_type,ParserEndianness};
+use crate::parsertree::ParserTree;
+use crate::structs::parse_struct;
+
+fn is_columnar(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "Columnar",
+            _ => false
+        }
+    })
+}
+
+/// Generate the `parse_columnar` function (if requested)
+///
+/// The function parses `count` items stored by columns: `count` values of the first field,
+/// then `count` values of the second field, etc. The columns are then zipped to build the
+/// items.
+pub(crate) fn gen_parse_columnar(ast: &syn::DeriveInput, s: &syn::DataStruct, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    if !is_columnar(&ast.attrs) {
+        return quote!{};
+    }
+    let mut tree = parse_struct(s, &ast.attrs, endianness);
+    let columns : Vec<_> = tree.fields.iter()
+        .filter(|(_, init)| init.is_none())
+        .map(|(name, _)| syn::Ident::new(name, Span::call_site()))
+        .collect();
+    if tree.parsers.len() != columns.len() || tree.bounding_length.is_some() || !tree.execs.is_empty() {
+        panic!("Nom-derive: the 'Columnar' attribute cannot be used with 'Skip', 'Tag', 'BoundingLength', 'SetEndian', 'EndianFrom', 'PreExec', 'PostExec' or 'NomByteOrderMark'");
+    }
+    let (first, others) = columns.split_first().expect("Nom-derive: the 'Columnar' attribute requires at least one parsed field");
+    // the errors are returned at the position of the failing item, like the `Count` attribute
+    let parsers : Vec<_> = ::std::mem::take(&mut tree.parsers).into_iter()
+        .map(|(_, parser)| ParserTree::Count(Box::new(parser), "__count".to_owned()))
+        .collect();
+    // the zipped values are nested pairs: ((a, b), c)
+    let pattern = others.iter().fold(quote!{ #first }, |acc, id| quote!{ (#acc, #id) });
+    let name = &ast.ident;
+    let struct_def = tree.gen_struct_def(&quote!{ #name });
+    let columns = &columns;
+    let output = gen_input_bound_type(ast, &quote!{ '_ });
+    quote!{
+        fn parse_columnar(i: &[u8], __count: usize) -> IResult<&[u8],Vec<#output>> {
+            do_parse!{
+                i,
+                #(#columns: #parsers >>)*
+                (
+                    #first.into_iter()
+                        #(.zip(#others))*
+                        .map(|#pattern| #struct_def)
+                        .collect()
+                )
+            }
+        }
+    }
+}
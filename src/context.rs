@@ -0,0 +1,54 @@
+use crate::endian::{gen_input_bound_type,is_dynamic_endian,ParserEndianness};
+use crate::structs::parse_struct;
+
+/// Get the type of the parsing context, given by the `NomContext` attribute
+pub(crate) fn get_context(attrs: &[syn::Attribute]) -> Option<proc_macro2::TokenStream> {
+    for attr in attrs {
+        if let Ok(syn::Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "NomContext" {
+                return match &namevalue.lit {
+                    syn::Lit::Str(s) => Some(s.value().parse().expect("Invalid 'NomContext' attribute value")),
+                    _ => panic!("Invalid 'NomContext' attribute type/value")
+                };
+            }
+        }
+    }
+    None
+}
+
+/// Generate the body of a parse function without context: `parse_with_context` is called
+/// using the default value of the context
+pub(crate) fn gen_default_context_call(ast: &syn::DeriveInput) -> Option<proc_macro2::TokenStream> {
+    let name = &ast.ident;
+    get_context(&ast.attrs).map(|_| quote!{
+        #name::parse_with_context(i, &Default::default())
+    })
+}
+
+/// Generate the `parse_with_context` function (if requested)
+///
+/// The function takes a reference to the context (named `ctx`) as last argument, which can
+/// be used in the attributes of the fields. The structure is parsed using its default byte
+/// order.
+pub(crate) fn gen_parse_with_context(ast: &syn::DeriveInput, s: &syn::DataStruct, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    let ctx = match get_context(&ast.attrs) {
+        Some(ctx) => ctx,
+        None => return quote!{}
+    };
+    if is_dynamic_endian(&ast.attrs) {
+        panic!("Nom-derive: the 'NomContext' and 'DynamicEndian' attributes are mutually exclusive");
+    }
+    let other_parsers = ["NomBothEndian", "NomEndianAware", "NomResumable", "Columnar", "NomTraceErrors", "NomParseTraced", "NomUnsafeFastPath"];
+    if ast.attrs.iter().any(|attr| other_parsers.iter().any(|name| attr.path.is_ident(name))) {
+        panic!("Nom-derive: the 'NomContext' attribute cannot be used with 'NomBothEndian', 'NomEndianAware', 'NomResumable', 'Columnar', 'NomTraceErrors', 'NomParseTraced' or 'NomUnsafeFastPath'");
+    }
+    let name = &ast.ident;
+    let body = parse_struct(s, &ast.attrs, endianness).gen_do_parse(&quote!{ #name });
+    // the lifetime of the output cannot be elided (there are two references)
+    let output = gen_input_bound_type(ast, &quote!{ '__i });
+    quote!{
+        fn parse_with_context<'__i>(i: &'__i [u8], ctx: &#ctx) -> IResult<&'__i [u8],#output> {
+            #body
+        }
+    }
+}
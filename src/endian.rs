@@ -0,0 +1,304 @@
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ParserEndianness {
+    #[default]
+    BigEndian,
+    LittleEndian,
+    /// Byte order of the target, selected at compile time
+    Native,
+    /// Selected at runtime, by the value of the `__endianness` variable
+    Runtime,
+}
+
+impl ParserEndianness {
+    /// Nom parser for the primitive type `ty` (for ex. `be_u16`) with this endianness
+    pub fn primitive_parser(self, ty: &str) -> String {
+        match self {
+            ParserEndianness::BigEndian    => format!("be_{}", ty),
+            ParserEndianness::LittleEndian => format!("le_{}", ty),
+            ParserEndianness::Native       => {
+                format!("call!(if cfg!(target_endian = \"big\") {{ be_{} }} else {{ le_{} }})", ty, ty)
+            },
+            ParserEndianness::Runtime      => {
+                format!("call!(if __endianness == nom::Endianness::Big {{ be_{} }} else {{ le_{} }})", ty, ty)
+            }
+        }
+    }
+
+    /// Runtime value of this endianness, and of the opposite endianness
+    pub fn runtime_values(self) -> (&'static str, &'static str) {
+        match self {
+            ParserEndianness::BigEndian    => ("nom::Endianness::Big", "nom::Endianness::Little"),
+            ParserEndianness::LittleEndian => ("nom::Endianness::Little", "nom::Endianness::Big"),
+            ParserEndianness::Native       => {
+                ("(if cfg!(target_endian = \"big\") { nom::Endianness::Big } else { nom::Endianness::Little })",
+                 "(if cfg!(target_endian = \"big\") { nom::Endianness::Little } else { nom::Endianness::Big })")
+            },
+            ParserEndianness::Runtime      => panic!("'SetEndian' attribute cannot be used on a field whose endianness is selected at runtime"),
+        }
+    }
+}
+
+fn get_endianness(attrs: &[syn::Attribute]) -> Option<ParserEndianness> {
+    let mut endianness = None;
+    for attr in attrs {
+        if let Ok(syn::Meta::Word(ref ident)) = attr.parse_meta() {
+            let e = if ident == "LittleEndian" { ParserEndianness::LittleEndian }
+                    else if ident == "BigEndian" { ParserEndianness::BigEndian }
+                    else if ident == "NativeEndian" { ParserEndianness::Native }
+                    else { continue; };
+            if endianness.is_some() {
+                panic!("Nom-derive: 'BigEndian', 'LittleEndian' and 'NativeEndian' attributes are mutually exclusive");
+            }
+            endianness = Some(e);
+        }
+    }
+    endianness
+}
+
+/// Get the endianness specified by the attributes of a struct (or enum), if any, or the
+/// default endianness of the derive
+pub(crate) fn get_object_endianness(attrs: &[syn::Attribute], default: ParserEndianness) -> ParserEndianness {
+    get_endianness(attrs).unwrap_or(default)
+}
+
+/// Get the endianness of a field: its attributes override the endianness of the object
+pub(crate) fn get_local_endianness(attrs: &[syn::Attribute], object_endianness: ParserEndianness) -> ParserEndianness {
+    get_endianness(attrs).unwrap_or(object_endianness)
+}
+
+/// Get the byte order marks of the `NomByteOrderMark(le="...", be="...")` attribute, if present
+pub(crate) fn get_byte_order_mark(attrs: &[syn::Attribute]) -> Option<(String, String)> {
+    for attr in attrs {
+        if let Ok(syn::Meta::List(ref metalist)) = attr.parse_meta() {
+            if metalist.ident != "NomByteOrderMark" {
+                continue;
+            }
+            let (mut le, mut be) = (None, None);
+            for n in metalist.nested.iter() {
+                match n {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(ref namevalue)) => {
+                        let value = match &namevalue.lit {
+                            syn::Lit::Str(s) => s.value(),
+                            _ => panic!("Invalid 'NomByteOrderMark' attribute type/value")
+                        };
+                        if namevalue.ident == "le" { le = Some(value); }
+                        else if namevalue.ident == "be" { be = Some(value); }
+                        else { panic!("Invalid 'NomByteOrderMark' attribute: unknown key {}", namevalue.ident); }
+                    },
+                    _ => panic!("Invalid 'NomByteOrderMark' attribute type/value")
+                }
+            }
+            match (le, be) {
+                (Some(le), Some(be)) => return Some((le, be)),
+                _ => panic!("Nom-derive: the 'NomByteOrderMark' attribute requires both 'le' and 'be' marks")
+            }
+        }
+    }
+    None
+}
+
+fn is_both_endian(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomBothEndian",
+            _ => false
+        }
+    })
+}
+
+/// Test if the `DynamicEndian` attribute is present: on an object, the endianness is given
+/// as an argument of `parse`; on a field, this endianness is forwarded to the field parser
+pub(crate) fn is_dynamic_endian(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "DynamicEndian",
+            _ => false
+        }
+    })
+}
+
+fn is_endian_aware(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomEndianAware",
+            _ => false
+        }
+    })
+}
+
+/// Get the name of the parse function, given by the `NomParseName` attribute (`parse` by default)
+pub(crate) fn get_parse_name(attrs: &[syn::Attribute]) -> syn::Ident {
+    for attr in attrs {
+        if let Ok(syn::Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "NomParseName" {
+                match &namevalue.lit {
+                    syn::Lit::Str(s) => return syn::Ident::new(&s.value(), s.span()),
+                    _ => panic!("Invalid 'NomParseName' attribute type/value")
+                }
+            }
+        }
+    }
+    syn::Ident::new("parse", proc_macro2::Span::call_site())
+}
+
+/// Get the error type of the parse function, given by the `NomErr` attribute
+pub(crate) fn get_nom_err(attrs: &[syn::Attribute]) -> Option<proc_macro2::TokenStream> {
+    for attr in attrs {
+        if let Ok(syn::Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "NomErr" {
+                return match &namevalue.lit {
+                    syn::Lit::Str(s) => Some(s.value().parse().expect("Invalid 'NomErr' attribute value")),
+                    _ => panic!("Invalid 'NomErr' attribute type/value")
+                };
+            }
+        }
+    }
+    None
+}
+
+/// Get the maximum length of the input, given by the `NomMaxLen` attribute
+fn get_max_len(attrs: &[syn::Attribute]) -> Option<proc_macro2::TokenStream> {
+    for attr in attrs {
+        if let Ok(syn::Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "NomMaxLen" {
+                return match &namevalue.lit {
+                    syn::Lit::Str(s) => Some(s.value().parse().expect("Invalid 'NomMaxLen' attribute value")),
+                    syn::Lit::Int(i) => Some(quote!{ #i }),
+                    _ => panic!("Invalid 'NomMaxLen' attribute type/value")
+                };
+            }
+        }
+    }
+    None
+}
+
+/// Generate the type of an object, with its lifetimes bound to the lifetime of the input
+/// (`lifetime`, or `'_` where it is elided) and its type parameters
+pub(crate) fn gen_input_bound_type(ast: &syn::DeriveInput, lifetime: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    if ast.generics.params.is_empty() {
+        return quote!{ #name };
+    }
+    let args : Vec<_> = ast.generics.params.iter()
+        .map(|param| match param {
+            syn::GenericParam::Lifetime(_) => lifetime.clone(),
+            syn::GenericParam::Type(t) => { let id = &t.ident; quote!{ #id } },
+            syn::GenericParam::Const(c) => { let id = &c.ident; quote!{ #id } },
+        })
+        .collect();
+    quote!{ #name<#(#args),*> }
+}
+
+/// Generate the parse function(s) of an object
+///
+/// By default, only `parse` is generated (it can be renamed using the `NomParseName`
+/// attribute). If the object has the `NomBothEndian` attribute,
+/// `parse_be` and `parse_le` are generated, and `parse` calls the one matching the
+/// endianness of the object. If the object has the `DynamicEndian` attribute, `parse` takes
+/// the endianness as last argument.
+///
+/// If the object has the `DynamicEndian` or `NomEndianAware` attribute, `parse_endian` is
+/// also generated, taking the endianness as last argument. It is used by parents with a
+/// runtime endianness.
+///
+/// If the object has the `NomMaxLen` attribute, all functions first reject inputs longer
+/// than the limit.
+///
+/// If the object has the `NomErr` attribute, `parse` returns this error type: the errors of
+/// the parser (using the default error type) are converted. The other functions are not
+/// modified.
+///
+/// `args` are the additional arguments of the functions (after the input), and `call_args`
+/// the tokens used to pass them. `gen_body` generates the body of the function for the
+/// given endianness.
+pub(crate) fn gen_parse_functions<F>(ast: &syn::DeriveInput, endianness: ParserEndianness,
+                                     args: &proc_macro2::TokenStream, call_args: &proc_macro2::TokenStream,
+                                     gen_body: F) -> proc_macro2::TokenStream
+    where F: Fn(ParserEndianness) -> proc_macro2::TokenStream
+{
+    let attrs = &ast.attrs;
+    let name = &ast.ident;
+    let output = gen_input_bound_type(ast, &quote!{ '_ });
+    let parse = get_parse_name(attrs);
+    // reject large inputs before parsing, if requested
+    let max_len = get_max_len(attrs);
+    let gen_body = |endianness| {
+        let body = gen_body(endianness);
+        match max_len {
+            Some(ref n) => quote!{
+                if i.len() > (#n) as usize {
+                    return Err(nom::Err::Error(error_position!(i, nom::ErrorKind::TooLarge)));
+                }
+                #body
+            },
+            None => body
+        }
+    };
+    // convert the errors to the custom error type, if requested
+    let nom_err = get_nom_err(attrs);
+    let parse_fn = |extra_args: proc_macro2::TokenStream, body: proc_macro2::TokenStream| {
+        match nom_err {
+            Some(ref e) => quote!{
+                fn #parse(i: &[u8] #args #extra_args) -> IResult<&[u8],#output,#e> {
+                    #[allow(clippy::redundant_closure_call)]
+                    let res : IResult<&[u8],#output> = (|| { #body })();
+                    res.map_err(nom::Convert::convert)
+                }
+            },
+            None => quote!{
+                fn #parse(i: &[u8] #args #extra_args) -> IResult<&[u8],#output> {
+                    #body
+                }
+            }
+        }
+    };
+    let parse_endian =
+        if is_dynamic_endian(attrs) || is_endian_aware(attrs) {
+            let body = gen_body(ParserEndianness::Runtime);
+            quote!{
+                fn parse_endian(i: &[u8] #args, endian: nom::Endianness) -> IResult<&[u8],#output> {
+                    let __endianness = endian;
+                    #body
+                }
+            }
+        } else {
+            quote!{}
+        };
+    if is_dynamic_endian(attrs) {
+        if is_both_endian(attrs) {
+            panic!("Nom-derive: 'DynamicEndian' and 'NomBothEndian' attributes are mutually exclusive");
+        }
+        let parse_fn = parse_fn(quote!{ , endian: nom::Endianness }, quote!{ #name::parse_endian(i #call_args, endian) });
+        return quote!{
+            #parse_endian
+            #parse_fn
+        };
+    }
+    if !is_both_endian(attrs) {
+        let parse_fn = parse_fn(quote!{}, gen_body(endianness));
+        return quote!{
+            #parse_endian
+            #parse_fn
+        };
+    }
+    let body_be = gen_body(ParserEndianness::BigEndian);
+    let body_le = gen_body(ParserEndianness::LittleEndian);
+    let default_call = match endianness {
+        ParserEndianness::LittleEndian => quote!{ #name::parse_le(i #call_args) },
+        ParserEndianness::Native       => quote!{
+            if cfg!(target_endian = "big") { #name::parse_be(i #call_args) } else { #name::parse_le(i #call_args) }
+        },
+        _                              => quote!{ #name::parse_be(i #call_args) },
+    };
+    let parse_fn = parse_fn(quote!{}, default_call);
+    quote!{
+        #parse_endian
+        fn parse_be(i: &[u8] #args) -> IResult<&[u8],#output> {
+            #body_be
+        }
+        fn parse_le(i: &[u8] #args) -> IResult<&[u8],#output> {
+            #body_le
+        }
+        #parse_fn
+    }
+}
@@ -1,9 +1,9 @@
-use proc_macro::TokenStream;
-use syn;
 use syn::export::Span;
 
+use crate::endian::{gen_parse_functions,ParserEndianness};
+use crate::erased::{gen_parse_async,gen_parse_erased};
 use crate::parsertree::ParserTree;
-use crate::structs::{parse_fields,StructParserTree};
+use crate::structs::{parse_variant_fields,StructParserTree};
 
 #[derive(Debug)]
 struct VariantParserTree{
@@ -12,10 +12,10 @@ struct VariantParserTree{
     pub struct_def: StructParserTree,
 }
 
-fn parse_variant(variant: &syn::Variant) -> VariantParserTree {
+fn parse_variant(variant: &syn::Variant, endianness: ParserEndianness) -> VariantParserTree {
     // eprintln!("variant: {:?}", variant);
-    let selector = get_selector(&variant.attrs).expect(&format!("The 'Selector' attribute must be used to give the value of selector item (variant {})", variant.ident));
-    let struct_def = parse_fields(&variant.fields);
+    let selector = get_selector(&variant.attrs).unwrap_or_else(|| panic!("The 'Selector' attribute must be used to give the value of selector item (variant {})", variant.ident));
+    let struct_def = parse_variant_fields(variant, endianness);
     // discriminant ?
     VariantParserTree{
         ident: variant.ident.clone(),
@@ -29,7 +29,7 @@ fn get_selector(attrs: &[syn::Attribute]) -> Option<String> {
         if let Ok(ref meta) = attr.parse_meta() {
             match meta {
                 syn::Meta::NameValue(ref namevalue) => {
-                    if &namevalue.ident == &"Selector" {
+                    if namevalue.ident == "Selector" {
                         match &namevalue.lit {
                             syn::Lit::Str(litstr) => {
                                 return Some(litstr.value())
@@ -39,19 +39,21 @@ fn get_selector(attrs: &[syn::Attribute]) -> Option<String> {
                     }
                 }
                 syn::Meta::List(ref metalist) => {
-                    if &metalist.ident == &"Selector" {
-                        for n in metalist.nested.iter() {
-                            match n {
-                                syn::NestedMeta::Literal(lit) => {
-                                    match lit {
-                                        syn::Lit::Str(litstr) => {
-                                            return Some(litstr.value())
-                                        },
-                                        _ => panic!("unsupported literal type")
-                                    }
-                                },
+                    if metalist.ident == "Selector" {
+                        // several values are alternatives of the same pattern
+                        let values : Vec<_> = metalist.nested.iter()
+                            .map(|n| match n {
+                                syn::NestedMeta::Literal(syn::Lit::Str(litstr)) => litstr.value(),
+                                syn::NestedMeta::Literal(syn::Lit::Int(litint)) => litint.value().to_string(),
+                                syn::NestedMeta::Literal(_) => panic!("unsupported literal type"),
                                 _ => panic!("unsupported meta type")
-                            }
+                            })
+                            .collect();
+                        if values.len() > 1 && values.iter().any(|v| !is_alternative(v)) {
+                            panic!("Nom-derive: a list of selectors cannot contain '_', bitmasks or guards ({})", values.join(", "));
+                        }
+                        if !values.is_empty() {
+                            return Some(values.join(" | "));
                         }
                     }
                 }
@@ -62,14 +64,145 @@ fn get_selector(attrs: &[syn::Attribute]) -> Option<String> {
     None
 }
 
+/// Test if a selector can be an alternative of a pattern (not a default case, a bitmask or
+/// a guard)
+fn is_alternative(selector: &str) -> bool {
+    let tokens : proc_macro2::TokenStream = selector.parse().expect("invalid selector value");
+    !selector.trim_start().starts_with('&') && tokens.into_iter().all(|t| {
+        match t {
+            proc_macro2::TokenTree::Ident(ref id) => id != "_" && id != "if",
+            _ => true
+        }
+    })
+}
+
+/// Build the match pattern of a variant from its selector
+///
+/// Selectors starting with `&` are bitmask tests (for ex. `& 0x80 != 0`), and are expanded
+/// to a guard on the selector value.
+///
+/// Selectors which are constants (for ex. `MAX`, or associated constants like `Kind::DATA`)
+/// are compared to the selector value using `==` in a guard, so the type of the selector
+/// only needs to implement `PartialEq`. This also applies to several constants (`A | B`).
+/// Constants are recognized by their upper case name: a constant used as a pattern already
+/// requires `PartialEq`, so the guard does not change the meaning of the selector.
+///
+/// Other selectors are used as patterns, so they can be enum variants (`Kind::Data`),
+/// ranges (`0x10..=0x1f`), several values (`1 | 2 | 3`), or have a guard (`x if x > 10`).
+///
+/// `sel` is the name of the selector argument of the parse function.
+fn get_selector_pattern(selector: &str, sel: &syn::Ident) -> proc_macro2::TokenStream {
+    let trimmed = selector.trim_start();
+    if trimmed.starts_with('&') {
+        let test : proc_macro2::TokenStream = trimmed.parse().expect("invalid selector mask");
+        return quote!{ _ if #sel #test };
+    }
+    let pattern : proc_macro2::TokenStream = selector.parse().expect("invalid selector value");
+    // several values (`A | B`) are compared one by one if they are all constants
+    let alternatives = split_alternatives(&pattern);
+    if alternatives.iter().all(is_constant) {
+        let sel = ::std::iter::repeat(sel);
+        quote!{ _ if #(#sel == #alternatives)||* }
+    } else {
+        pattern
+    }
+}
+
+/// Split the alternatives of a pattern (`A | B | C`)
+fn split_alternatives(tokens: &proc_macro2::TokenStream) -> Vec<proc_macro2::TokenStream> {
+    let mut alternatives = vec![proc_macro2::TokenStream::new()];
+    for t in tokens.clone() {
+        match t {
+            proc_macro2::TokenTree::Punct(ref p) if p.as_char() == '|' => alternatives.push(proc_macro2::TokenStream::new()),
+            t => alternatives.last_mut().expect("empty alternatives").extend(Some(t)),
+        }
+    }
+    alternatives
+}
+
+/// Test if the tokens are the path of a constant (identifiers separated by `::`, the last
+/// one in upper case, for ex. `Kind::DATA`)
+fn is_constant(tokens: &proc_macro2::TokenStream) -> bool {
+    let mut last = None;
+    for t in tokens.clone() {
+        match t {
+            proc_macro2::TokenTree::Ident(id) => last = Some(id.to_string()),
+            proc_macro2::TokenTree::Punct(ref p) if p.as_char() == ':' => (),
+            _ => return false,
+        }
+    }
+    match last {
+        Some(id) => {
+            id.chars().any(|c| c.is_ascii_uppercase()) &&
+                id.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+        },
+        None => false
+    }
+}
+
+fn is_selector_fn(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomSelectorFn",
+            _ => false
+        }
+    })
+}
+
+/// Test if the tokens of a selector are a single value (not a wildcard, a range, several
+/// values or a guard), so they can also be used as an expression
+fn is_single_value(tokens: &proc_macro2::TokenStream) -> bool {
+    tokens.clone().into_iter().all(|t| {
+        match t {
+            proc_macro2::TokenTree::Ident(ref id) => id != "_" && id != "if",
+            proc_macro2::TokenTree::Punct(ref p) => p.as_char() != '|' && p.as_char() != '.' && p.as_char() != '&',
+            proc_macro2::TokenTree::Group(ref g) => is_single_value(&g.stream()),
+            proc_macro2::TokenTree::Literal(_) => true,
+        }
+    })
+}
+
+/// Generate the `selector` function (if requested), returning the selector value of the
+/// variant of an instance
+///
+/// The selector of each variant must be a single value.
+fn gen_selector_fn(ast: &syn::DeriveInput, selector_type: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    if !is_selector_fn(&ast.attrs) {
+        return quote!{};
+    }
+    let name = &ast.ident;
+    let variants = match ast.data {
+        syn::Data::Enum(ref data_enum) => &data_enum.variants,
+        _ => { panic!("expect enum"); }
+    };
+    let arms : Vec<_> = variants.iter()
+        .map(|v| {
+            let selector = get_selector(&v.attrs).unwrap_or_else(|| panic!("The 'Selector' attribute must be used to give the value of selector item (variant {})", v.ident));
+            let value : proc_macro2::TokenStream = selector.parse().expect("invalid selector value");
+            if !is_single_value(&value) {
+                panic!("Nom-derive: the 'NomSelectorFn' attribute requires a single selector value for each variant (variant {})", v.ident);
+            }
+            let variantname = &v.ident;
+            quote!{ #name::#variantname { .. } => #value, }
+        })
+        .collect();
+    quote!{
+        fn selector(&self) -> #selector_type {
+            match self {
+                #(#arms)*
+            }
+        }
+    }
+}
+
 fn get_repr(attrs: &[syn::Attribute]) -> Option<String> {
     for attr in attrs {
         if let Ok(ref meta) = attr.parse_meta() {
             match meta {
                 syn::Meta::NameValue(_) => (),
                 syn::Meta::List(ref metalist) => {
-                    if &metalist.ident == &"repr" {
-                        for n in metalist.nested.iter() {
+                    if metalist.ident == "repr" {
+                        if let Some(n) = metalist.nested.iter().next() {
                             match n {
                                 syn::NestedMeta::Meta(meta) => {
                                     match meta {
@@ -105,8 +238,23 @@ fn is_input_fieldless_enum(ast: &syn::DeriveInput) -> bool {
     }
 }
 
-fn impl_nom_fieldless_enums(ast: &syn::DeriveInput, repr:String, debug:bool) -> TokenStream {
-    let parser = match repr.as_ref() {
+fn is_varint_tag(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomVarintTag",
+            _ => false
+        }
+    })
+}
+
+/// Generate the body of the parse function of a fieldless enum
+///
+/// If the enum has the `NomVarintTag` attribute, the discriminant is read as a LEB128
+/// varint, and compared to the values of the variants converted to `u64` (negative values
+/// are sign-extended, as in protobuf).
+fn gen_fieldless_enum_parser(ast: &syn::DeriveInput, repr: &str, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    let varint = is_varint_tag(&ast.attrs);
+    let parser = match repr {
         "u8"  |
         "u16" |
         "u32" |
@@ -114,7 +262,9 @@ fn impl_nom_fieldless_enums(ast: &syn::DeriveInput, repr:String, debug:bool) ->
         "i8"  |
         "i16" |
         "i32" |
-        "i64"    => ParserTree::Raw(format!("be_{}", repr)),
+        "i64"    => {
+            if varint { ParserTree::Varint } else { ParserTree::Raw(endianness.primitive_parser(repr)) }
+        },
         _ => panic!("Cannot parse 'repr' content")
     };
     let variant_names : Vec<_> =
@@ -129,90 +279,94 @@ fn impl_nom_fieldless_enums(ast: &syn::DeriveInput, repr:String, debug:bool) ->
             },
             _ => { panic!("expect enum"); }
         };
-    let generics = &ast.generics;
     let name = &ast.ident;
-    let ty = syn::Ident::new(&repr, Span::call_site());
+    let ty = syn::Ident::new(repr, Span::call_site());
     let variants_code : Vec<_> =
         variant_names.iter()
             .map(|variant_name| {
                 let id = syn::Ident::new(variant_name, Span::call_site());
-                quote!{ if selector == #name::#id as #ty { return Some(#name::#id); } }
+                if varint {
+                    quote!{ if selector == #name::#id as #ty as u64 { return Some(#name::#id); } }
+                } else {
+                    quote!{ if selector == #name::#id as #ty { return Some(#name::#id); } }
+                }
             })
             .collect();
-    let tokens = quote!{
-        impl#generics #name#generics {
-            fn parse(i: &[u8]) -> IResult<&[u8],#name> {
-                map_opt!(
-                    i,
-                    #parser,
-                    |selector| {
-                        #(#variants_code)*
-                        None
-                    }
-                )
+    quote!{
+        map_opt!(
+            i,
+            #parser,
+            |selector| {
+                #(#variants_code)*
+                None
             }
+        )
+    }
+}
+
+fn impl_nom_fieldless_enums(ast: &syn::DeriveInput, repr:String, endianness: ParserEndianness, debug:bool) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let name = &ast.ident;
+    let parse_functions = gen_parse_functions(
+        ast, endianness, &quote!{}, &quote!{},
+        |endianness| gen_fieldless_enum_parser(ast, &repr, endianness));
+    let parse_erased = gen_parse_erased(ast, false);
+    let parse_async = gen_parse_async(ast, false);
+    let tokens = quote!{
+        impl#impl_generics #name#ty_generics #where_clause {
+            #parse_functions
+            #parse_erased
+            #parse_async
         }
     };
     if debug {
         eprintln!("impl_nom_enums: {}", tokens);
     }
 
-    tokens.into()
+    tokens
 }
 
-pub(crate) fn impl_nom_enums(ast: &syn::DeriveInput, debug:bool) -> TokenStream {
-    let name = &ast.ident;
-    // eprintln!("{:?}", ast.attrs);
-    let selector = match get_selector(&ast.attrs) { //.expect("The 'Selector' attribute must be used to give the type of selector item");
-        Some(s) => s,
-        None    => {
-            if is_input_fieldless_enum(ast) {
-                // check that we have a repr attribute
-                let repr = get_repr(&ast.attrs).expect("Nom-derive: fieldless enums must have a 'repr' attribute");
-                return impl_nom_fieldless_enums(ast, repr, debug);
-            } else {
-                panic!("Nom-derive: enums must specify the 'selector' attribute");
+/// Get the name of the selector argument of the parse function, given by the
+/// `NomSelectorName` attribute (`selector` by default)
+fn get_selector_name(attrs: &[syn::Attribute]) -> syn::Ident {
+    for attr in attrs {
+        if let Ok(syn::Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "NomSelectorName" {
+                match &namevalue.lit {
+                    syn::Lit::Str(s) => return syn::Ident::new(&s.value(), s.span()),
+                    _ => panic!("Invalid 'NomSelectorName' attribute type/value")
+                }
             }
         }
-    };
+    }
+    syn::Ident::new("selector", Span::call_site())
+}
+
+/// Generate the body of the parse function of an enum with a selector
+fn gen_enum_parser(ast: &syn::DeriveInput, endianness: ParserEndianness, sel: &syn::Ident) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
     let mut variants_defs : Vec<_> =
         match ast.data {
             syn::Data::Enum(ref data_enum) => {
                 // eprintln!("{:?}", data_enum);
                 data_enum.variants.iter()
-                    .map(parse_variant)
+                    .map(|v| parse_variant(v, endianness))
                     .collect()
             },
             _ => { panic!("expect enum"); }
         };
     // parse string items and prepare tokens for each variant
-    let generics = &ast.generics;
-    let selector_type : proc_macro2::TokenStream = selector.parse().unwrap();
     let mut default_case_handled = false;
     let mut variants_code : Vec<_> = {
         variants_defs.iter()
             .map(|def| {
                 if def.selector == "_" { default_case_handled = true; }
-                let m : proc_macro2::TokenStream = def.selector.parse().expect("invalid selector value");
+                let m = get_selector_pattern(&def.selector, sel);
                 let variantname = &def.ident;
-                let (idents,parser_tokens) : (Vec<_>,Vec<_>) = def.struct_def.parsers.iter()
-                    .map(|(name,parser)| {
-                        let id = syn::Ident::new(name, Span::call_site());
-                        (id,parser)
-                    })
-                    .unzip();
-                let idents2 = idents.clone();
-                let struct_def = match def.struct_def.unnamed {
-                    false => quote!{ ( #name::#variantname { #(#idents2),* } ) },
-                    true  => quote!{ ( #name::#variantname ( #(#idents2),* ) ) },
-                };
+                let do_parse = def.struct_def.gen_do_parse(&quote!{ #name::#variantname });
                 quote!{
                     #m => {
-                        do_parse!{
-                            i,
-                            #(#idents: #parser_tokens >>)*
-                            #struct_def
-                        }
+                        #do_parse
                         // Err(nom::Err::Error(error_position!(i, nom::ErrorKind::Switch)))
                     },
                 }
@@ -234,20 +388,135 @@ pub(crate) fn impl_nom_enums(ast: &syn::DeriveInput, debug:bool) -> TokenStream
     let default_case =
         if default_case_handled { quote!{} }
         else { quote!{ _ => Err(nom::Err::Error(error_position!(i, nom::ErrorKind::Switch))) } };
-    let tokens = quote!{
-        impl#generics #name#generics {
-            fn parse(i: &[u8], selector: #selector_type) -> IResult<&[u8],#name> {
-                match selector {
-                    #(#variants_code)*
-                    #default_case
+    quote!{
+        // length of the input given to the enum parser, usable in field attributes
+        let __rest_len = i.len();
+        match #sel {
+            #(#variants_code)*
+            #default_case
+        }
+    }
+}
+
+fn is_alt(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomAlt",
+            _ => false
+        }
+    })
+}
+
+/// Generate the body of the parse function of an enum without selector (`NomAlt`)
+///
+/// Variants are tried in order, and the first one which can be parsed is returned. The
+/// variant with the `Selector("_")` attribute, if any, is the fallback: it is tried last.
+/// Only errors cause the next variant to be tried, incomplete input and failures are
+/// returned immediately.
+fn gen_alt_enum_parser(ast: &syn::DeriveInput, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let variants = match ast.data {
+        syn::Data::Enum(ref data_enum) => &data_enum.variants,
+        _ => { panic!("expect enum"); }
+    };
+    let mut fallback = None;
+    let mut variants_code = Vec::new();
+    for v in variants.iter() {
+        let variantname = &v.ident;
+        let do_parse = parse_variant_fields(v, endianness).gen_do_parse(&quote!{ #name::#variantname });
+        match get_selector(&v.attrs) {
+            None => {
+                variants_code.push(quote!{
+                    match #do_parse {
+                        Err(nom::Err::Error(_)) => (),
+                        r => return r,
+                    }
+                });
+            },
+            Some(ref s) if s.trim() == "_" => {
+                if fallback.is_some() {
+                    panic!("Nom-derive: only one variant can be the fallback of a 'NomAlt' enum");
                 }
+                fallback = Some(do_parse);
+            },
+            Some(_) => panic!("Nom-derive: the variants of a 'NomAlt' enum cannot have a selector (except the '_' fallback)"),
+        }
+    }
+    let fallback = fallback.unwrap_or_else(|| quote!{ Err(nom::Err::Error(error_position!(i, nom::ErrorKind::Alt))) });
+    quote!{
+        // length of the input given to the enum parser, usable in field attributes
+        let __rest_len = i.len();
+        #(#variants_code)*
+        #fallback
+    }
+}
+
+fn impl_nom_alt_enums(ast: &syn::DeriveInput, endianness: ParserEndianness, debug:bool) -> proc_macro2::TokenStream {
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let name = &ast.ident;
+    let parse_functions = gen_parse_functions(
+        ast, endianness, &quote!{}, &quote!{},
+        |endianness| gen_alt_enum_parser(ast, endianness));
+    let parse_erased = gen_parse_erased(ast, false);
+    let parse_async = gen_parse_async(ast, false);
+    let tokens = quote!{
+        impl#impl_generics #name#ty_generics #where_clause {
+            #parse_functions
+            #parse_erased
+            #parse_async
+        }
+    };
+    if debug {
+        eprintln!("impl_nom_enums: {}", tokens);
+    }
+
+    tokens
+}
+
+pub(crate) fn impl_nom_enums(ast: &syn::DeriveInput, endianness: ParserEndianness, debug:bool) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    // eprintln!("{:?}", ast.attrs);
+    if is_alt(&ast.attrs) {
+        if get_selector(&ast.attrs).is_some() {
+            panic!("Nom-derive: 'NomAlt' and 'Selector' attributes are mutually exclusive");
+        }
+        return impl_nom_alt_enums(ast, endianness, debug);
+    }
+    let selector = match get_selector(&ast.attrs) { //.expect("The 'Selector' attribute must be used to give the type of selector item");
+        Some(s) => s,
+        None    => {
+            if is_input_fieldless_enum(ast) {
+                // check that we have a repr attribute
+                let repr = get_repr(&ast.attrs).expect("Nom-derive: fieldless enums must have a 'repr' attribute");
+                return impl_nom_fieldless_enums(ast, repr, endianness, debug);
+            } else {
+                panic!("Nom-derive: enums must specify the 'selector' attribute");
             }
         }
     };
+    // generate code
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let selector_type : proc_macro2::TokenStream = selector.parse().unwrap();
+    let sel = get_selector_name(&ast.attrs);
+    let parse_functions = gen_parse_functions(
+        ast, endianness,
+        &quote!{ , #sel: #selector_type }, &quote!{ , #sel },
+        |endianness| gen_enum_parser(ast, endianness, &sel));
+    let parse_erased = gen_parse_erased(ast, true);
+    let parse_async = gen_parse_async(ast, true);
+    let selector_fn = gen_selector_fn(ast, &selector_type);
+    let tokens = quote!{
+        impl#impl_generics #name#ty_generics #where_clause {
+            #parse_functions
+            #parse_erased
+            #parse_async
+            #selector_fn
+        }
+    };
 
     if debug {
         eprintln!("impl_nom_enums: {}", tokens);
     }
 
-    tokens.into()
+    tokens
 }
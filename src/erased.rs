@@ -0,0 +1,88 @@
+use crate::endian::{gen_input_bound_type,get_nom_err,get_parse_name,is_dynamic_endian};
+
+/// Get the value of a wrapper attribute (`ParseErased` or `NomAsync`): `Some(None)` if the
+/// attribute is set without a value, `Some(Some(args))` if it is set with the default
+/// arguments of `parse`
+fn get_wrapper_attribute(attrs: &[syn::Attribute], name: &str) -> Option<Option<String>> {
+    for attr in attrs {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) if ident == name => return Some(None),
+            Ok(syn::Meta::NameValue(ref namevalue)) if namevalue.ident == name => {
+                match &namevalue.lit {
+                    syn::Lit::Str(s) => return Some(Some(s.value())),
+                    _ => panic!("Invalid '{}' attribute type/value", name)
+                }
+            },
+            _ => ()
+        }
+    }
+    None
+}
+
+/// Generate the call to `parse` from a wrapper function (if the wrapper attribute is set),
+/// using the default arguments given by the attribute.
+///
+/// `has_args` is true if the `parse` function takes arguments after the input (for ex. a
+/// selector, or the endianness), in which case the attribute must give their default value.
+fn gen_wrapped_call(attrs: &[syn::Attribute], attr_name: &str, name: &syn::Ident, input: proc_macro2::TokenStream, has_args: bool) -> Option<proc_macro2::TokenStream> {
+    // the endianness is an argument of `parse`
+    let has_args = has_args || is_dynamic_endian(attrs);
+    let parse = get_parse_name(attrs);
+    let call = match (get_wrapper_attribute(attrs, attr_name), has_args) {
+        (None, _)                => return None,
+        (Some(None), false)      => quote!{ #name::#parse(#input) },
+        (Some(Some(args)), true) => {
+            let args : proc_macro2::TokenStream = args.parse().unwrap_or_else(|_| panic!("invalid '{}' arguments", attr_name));
+            quote!{ #name::#parse(#input, #args) }
+        },
+        (Some(None), true)       => panic!("Nom-derive: the '{}' attribute must give the default selector (for ex. #[{}=\"0\"])", attr_name, attr_name),
+        (Some(Some(_)), false)   => panic!("Nom-derive: the '{}' attribute does not take a value for this type", attr_name),
+    };
+    if get_nom_err(attrs).is_some() {
+        panic!("Nom-derive: the '{}' and 'NomErr' attributes are mutually exclusive", attr_name);
+    }
+    Some(call)
+}
+
+/// Generate the `parse_erased` function (if requested), with the same signature for all
+/// types, so it can be stored as a function pointer.
+///
+/// `has_args` is true if the `parse` function takes arguments after the input (for ex. a
+/// selector, or the endianness), in which case the `ParseErased` attribute must give their default value.
+pub(crate) fn gen_parse_erased(ast: &syn::DeriveInput, has_args: bool) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let call = match gen_wrapped_call(&ast.attrs, "ParseErased", name, quote!{ i }, has_args) {
+        Some(call) => call,
+        None       => return quote!{}
+    };
+    let output = gen_input_bound_type(ast, &quote!{ '_ });
+    quote!{
+        fn parse_erased(i: &[u8]) -> IResult<&[u8],#output> {
+            #call
+        }
+    }
+}
+
+/// Generate the `parse_async` function (if requested), taking and returning owned values,
+/// so the future does not borrow the input and can cross `.await` points.
+///
+/// The arguments are the same as for `gen_parse_erased`.
+pub(crate) fn gen_parse_async(ast: &syn::DeriveInput, has_args: bool) -> proc_macro2::TokenStream {
+    let name = &ast.ident;
+    let call = match gen_wrapped_call(&ast.attrs, "NomAsync", name, quote!{ &i }, has_args) {
+        Some(call) => call,
+        None       => return quote!{}
+    };
+    if ast.generics.lifetimes().next().is_some() {
+        panic!("Nom-derive: the 'NomAsync' attribute cannot be used on types with lifetimes (the result must be owned)");
+    }
+    let output = gen_input_bound_type(ast, &quote!{ '_ });
+    quote!{
+        async fn parse_async(i: Vec<u8>) -> Result<(Vec<u8>,#output),nom::Err<Vec<u8>>> {
+            match #call {
+                Ok((rem, o)) => Ok((rem.to_vec(), o)),
+                Err(e)       => Err(nom::Convert::convert(e)),
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use syn::export::Span;
+
+use crate::endian::{get_local_endianness,ParserEndianness};
+
+/// Test if the `NomUnsafeFastPath` attribute is present
+pub(crate) fn is_unsafe_fast_path(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomUnsafeFastPath",
+            _ => false
+        }
+    })
+}
+
+/// Size of a primitive type, if supported by the fast path
+fn get_primitive_size(ty: &syn::Type) -> Option<(String, usize)> {
+    let ty_s = quote!{ #ty }.to_string();
+    let size = match ty_s.as_ref() {
+        "u8" | "i8"     => 1,
+        "u16" | "i16"   => 2,
+        "u32" | "i32" | "f32" => 4,
+        "u64" | "i64" | "f64" => 8,
+        "u128" | "i128" => 16,
+        _ => return None
+    };
+    Some((ty_s, size))
+}
+
+/// Test if an attribute of a field is allowed by the fast path (documentation, or byte order)
+fn is_allowed_attribute(attr: &syn::Attribute) -> bool {
+    attr.path.is_ident("doc") ||
+        attr.path.is_ident("BigEndian") ||
+        attr.path.is_ident("LittleEndian") ||
+        attr.path.is_ident("NativeEndian")
+}
+
+/// Generate the body of the parse function of a structure with the `NomUnsafeFastPath`
+/// attribute
+///
+/// All fields must be primitive types (integers or floats), so the structure has a fixed
+/// size. The length of the input is checked once, then the values are read without bounds
+/// checks.
+pub(crate) fn gen_fast_path(ast: &syn::DeriveInput, s: &syn::DataStruct, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    let object_attrs = ["Tag", "Magic", "NomByteOrderMark", "NomVersionField"];
+    if ast.attrs.iter().any(|attr| object_attrs.iter().any(|a| attr.path.is_ident(a))) {
+        panic!("Nom-derive: the 'NomUnsafeFastPath' attribute cannot be used with the 'Tag', 'Magic', 'NomByteOrderMark' or 'NomVersionField' attributes");
+    }
+    let name = &ast.ident;
+    let mut offset = 0;
+    let mut reads = Vec::new();
+    let mut idents = Vec::new();
+    for (idx, field) in s.fields.iter().enumerate() {
+        let (ty, size) = match get_primitive_size(&field.ty) {
+            Some(t) if field.attrs.iter().all(is_allowed_attribute) => t,
+            _ => panic!("Nom-derive: the 'NomUnsafeFastPath' attribute requires all fields to be integers or floats, without attributes (except the byte order)")
+        };
+        let ty = syn::Ident::new(&ty, Span::call_site());
+        let id = match field.ident {
+            Some(ref id) => id.clone(),
+            None => syn::Ident::new(&format!("_{}", idx), Span::call_site())
+        };
+        let ptr = if offset == 0 { quote!{ __input.as_ptr() } } else { quote!{ __input.as_ptr().add(#offset) } };
+        let from_bytes = match get_local_endianness(&field.attrs, endianness) {
+            ParserEndianness::BigEndian    => quote!{ #ty::from_be_bytes(b) },
+            ParserEndianness::LittleEndian => quote!{ #ty::from_le_bytes(b) },
+            ParserEndianness::Native       => quote!{ #ty::from_ne_bytes(b) },
+            ParserEndianness::Runtime      => quote!{
+                if __endianness == nom::Endianness::Big { #ty::from_be_bytes(b) } else { #ty::from_le_bytes(b) }
+            },
+        };
+        reads.push(quote!{
+            // the length of the input was checked, and arrays of bytes have no alignment
+            let #id = {
+                let b = unsafe { ::std::ptr::read_unaligned(#ptr as *const [u8; #size]) };
+                #from_bytes
+            };
+        });
+        idents.push(id);
+        offset += size;
+    }
+    let struct_def = match s.fields {
+        syn::Fields::Named(_) => quote!{ #name{ #(#idents),* } },
+        _ => quote!{ #name( #(#idents),* ) },
+    };
+    quote!{
+        if i.len() < #offset {
+            return Err(nom::Err::Incomplete(nom::Needed::Size(#offset)));
+        }
+        // the variables of the fields may shadow `i`
+        let __input = i;
+        #(#reads)*
+        Ok((&__input[#offset..], #struct_def))
+    }
+}
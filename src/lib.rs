@@ -20,20 +20,38 @@ extern crate quote;
 
 use proc_macro::TokenStream;
 use syn::*;
-use syn::export::Span;
 
 
+mod endian;
+mod erased;
 mod parsertree;
+mod resumable;
+mod columnar;
+mod checksum;
+mod trace;
+mod fastpath;
+mod context;
 mod structs;
 mod enums;
 
-use structs::parse_struct;
+use endian::{gen_parse_functions,get_object_endianness,ParserEndianness};
+use structs::{gen_group_label,parse_struct};
 use enums::impl_nom_enums;
+use erased::{gen_parse_async,gen_parse_erased};
+use resumable::gen_parse_resumable;
+use columnar::gen_parse_columnar;
+use trace::{gen_parse_trace,gen_parse_traced};
+use fastpath::{gen_fast_path,is_unsafe_fast_path};
+use context::{gen_default_context_call,gen_parse_with_context};
 
 /// The `Nom` derive automatically generates a `parse` function for the structure
 /// using [nom] parsers. It will try to infer parsers for primitive of known
 /// types, but also allows you to specify parsers using custom attributes.
 ///
+/// The custom attributes can be mixed with other attributes (doc comments, `cfg`,
+/// `cfg_attr`, lints, or the attributes of other derives) in any order: the other attributes
+/// are ignored. Fields and variants removed by `cfg` are not parsed.
+///
 /// [nom]: https://github.com/Geal/nom
 ///
 /// # Deriving parsers for `Struct`
@@ -77,6 +95,132 @@ use enums::impl_nom_enums;
 /// ```
 ///
 /// By default, integers are parsed are Big Endian.
+/// All integer types from `u8` to `u128` (and `i8` to `i128`) are supported.
+///
+/// Floating-point types (`f32` and `f64`) are also supported, using the IEEE-754 parsers
+/// (`be_f32` and `be_f64` by default).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct Measure {
+///   temperature: f32,
+///   #[LittleEndian]
+///   pressure: f32,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x41\xb4\x00\x00\x00\x00\x7d\x44";
+/// let res = Measure::parse(input);
+/// assert_eq!(res, Ok((&input[8..],Measure{temperature:22.5, pressure:1012.0})));
+/// # }
+/// ```
+///
+/// A `bool` is read from a single byte: 0 is `false`, and any other value is `true`. With the
+/// `Strict` attribute, values other than 0 and 1 are rejected (`ErrorKind::Verify`). This also
+/// applies to the `bool` items of containers, like `Option<bool>` or `Vec<bool>`.
+///
+/// The `BoolWidth` attribute reads the value from a larger unsigned integer instead (for ex.
+/// `#[BoolWidth="u32"]`), using the byte order of the field.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct Flags {
+///   enabled: bool,
+///   #[Strict]
+///   visible: bool,
+///   #[BoolWidth="u32"]
+///   locked: bool,
+/// }
+/// #
+/// # fn main() {
+/// let res = Flags::parse(b"\x02\x01\x00\x00\x00\x00");
+/// assert_eq!(res, Ok((&b""[..],Flags{enabled:true, visible:true, locked:false})));
+/// assert!(Flags::parse(b"\x01\x02\x00\x00\x00\x00").is_err());
+/// # }
+/// ```
+///
+/// A `char` is read from a `u32` code point, and the parser fails (`ErrorKind::MapOpt`) if it
+/// is not a valid `char`. With the `Utf8` attribute, one UTF-8 encoded character (1 to 4
+/// bytes) is read instead, and the parser fails (`ErrorKind::Char`) if the encoding is
+/// invalid.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   a: char,
+///   #[Utf8]
+///   b: char,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x41\xc3\xa9";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{a:'A', b:'\u{e9}'})));
+/// # }
+/// ```
+///
+/// The non-zero integer types of `std::num` (`NonZeroU8` to `NonZeroU128`, and `NonZeroI8`
+/// to `NonZeroI128`) are also supported. The types must be imported, and the parser fails
+/// (`ErrorKind::MapOpt`) if the value is zero.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// use std::num::{NonZeroU16, NonZeroU32};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   id: NonZeroU32,
+///   #[LittleEndian]
+///   port: NonZeroU16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x07\x50\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{id:NonZeroU32::new(7).unwrap(), port:NonZeroU16::new(80).unwrap()})));
+/// let input = b"\x00\x00\x00\x00\x50\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::MapOpt))));
+/// # }
+/// ```
+///
+/// `usize` and `isize` have no fixed size, so fields of these types require the `Width`
+/// attribute, giving the number of bytes of the stored integer (1, 2, 4 or 8). The value is
+/// converted, and the parser fails (`ErrorKind::MapRes`) if it does not fit.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   #[Width=4]
+///   len: usize,
+///   #[Width=2]
+///   offset: isize,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x01\x00\xff\xfe";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{len:256, offset:-2})));
+/// # }
+/// ```
 ///
 /// `nom-derive` is also able to derive default parsers for some usual types:
 ///
@@ -128,12 +272,24 @@ use enums::impl_nom_enums;
 ///
 /// Notes:
 ///   - the subparser is inferred as usual (item type must be `Vec< ... >`)
-///   - the number of items (`n`) can be any expression, and will be cast to `usize`
+///   - the number of items (`n`) can be any expression, and will be cast to `usize`. An
+///     integer literal can also be used (`#[Count=16]`). The expression is evaluated in its
+///     own block before the cast, so operators and method calls do not need parentheses
+///     (for ex. `#[Count="a * 2 + b"]`, or `#[Count="a.checked_sub(1).unwrap_or(0)"]` to
+///     avoid overflows). The arithmetic uses the type of the fields, so cast them first if
+///     the result may not fit (`#[Count="a as usize * 4"]`)
+///   - the expression can also be written without quotes, between parentheses (for ex.
+///     `#[Count(a * 2 + 1)]`)
+///   - the expression can use the previous fields. For ex., `#[Count="names.len()"]` parses
+///     one item per element of a previous `Vec` field: it is only borrowed, so it can still
+///     be stored in the structure
+///   - if an item cannot be parsed, its error is returned unchanged: the error position is the
+///     input of the failing item (not the start of the `Vec`)
 ///
 /// For ex:
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,call,count,be_u16};
+/// # use nom::{do_parse,IResult,call,map,be_u16};
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
@@ -150,187 +306,201 @@ use enums::impl_nom_enums;
 /// # }
 /// ```
 ///
-/// ## Default parsing function
+/// ## Array types
 ///
-/// If a field with type `T` is not a primitive or known type, the generated parser is
-/// `call!(T::parse)`.
+/// If a field is an array `[T; N]`, `N` items are parsed (like `Count`), and the
+/// result is converted to an array. The parser fails if there are less than `N` items.
+/// The parser of the items is inferred like for `Vec<T>`, so `T` can be a primitive type
+/// (read using the byte order of the field), a structure deriving `Nom`, or an array.
 ///
-/// This function can be automatically derived, or specified as a method for the struct.
-/// In that case, the function must be a static method with the same API as a
-/// [nom] combinator, returning the wrapped struct when parsing succeeds.
+/// Byte arrays `[u8; N]` (for ex. a hash, or a magic number) are read using `take!(N)`, and
+/// copied to the array. If less than `N` bytes remain, the parser returns `Incomplete`.
 ///
-/// Example (using `Nom` derive):
+/// For ex:
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,call,be_u16};
+/// # use nom::{do_parse,IResult,call,map,map_res,take,be_u16};
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// struct S2 {
-///   c: u16
-/// }
-///
-/// # #[derive(Debug,PartialEq)] // for assert_eq!
-/// #[derive(Nom)]
 /// struct S {
-///   a: u16,
-///   b: S2
+///   a: [u8; 4],
+///   b: [u16; 2],
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x00\x00\x00\x01";
-/// # let res = S::parse(input);
-/// # assert_eq!(res, Ok((&input[4..],S{a:0,b:S2{c:1}})));
+/// let input = b"\x01\x02\x03\x04\x00\x05\x00\x06";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[8..],S{a:[1,2,3,4], b:[5,6]})));
 /// # }
 /// ```
 ///
-/// Example (defining `parse` method):
+/// Note: the items (other than bytes) are parsed into a temporary `Vec`, which is then
+/// converted to the array. Filling the array item by item would require `unsafe` code (to
+/// write to an uninitialized array), a `Default` or `Copy` bound on `T`, or `<[T; N]>::map`
+/// (which requires a much newer compiler than this crate), so the `Vec` cannot
+/// be avoided without restricting the item types.
+///
+/// ## Tuple types
+///
+/// If a field is a tuple `(A, B, ...)`, the elements are parsed in order (`tuple!`), each
+/// using the parser inferred from its type and the byte order of the field. Elements can be
+/// primitive types, structures deriving `Nom`, arrays, `Vec` or other tuples.
+///
+/// A `Count` attribute on a tuple field gives the number of items of the list in the tuple,
+/// which must contain exactly one `Vec` element. For a list of tuples, use a `Vec<(A, B)>`
+/// field instead.
+///
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,call,map,be_u16,le_u16};
+/// # use nom::*;
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
-/// // no Nom derive
-/// struct S2 {
-///   c: u16
-/// }
-///
-/// impl S2 {
-///     fn parse(i:&[u8]) -> IResult<&[u8],S2> {
-///         map!(
-///             i,
-///             call!(le_u16), // little-endian
-///             |c| S2{c} // return a struct S2
-///             )
-///     }
-/// }
-///
-/// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
 /// struct S {
-///   a: u16,
-///   b: S2
+///   a: (u8, u16),
+///   #[Count="2"]
+///   b: (u8, Vec<u8>),
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x00\x00\x00\x01";
-/// # let res = S::parse(input);
-/// # assert_eq!(res, Ok((&input[4..],S{a:0,b:S2{c:256}})));
+/// let input = b"\x01\x00\x02\x03\x04\x05";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{a:(1,2), b:(3,vec![4,5])})));
 /// # }
 /// ```
 ///
-/// ## Specifying parsers
-///
-/// Sometimes, the default parsers generated automatically are not those you
-/// want.
+/// ## Box, Rc and Arc types
 ///
-/// The `Parse` custom attribute allows for specifying the parser, using code that
-/// will be inserted in the `do_parse` block of the nom parser.
+/// If a field is a `Box<T>`, `Rc<T>` or `Arc<T>`, the parser of `T` is inferred as usual (a
+/// primitive type, a structure deriving `Nom`, an `Option`, a `Vec`, etc.), and the result is
+/// moved to the heap using `Box::new`, `Rc::new` or `Arc::new`. The attributes of the field
+/// (for ex. `Count` for an `Arc<Vec<u16>>`) apply to the inner type, unless the parser of the
+/// whole field is given by `Parse` or `ParseOuter`. This allows recursive structures:
 ///
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,call,le_u16};
+/// # use nom::*;
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// struct S{
-///     #[Parse="le_u16"]
-///     a: u16
+/// struct List {
+///   value: u8,
+///   #[Cond="value != 0"]
+///   next: Option<Box<List>>,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x00\x01";
-/// # let res = S::parse(input);
-/// # assert_eq!(res, Ok((&input[2..],S{a:256})));
+/// let input = b"\x01\x00";
+/// let res = List::parse(input);
+/// assert_eq!(res, Ok((&input[2..],List{value:1, next:Some(Box::new(List{value:0, next:None}))})));
 /// # }
 /// ```
 ///
-/// The `Parse` argument can be a complex expression:
+/// ## Generic types
+///
+/// Structures and enums can have lifetimes and type parameters, with bounds and `where`
+/// clauses. The generated functions return the type with its type parameters, and with its
+/// lifetimes bound to the input (for ex. `IResult<&[u8],S<'_,T>>`). The parser of a field
+/// whose type is a type parameter `T` is `T::parse`, so `T` must be bound by a trait
+/// providing this function. Since the derived parsers are inherent functions, the trait
+/// must be defined (and implemented) in the crate:
+///
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,be_u8,be_u16,call,cond};
+/// # use nom::*;
 /// #
+/// trait SubParse: Sized {
+///     fn parse(i: &[u8]) -> IResult<&[u8],Self>;
+/// }
+///
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// struct S{
-///     pub a: u8,
-///     #[Parse="cond!(a > 0,be_u16)"]
-///     pub b: Option<u16>,
+/// struct Point {
+///   x: u8,
+///   y: u8,
+/// }
+///
+/// impl SubParse for Point {
+///     fn parse(i: &[u8]) -> IResult<&[u8],Self> {
+///         Point::parse(i)
+///     }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct Pair<T> where T: SubParse {
+///   a: T,
+///   b: T,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x01\x00\x01";
-/// # let res = S::parse(input);
-/// # assert_eq!(res, Ok((&input[3..],S{a:1,b:Some(1)})));
+/// let input = b"\x01\x02\x03\x04";
+/// let res = Pair::<Point>::parse(input);
+/// assert_eq!(res, Ok((&input[4..],Pair{a:Point{x:1,y:2}, b:Point{x:3,y:4}})));
 /// # }
 /// ```
-/// Note that you are responsible from providing correct code.
-///
-/// ## Adding conditions
 ///
-/// The `Cond` custom attribute allows for specifying a condition.
-/// The generated parser will use the `cond!` combinator, which calls the
-/// child parser only if the condition is met.
-/// The type with this attribute must be an `Option` type.
+/// Marker fields of type `PhantomData<T>` (also written `std::marker::PhantomData<T>`)
+/// consume no input, and do not require any bound on `T`:
 ///
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,cond,complete,be_u8,be_u16,call};
+/// # use nom::*;
+/// # use std::marker::PhantomData;
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// struct S{
-///     pub a: u8,
-///     #[Cond="a == 1"]
-///     pub b: Option<u16>,
+/// struct S<T> {
+///   len: u32,
+///   _marker: PhantomData<T>,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x01\x00\x01";
-/// # let res = S::parse(input);
-/// # assert_eq!(res, Ok((&input[3..],S{a:1,b:Some(1)})));
+/// let input = b"\x00\x00\x00\x10";
+/// let res = S::<String>::parse(input);
+/// assert_eq!(res, Ok((&input[4..],S{len:16, _marker:PhantomData})));
 /// # }
 /// ```
 ///
-/// ## Adding verifications
+/// ## Map types
 ///
-/// The `Verify` custom attribute allows for specifying a verifying function.
-/// The generated parser will use the `verify!` combinator, which calls the
-/// child parser only if is verifies a condition (and otherwise raises an error).
+/// A `HashMap<K,V>` or `BTreeMap<K,V>` field must have a `Count` attribute, giving the
+/// number of entries.
+/// Each entry is parsed as a key followed by a value (`pair!(K::parse, V::parse)`), and
+/// the entries are collected into the map. The parsers of keys and values are inferred
+/// from their types.
 ///
 /// ```rust
 /// # use nom_derive::Nom;
-/// # use nom::{do_parse,IResult,verify,complete,be_u8,be_u16,call};
+/// # use nom::*;
+/// # use std::collections::HashMap;
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// struct S{
-///     #[Verify="a == 1"]
-///     pub a: u8,
+/// struct S {
+///   n: u8,
+///   #[Count="n"]
+///   entries: HashMap<u8,u16>,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x01";
-/// # let res = S::parse(input);
-/// # assert_eq!(res, Ok((&input[1..],S{a:1})));
+/// let input = b"\x02\x01\x00\x10\x02\x00\x20";
+/// let (_, s) = S::parse(input).expect("parsing failed");
+/// assert_eq!(s.entries.get(&2), Some(&0x20));
 /// # }
 /// ```
 ///
-/// ## Known problems
-///
-/// The generated parsers use the [nom] combinators directly, so they must be
-/// visible in the current namespace (*i.e* imported in a `use` statement).
-///
-/// # Deriving parsers for `Enum`
+/// ## Folding items
 ///
-/// The `Nom` attribute can also used to generate parser for `Enum` types.
-/// The generated parser will used a value (called *selector*) to determine
-/// which attribute variant is parsed.
-/// Named and unnamed enums are supported.
+/// To keep only an aggregate of a list of items (for ex. a sum), the `Fold` attribute can
+/// be set on a field with the type of the accumulator. Its value is the initial value and
+/// the function combining the accumulator and an item: `#[Fold="init, |acc, item| ..."]`.
+/// The parser of items is given by the `Parse` attribute.
 ///
-/// In addition of `derive(Nom)`, a `Selector` attribute must be used:
-///   - on the structure, to specify the type of selector to match
-///   - on each variant, to specify the value associated with this variant.
+/// Items are parsed until the parser fails (using `fold_many0!`), or exactly `n` items are
+/// parsed if a `Count="n"` attribute is also present (using `fold_many_m_n!`). No `Vec`
+/// is allocated.
 ///
 /// ```rust
 /// # use nom_derive::Nom;
@@ -338,72 +508,69 @@ use enums::impl_nom_enums;
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// #[Selector="u8"]
-/// pub enum U1{
-///     #[Selector("0")] Field1(u32),
-///     #[Selector("1")] Field2(Option<u32>),
+/// struct S {
+///   n: u8,
+///   #[Parse="be_u16"]
+///   #[Count="n"]
+///   #[Fold="0, |acc: u32, item: u16| acc + u32::from(item)"]
+///   sum: u32,
+///   #[Parse="be_u8"]
+///   #[Fold="0, |acc, item| std::cmp::max(acc, item)"]
+///   max: u8,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x00\x00\x00\x02";
-/// # let res = U1::parse(input, 0);
-/// # assert_eq!(res, Ok((&input[4..],U1::Field1(2))));
+/// let input = b"\x02\xff\xff\x00\x02\x03\x07\x05";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[8..],S{n:2, sum:0x10001, max:7})));
 /// # }
 /// ```
 ///
-/// The generated function will look like:
-///
-/// <pre>
-/// impl U1{
-///     pub fn parse(i:&[u8), selector: u8) -> IResult<&[u8],U1> {
-///         match selector {
-///             ...
-///         }
-///     }
-/// }
-/// </pre>
-///
-/// It can be called either directly (`U1::parse(n)`) or using nom
-/// (`call!(U1::parse,n)`).
+/// ## Columnar data
 ///
-/// The selector can be a primitive type (`u8`), or any other type implementing the `PartialEq`
-/// trait.
+/// Some formats store a list of items by columns: the first field of all items, then the
+/// second field of all items, etc. The `Columnar` attribute on a structure generates a
+/// `parse_columnar(i, count)` function, parsing `count` items stored this way and returning
+/// them as a `Vec`. A field with this type can then use the `Columnar(count="n")` attribute.
 ///
 /// ```rust
 /// # use nom_derive::Nom;
 /// # use nom::*;
 /// #
-/// #[derive(Debug,PartialEq,Eq,Clone,Copy,Nom)]
-/// pub struct MessageType(pub u8);
-///
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// #[Selector="MessageType"]
-/// pub enum U1{
-///     #[Selector("MessageType(0)")] Field1(u32),
-///     #[Selector("MessageType(1)")] Field2(Option<u32>),
+/// #[Columnar]
+/// struct Point {
+///   x: u8,
+///   y: u16,
 /// }
 ///
-/// // Example of call from a struct:
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// pub struct S1{
-///     pub msg_type: MessageType,
-///     #[Parse="call!(U1::parse,msg_type)"]
-///     pub msg_value: U1
+/// struct S {
+///   n: u8,
+///   #[Columnar(count="n")]
+///   points: Vec<Point>,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x00\x00\x00\x02";
-/// # let res = U1::parse(input, MessageType(0));
-/// # assert_eq!(res, Ok((&input[4..],U1::Field1(2))));
+/// let input = b"\x02\x01\x02\x00\x03\x00\x04";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[7..],S{n:2, points:vec![Point{x:1,y:3}, Point{x:2,y:4}]})));
 /// # }
 /// ```
 ///
-/// ## Default case
+/// Since columns are parsed one after the other, the attributes of the fields of the item
+/// cannot refer to the other fields. The `Skip`, `Tag` (if the bytes are not stored),
+/// `BoundingLength`, `SetEndian`, `EndianFrom` and `NomByteOrderMark` attributes are not
+/// supported.
 ///
-/// By default, if no value of the selector matches the input value, a nom error
-/// `ErrorKind::Switch` is raised. This can be changed by using `_` as selector
-/// value for one the variants.
+/// ## Run-length encoded data
+///
+/// The `RunLength` attribute on a `Vec<u8>` field reads (count, value) byte pairs, each pair
+/// expanding to `count` copies of `value`. By default, the data ends with a zero count byte.
+/// With `RunLength="len"`, pairs are read until the expanded data has `len` bytes (a run
+/// exceeding this length is an error).
 ///
 /// ```rust
 /// # use nom_derive::Nom;
@@ -411,23 +578,2133 @@ use enums::impl_nom_enums;
 /// #
 /// # #[derive(Debug,PartialEq)] // for assert_eq!
 /// #[derive(Nom)]
-/// #[Selector="u8"]
-/// pub enum U2{
-///     #[Selector("0")] Field1(u32),
-///     #[Selector("_")] Field2(u32),
+/// struct S {
+///   #[RunLength]
+///   a: Vec<u8>,
+///   len: u8,
+///   #[RunLength="len"]
+///   b: Vec<u8>,
 /// }
 /// #
 /// # fn main() {
-/// # let input = b"\x00\x00\x00\x02";
-/// # let res = U2::parse(input, 123);
-/// # assert_eq!(res, Ok((&input[4..],U2::Field2(2))));
+/// let input = b"\x03\xff\x01\x00\x00\x04\x02\x01\x02\x02";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[10..],S{a:vec![0xff,0xff,0xff,0], len:4, b:vec![1,1,2,2]})));
 /// # }
 /// ```
 ///
-/// If the `_` selector is not the last variant, the generated code will use it
-/// as the last match to avoid unreachable code.
+/// ## String types
 ///
-/// ## Special case: specifying parsers for fields
+/// `String` fields are parsed as a length, followed by the UTF-8 bytes of the string.
+/// The length is a `u32` by default, and can be changed using the `LengthType` attribute
+/// (`u8`, `u16`, `u32` or `u64`). It uses the byte order of the field.
+/// Invalid UTF-8 sequences are replaced by `U+FFFD` (see `String::from_utf8_lossy`).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   name: String,
+///   #[LengthType="u8"]
+///   short_name: String,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x05hello\x02hi";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[12..],S{name:"hello".to_string(), short_name:"hi".to_string()})));
+/// # }
+/// ```
+///
+/// If the length is given by a previous field (or is fixed), the `Count` attribute gives the
+/// number of bytes of the string, and there is no length prefix. In that case, the bytes
+/// must be valid UTF-8, or a `MapRes` error is returned.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   len: u8,
+///   #[Count="len"]
+///   name: String,
+///   #[Count="2"]
+///   code: String,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x05helloFR";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[8..],S{len:5, name:"hello".to_string(), code:"FR".to_string()})));
+/// # }
+/// ```
+///
+/// `CString` fields are parsed as bytes terminated by a NUL byte, which is consumed. If no
+/// NUL byte is found, `Incomplete` is returned. The type must be imported (for ex. with
+/// `use std::ffi::CString`), since paths are not supported in field types.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// use std::ffi::CString;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   name: CString,
+///   a: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"abc\x00\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[5..],S{name:CString::new("abc").unwrap(), a:1})));
+/// # }
+/// ```
+///
+/// ## Default parsing function
+///
+/// If a field with type `T` is not a primitive or known type, the generated parser is
+/// `call!(T::parse)`.
+///
+/// This function can be automatically derived, or specified as a method for the struct.
+/// In that case, the function must be a static method with the same API as a
+/// [nom] combinator, returning the wrapped struct when parsing succeeds.
+///
+/// Example (using `Nom` derive):
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,call,be_u16};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S2 {
+///   c: u16
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   a: u16,
+///   b: S2
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x00\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[4..],S{a:0,b:S2{c:1}})));
+/// # }
+/// ```
+///
+/// Example (defining `parse` method):
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,call,map,be_u16,le_u16};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// // no Nom derive
+/// struct S2 {
+///   c: u16
+/// }
+///
+/// impl S2 {
+///     fn parse(i:&[u8]) -> IResult<&[u8],S2> {
+///         map!(
+///             i,
+///             call!(le_u16), // little-endian
+///             |c| S2{c} // return a struct S2
+///             )
+///     }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   a: u16,
+///   b: S2
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x00\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[4..],S{a:0,b:S2{c:256}})));
+/// # }
+/// ```
+///
+/// The generated function can be renamed using the `NomParseName` attribute, for ex. if
+/// the type already has a `parse` method, or to provide several entry points. Fields of
+/// this type then need a `Parse` attribute, since `T::parse` does not exist.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomParseName="parse_v4"]
+/// struct Addr {
+///   a: [u8; 4],
+/// }
+///
+/// impl Addr {
+///     fn parse(s: &str) -> Option<Addr> {
+///         // ...
+/// #       None
+///     }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   #[Parse="call!(Addr::parse_v4)"]
+///   addr: Addr,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x7f\x00\x00\x01";
+/// let res = Addr::parse_v4(input);
+/// assert_eq!(res, Ok((&input[4..],Addr{a:[127,0,0,1]})));
+/// # assert_eq!(Addr::parse("127.0.0.1"), None);
+/// # assert_eq!(S::parse(input), Ok((&input[4..],S{addr:Addr{a:[127,0,0,1]}})));
+/// # }
+/// ```
+///
+/// ## Custom error type
+///
+/// The `NomErr` attribute gives the error type `E` of the `parse` function, which then
+/// returns `IResult<&[u8],T,E>`. The errors of the fields parsers (using the default error
+/// type `u32`) are converted, so `E` must implement `From<u32>`: the error kinds are kept,
+/// and custom errors (`ErrorKind::Custom(n)`) are converted using `E::from(n)`.
+///
+/// Only `parse` is modified, so the fields of other structures cannot have this type (unless
+/// they use the `Parse` attribute). The `ParseErased` attribute is not supported.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Debug,PartialEq)]
+/// enum MyError { BadVersion, Other(u32) }
+///
+/// impl From<u32> for MyError {
+///     fn from(n: u32) -> MyError { if n == 1 { MyError::BadVersion } else { MyError::Other(n) } }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomErr="MyError"]
+/// struct S {
+///   #[Verify="version == 4"]
+///   #[VerifyError="ErrorKind::Custom(1)"]
+///   version: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x06";
+/// let res : IResult<&[u8],S,MyError> = S::parse(input);
+/// assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::Custom(MyError::BadVersion)))));
+/// # }
+/// ```
+///
+/// ## Byte order
+///
+/// The `LittleEndian` attribute can be set on the structure to change the default byte
+/// order: all inferred integer parsers will then use `le_*` instead of `be_*`.
+/// This includes the items of `Option`, `Vec` and array fields (with or without `Count`).
+/// Fields with an explicit `Parse` attribute are not affected.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,le_u16,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[LittleEndian]
+/// struct S {
+///   a: u16,
+///   b: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x34\x12";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[4..],S{a:1,b:0x1234})));
+/// # }
+/// ```
+///
+/// The `BigEndian` and `LittleEndian` attributes can also be set on a field, to change
+/// the byte order of this field only. The field type still selects the parser, so the
+/// width of the integer is kept, and the items of container fields use the same byte order.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,be_u16,le_u32,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   a: u16,
+///   #[LittleEndian]
+///   b: u32,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01\x34\x12\x00\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{a:1,b:0x1234})));
+/// # }
+/// ```
+///
+/// For formats using the byte order of the host (for ex. shared memory), the `NativeEndian`
+/// attribute can be used instead, on the structure or on a field (or on a fieldless enum). The
+/// parser of each integer is selected at compile time using `cfg!(target_endian)`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NativeEndian]
+/// struct S {
+///   a: u32,
+///   #[BigEndian]
+///   b: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = [&1u32.to_ne_bytes()[..], b"\x00\x02"].concat();
+/// let res = S::parse(&input);
+/// assert_eq!(res, Ok((&input[6..],S{a:1,b:2})));
+/// # }
+/// ```
+///
+/// The [NomLE](derive.NomLE.html) and [NomBE](derive.NomBE.html) derive macros can be used
+/// instead of `Nom` to set the default byte order of all parsers of the structure, or
+/// enum (including fieldless enums).
+///
+/// ```rust
+/// # use nom_derive::NomLE;
+/// # use nom::{do_parse,IResult,le_u16,le_u32,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(NomLE)]
+/// struct S {
+///   a: u16,
+///   b: u32,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x34\x12\x00\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{a:1,b:0x1234})));
+/// # }
+/// ```
+///
+/// Some formats (for ex. PCAP or TIFF) declare their byte order in a magic value at the
+/// start of the data. The `SetEndian` attribute takes a boolean expression, evaluated after
+/// parsing the field: if it is true, the byte order of all the next fields is swapped
+/// (compared to the byte order of this field). The choice between `be_*` and `le_*` parsers
+/// is then made at runtime.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,be_u16,le_u16,be_u32,le_u32,value,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   #[SetEndian="magic == 0xd4c3_b2a1"]
+///   magic: u32,
+///   a: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\xa1\xb2\xc3\xd4\x00\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{magic:0xa1b2_c3d4,a:1})));
+///
+/// let input = b"\xd4\xc3\xb2\xa1\x01\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{magic:0xd4c3_b2a1,a:1})));
+/// # }
+/// ```
+///
+/// If the byte order is given by a previous field (for ex. a `ByteOrder` enum), the
+/// `EndianFrom` attribute names this field: this field and all the next fields use the
+/// byte order `nom::Endianness::from(value)`. The type of the named field must implement
+/// `Copy`, and `nom::Endianness` must implement `From` for it.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Clone,Copy,Nom)]
+/// #[repr(u8)]
+/// enum ByteOrder {
+///   Big = 0,
+///   Little = 1,
+/// }
+///
+/// impl From<ByteOrder> for Endianness {
+///   fn from(order: ByteOrder) -> Endianness {
+///     match order {
+///       ByteOrder::Big    => Endianness::Big,
+///       ByteOrder::Little => Endianness::Little,
+///     }
+///   }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   order: ByteOrder,
+///   #[EndianFrom="order"]
+///   a: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x01\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[3..],S{order:ByteOrder::Little,a:1})));
+/// # }
+/// ```
+///
+/// If the byte order is only known by the caller, the `NomBothEndian` attribute can be set
+/// on the structure (or enum). Two functions `parse_be` and `parse_le` are then generated,
+/// which differ only by the byte order of the inferred parsers. The `parse` function calls
+/// the one matching the default byte order.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,be_u16,le_u16,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomBothEndian]
+/// struct S {
+///   a: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00";
+/// assert_eq!(S::parse_be(input), Ok((&input[2..],S{a:0x100})));
+/// assert_eq!(S::parse_le(input), Ok((&input[2..],S{a:1})));
+/// assert_eq!(S::parse(input), Ok((&input[2..],S{a:0x100})));
+/// # }
+/// ```
+///
+/// For stateful formats, where the byte order is known only at runtime, the `DynamicEndian`
+/// attribute can be set on the structure (or enum). The generated `parse` function then
+/// takes the byte order as last argument (`endian: nom::Endianness`), and the inferred
+/// parsers use it. Fields with a `Parse` attribute are not affected.
+///
+/// If a field has a type which also has the `DynamicEndian` attribute, the `DynamicEndian`
+/// attribute can be set on this field to forward the byte order to its `parse` function
+/// (see also below).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,Endianness,be_u16,le_u16,be_u32,le_u32,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[DynamicEndian]
+/// struct Inner {
+///   b: u32,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[DynamicEndian]
+/// struct S {
+///   a: u16,
+///   #[DynamicEndian]
+///   inner: Inner,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x02\x00\x00\x00";
+/// let res = S::parse(input, Endianness::Little);
+/// assert_eq!(res, Ok((&input[6..],S{a:1,inner:Inner{b:2}})));
+/// # }
+/// ```
+///
+/// When the byte order is selected at runtime (using `SetEndian`, `EndianFrom` or
+/// `DynamicEndian`), the fields whose type is another derived type (including in `Option`,
+/// `Vec` or arrays) are parsed using `T::parse_endian(i, endian)`, so the byte order is
+/// inherited.
+/// This function is generated for types with the `NomEndianAware` attribute (their `parse`
+/// function still uses the default byte order), or with the `DynamicEndian` attribute.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,be_u16,le_u16,be_u32,le_u32,value,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomEndianAware]
+/// struct Record {
+///   b: u16,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   #[SetEndian="magic == 0xd4c3_b2a1"]
+///   magic: u32,
+///   record: Record,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\xd4\xc3\xb2\xa1\x01\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{magic:0xd4c3_b2a1,record:Record{b:1}})));
+/// # }
+/// ```
+///
+/// If the nested type does not have one of these attributes, the code does not compile
+/// (`parse_endian` is not found). To parse it using its own byte order instead, set an
+/// explicit `BigEndian` or `LittleEndian` attribute (or a `Parse` attribute) on the field.
+///
+/// ```compile_fail
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Nom)]
+/// struct Record {
+///   b: u16,
+/// }
+///
+/// #[derive(Nom)]
+/// struct S {
+///   #[SetEndian="magic == 0xd4c3_b2a1"]
+///   magic: u32,
+///   record: Record,
+/// }
+/// #
+/// # fn main() {}
+/// ```
+///
+/// Formats starting with a byte order mark (for ex. TIFF, with `II` or `MM`) can use the
+/// `NomByteOrderMark` attribute on the structure. The `le` and `be` values are the
+/// expressions of the marks. The mark is parsed first (and is not stored), then all fields
+/// use the byte order it selects, including nested types with `NomEndianAware`. If the
+/// input starts with neither mark, an error is returned.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomByteOrderMark(le="b\"II\"", be="b\"MM\"")]
+/// struct Header {
+///   version: u16,
+///   offset: u32,
+/// }
+/// #
+/// # fn main() {
+/// let input_le = b"II\x2a\x00\x08\x00\x00\x00";
+/// let input_be = b"MM\x00\x2a\x00\x00\x00\x08";
+/// let res_le = Header::parse(input_le);
+/// let res_be = Header::parse(input_be);
+/// assert_eq!(res_le, Ok((&input_le[8..],Header{version:42,offset:8})));
+/// assert_eq!(res_be, Ok((&input_be[8..],Header{version:42,offset:8})));
+/// assert!(Header::parse(b"XX\x00\x2a\x00\x00\x00\x08").is_err());
+/// # }
+/// ```
+///
+/// The byte order of a field is chosen using the following rules, by order of precedence:
+///   - an explicit `Parse` attribute is used as-is
+///   - the `BigEndian`, `LittleEndian` or `NativeEndian` attribute of the field
+///   - the `SetEndian` attribute of a previous field, or the `EndianFrom` attribute of this
+///     field or a previous field (the last one applies)
+///   - the `NomByteOrderMark` attribute of the structure
+///   - the byte order given to `parse`, if the structure has the `DynamicEndian` attribute
+///   - the `BigEndian`, `LittleEndian` or `NativeEndian` attribute of the structure
+///   - the default byte order of the derive (big-endian, except for `NomLE`)
+///
+/// Setting more than one of `BigEndian`, `LittleEndian` and `NativeEndian` on the same item
+/// is an error:
+///
+/// ```compile_fail
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Nom)]
+/// struct S {
+///   #[BigEndian]
+///   #[LittleEndian]
+///   a: u16,
+/// }
+/// #
+/// # fn main() {}
+/// ```
+///
+/// ## Specifying parsers
+///
+/// Sometimes, the default parsers generated automatically are not those you
+/// want.
+///
+/// The `Parse` custom attribute allows for specifying the parser, using code that
+/// will be inserted in the `do_parse` block of the nom parser.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,call,le_u16};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Parse="le_u16"]
+///     a: u16
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[2..],S{a:256})));
+/// # }
+/// ```
+///
+/// On an `Option<T>` field, the attributes (like `Parse` or `Count`) apply to the parser
+/// of `T`, and the generated parser is `opt!(complete!(parser))`:
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Parse="le_u16"]
+///     a: Option<u16>,
+///     #[Count="2"]
+///     b: Option<Vec<u8>>,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01\x02";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[2..],S{a:Some(256), b:None})));
+/// # }
+/// ```
+///
+/// The `ParseOuter` attribute gives the parser of the whole field instead. Its argument, like
+/// the argument of `Parse`, can be a complex expression:
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,be_u8,be_u16,call,cond};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub a: u8,
+///     #[ParseOuter="cond!(a > 0,be_u16)"]
+///     pub b: Option<u16>,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[3..],S{a:1,b:Some(1)})));
+/// # }
+/// ```
+/// Note that you are responsible from providing correct code.
+///
+/// ## Nibbles
+///
+/// The `Nibbles` attribute can be set on two consecutive `u8` fields stored in a single
+/// byte: the first field gets the high nibble, and the second field the low nibble.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   #[Nibbles]
+///   version: u8,
+///   #[Nibbles]
+///   header_len: u8,
+///   ttl: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x45\x40";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[2..],S{version:4, header_len:5, ttl:64})));
+/// # }
+/// ```
+///
+/// ## ASCII decimal integers
+///
+/// The `AsciiInt` attribute can be set on an integer field stored as ASCII decimal text,
+/// with a fixed width. The value of the attribute is the width (in bytes). The parser fails
+/// (`ErrorKind::MapRes`) if a byte is not a digit, or if the number is too large for the
+/// field type. Signs are not supported.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[AsciiInt="4"]
+///     pub year: u16,
+///     pub a: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"2019\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[5..],S{year:2019,a:1})));
+/// # assert!(S::parse(b"20a9\x01").is_err());
+/// # }
+/// ```
+///
+/// ## Constant bytes
+///
+/// The `Tag` attribute checks that the input contains the given bytes (for ex. a magic
+/// number), using `tag!`, and returns an error otherwise. The value can be a byte string
+/// (`#[Tag=b"\xca\xfe"]`), a string (`#[Tag="GIF89a"]`), or a string containing a byte
+/// string (`#[Tag="b\"\\xca\\xfe\""]`).
+///
+/// The bytes are stored in the field if its type is a `[u8; N]` array or a `&[u8]` slice.
+/// If the type of the field is `()` or `PhantomData`, the bytes are checked, but not
+/// stored.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S {
+///   #[Tag=b"\xca\xfe"]
+///   magic: [u8; 2],
+///   #[Tag="v1"]
+///   version: (),
+///   a: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\xca\xfev1\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[5..],S{magic:[0xca,0xfe], version:(), a:1})));
+/// assert!(S::parse(b"\xca\xfev2\x01").is_err());
+/// # }
+/// ```
+///
+/// The `Tag` attribute can also be set on the structure: the bytes are checked before
+/// parsing the fields, and are not stored.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Tag="MZ"]
+/// struct DosHeader {
+///   last_page_size: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"MZ\x00\x90";
+/// let res = DosHeader::parse(input);
+/// assert_eq!(res, Ok((&input[4..],DosHeader{last_page_size:0x90})));
+/// assert!(DosHeader::parse(b"ZM\x00\x90").is_err());
+/// # }
+/// ```
+///
+/// On a structure (or an enum variant), the bytes can also be given using the `Magic`
+/// attribute, for ex. `#[Magic(b"\x89PNG\r\n\x1a\n")]`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Magic(b"\x89PNG\r\n\x1a\n")]
+/// struct Png {
+///   chunk_len: u32,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d";
+/// let res = Png::parse(input);
+/// assert_eq!(res, Ok((&input[12..],Png{chunk_len:13})));
+/// # }
+/// ```
+///
+/// ## Skipping bytes
+///
+/// The `Skip` attribute consumes bytes (for ex. padding or reserved bytes) before parsing
+/// the field. The skipped bytes are not stored. The number of bytes can be any expression,
+/// and can refer to previously parsed fields. It will be cast to `usize`.
+/// A constant number of bytes can also be given as an integer: `#[Skip=3]` or `#[Skip(3)]`.
+/// Like `Count`, the expression can also be written without quotes: `#[Skip(a - 1)]`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,call,take,be_u8};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub a: u8,
+///     #[Skip(3)]
+///     pub b: u8,
+///     #[Skip="a"]
+///     pub c: u8,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01\x00\x00\x00\x02\x00\x03";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[7..],S{a:1,b:2,c:3})));
+/// # }
+/// ```
+///
+/// The `Align` attribute skips padding bytes, until the offset of the field is a multiple
+/// of the alignment (`#[Align=4]`, `#[Align(4)]`, or an expression `#[Align("n")]`).
+///
+/// Note: the parser only knows the input of its parse function, so the offset is relative
+/// to the start of the structure (or of the enum), also after a `BoundingLength` field. The
+/// field is aligned in the file only if the structure starts at an aligned offset.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub a: u8,
+///     #[Align(4)]
+///     pub b: u32,
+///     #[Align(4)]
+///     pub c: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x00\x00\x00\x00\x00\x02\x00\x03";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[10..],S{a:1,b:2,c:3})));
+/// # }
+/// ```
+///
+/// ## Bounding length
+///
+/// The `BoundingLength` attribute can be set on a field containing the length of the rest of
+/// the structure (in bytes). All subsequent fields are parsed using a slice of this length,
+/// and bytes left at the end of this slice are skipped (for ex. padding).
+/// The length field must be an integer type.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub kind: u8,
+///     #[BoundingLength]
+///     pub len: u8,
+///     pub a: u16,
+///     pub b: Option<u16>,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x04\x00\x01\x00\x00\xff";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{kind:1,len:4,a:1,b:Some(0)})));
+/// # let input = b"\x01\x03\x00\x01\x00\x00\xff";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[5..],S{kind:1,len:3,a:1,b:None})));
+/// # }
+/// ```
+///
+/// ## Taking bytes while a predicate holds
+///
+/// The `TakeWhile` attribute can be set on a `&[u8]` or `Vec<u8>` field: the bytes are read
+/// while the predicate (any expression of type `Fn(u8) -> bool`) is true, using
+/// `take_while!`. Like `take_while!`, the parser returns `Incomplete` if all the remaining
+/// bytes match.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S<'a> {
+///   #[TakeWhile="is_digit"]
+///   number: &'a [u8],
+///   #[TakeWhile="|c| c != b';'"]
+///   name: Vec<u8>,
+///   sep: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"42abc;";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{number:b"42", name:b"abc".to_vec(), sep:b';'})));
+/// # }
+/// ```
+///
+/// Similarly, the `TakeUntil` attribute reads the bytes until the first occurrence of a
+/// pattern (a byte string, or any expression which can be sliced to `[u8]`), using
+/// `take_until!`. Unlike `CString`, the pattern is not consumed, and can be longer than one
+/// byte. If the pattern is not found, the parser returns `Incomplete`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct Line<'a> {
+///   #[TakeUntil="b\"\\r\\n\""]
+///   text: &'a [u8],
+///   #[Tag=b"\r\n"]
+///   end: (),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"GET / HTTP/1.0\r\n";
+/// let res = Line::parse(input);
+/// assert_eq!(res, Ok((&input[16..],Line{text:b"GET / HTTP/1.0", end:()})));
+/// # }
+/// ```
+///
+/// ## Reading the remaining bytes
+///
+/// The `Rest` attribute can be set on the last field of a structure (or enum variant), if
+/// its type is `&[u8]` or `Vec<u8>`: all the remaining bytes are read, using `rest`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct Packet<'a> {
+///   kind: u8,
+///   #[Rest]
+///   payload: &'a [u8],
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01abc";
+/// let res = Packet::parse(input);
+/// assert_eq!(res, Ok((&input[4..],Packet{kind:1, payload:b"abc"})));
+/// # }
+/// ```
+///
+/// ## Parsing until an offset
+///
+/// The `UntilOffset` attribute can be set on a `Vec` field, to parse items until the
+/// given offset. The offset is an expression, relative to the start of the input of the
+/// parse function (so it can be computed from previous fields, for ex. a header length).
+/// Items are parsed in the window between the current position and this offset (using
+/// `many0!`), and parsing resumes after the window.
+///
+/// An offset before the current position, or after the end of the input (or of the region
+/// restricted by `BoundingLength`), is an error.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub hdr_len: u8,
+///     #[UntilOffset="hdr_len"]
+///     pub options: Vec<u16>,
+///     pub a: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x05\x00\x01\x00\x02\xff";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[6..],S{hdr_len:5,options:vec![1,2],a:0xff})));
+/// # }
+/// ```
+///
+/// ## Limiting the input length
+///
+/// The `NomMaxLen` attribute can be set on the structure (or enum) to reject inputs longer
+/// than the limit, before parsing anything. This is a cheap protection against oversized
+/// inputs. An error with kind `TooLarge` is returned.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomMaxLen=4]
+/// struct S {
+///   a: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01\x00\x00";
+/// assert_eq!(S::parse(input), Ok((&input[2..],S{a:1})));
+/// let input = b"\x00\x01\x00\x00\x00";
+/// assert_eq!(S::parse(input), Err(Err::Error(error_position!(&input[..], ErrorKind::TooLarge))));
+/// # }
+/// ```
+///
+/// Note that the limit also applies when the type is parsed as a field of another
+/// structure: the input is then the remaining data.
+///
+/// ## Ignoring fields
+///
+/// The `Ignore` attribute marks a field which is not present in the input. Nothing is
+/// parsed, and the field is initialized with `Default::default()`. The type of the field is
+/// not inspected, so it can be any type implementing `Default`, for ex. an optional
+/// callback:
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,call,be_u8};
+/// #
+/// #[derive(Nom)]
+/// struct S{
+///     pub a: u8,
+///     #[Ignore]
+///     pub callback: Option<Box<dyn Fn(u8)>>,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01";
+/// # let (rem, s) = S::parse(input).expect("parsing failed");
+/// # assert!(rem.is_empty());
+/// # assert_eq!(s.a, 1);
+/// # assert!(s.callback.is_none());
+/// # }
+/// ```
+///
+/// Unlike `Skip`, `Ignore` does not consume any input.
+///
+/// The `Default` attribute is an alias of `Ignore`. It can also be given an expression, which
+/// is used instead of `Default::default()` to initialize the field. The expression is
+/// evaluated when building the object, after all fields are parsed.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,call,be_u32};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub a: u32,
+///     #[Default]
+///     pub extra: Vec<u8>,
+///     #[Default="0xff"]
+///     pub flags: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[4..],S{a:1,extra:vec![],flags:0xff})));
+/// # }
+/// ```
+///
+/// If the parser of some fields cannot be inferred, all these fields are listed in the
+/// error message.
+///
+/// ## Converting values
+///
+/// The `Into` and `TryInto` attributes convert the parsed value to the type of the field.
+/// They are mostly useful with a `Parse` attribute, when the type on the wire is different
+/// from the type of the field. With `TryInto`, the parser fails (`ErrorKind::MapRes`) if the
+/// conversion fails, for ex. if the value is out of range.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,map,map_res,call,be_u8,be_u16};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Parse="be_u8"]
+///     #[Into]
+///     pub a: u32,
+///     #[Parse="be_u16"]
+///     #[TryInto]
+///     pub b: u8,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[3..],S{a:1,b:1})));
+/// # assert!(S::parse(b"\x01\x01\x00").is_err());
+/// # }
+/// ```
+///
+/// The `Map` attribute applies a function (or closure) to the parsed value, using `map!`.
+/// The value given to the function is the output of the parser of the field (inferred, or
+/// given by a `Parse` attribute).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,map,call,be_u8,be_u32};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Map="|v| v & 0x7f"]
+///     pub a: u8,
+///     #[Parse="be_u32"]
+///     #[Map="|v| v as usize"]
+///     pub b: usize,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x81\x00\x00\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[5..],S{a:1,b:1})));
+/// # }
+/// ```
+///
+/// The field type can differ from the type on the wire, for ex. to store a domain type.
+/// In that case, the parser cannot be inferred from the field type, and must be given using
+/// a `Parse` attribute.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,map,call,be_u8};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// pub enum Color { Red, Other(u8) }
+///
+/// impl From<u8> for Color {
+///     fn from(v: u8) -> Color {
+///         if v == 0 { Color::Red } else { Color::Other(v) }
+///     }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Parse="be_u8"]
+///     #[Map="Color::from"]
+///     pub color: Color,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[1..],S{color:Color::Red})));
+/// # }
+/// ```
+///
+/// With the `Count` attribute, the function of `Map` is applied to the whole `Vec` (not to
+/// each element).
+///
+/// When several attributes are set on a field, they are applied in the following order:
+///   - the parser (`Parse` or `Count` attribute, or inferred from the type)
+///   - `Map`
+///   - `Cond` (the function of `Map` receives the value inside the `Option`)
+///   - `Into` or `TryInto`
+///   - `Verify`
+///
+/// ## Adding conditions
+///
+/// The `Cond` custom attribute allows for specifying a condition.
+/// The generated parser will use the `cond!` combinator, which calls the
+/// child parser only if the condition is met.
+/// The type with this attribute must be an `Option` type.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,cond,complete,be_u8,be_u16,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub a: u8,
+///     #[Cond="a == 1"]
+///     pub b: Option<u16>,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[3..],S{a:1,b:Some(1)})));
+/// # }
+/// ```
+///
+/// If the field also has a `Verify` attribute (in any order), the verification applies to
+/// the inner value, and only if the condition is met: the expression uses the name of the
+/// field for the value (not the `Option`). An invalid value is an error, instead of `None`
+/// (the parser uses `cond_with_error!`).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub flags: u8,
+///     #[Cond="flags & 1 != 0"]
+///     #[Verify="score <= 100"]
+///     pub score: Option<u8>,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x64";
+/// assert_eq!(S::parse(input), Ok((&input[2..],S{flags:1,score:Some(100)})));
+/// let input = b"\x01\x65";
+/// assert_eq!(S::parse(input), Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+/// # }
+/// ```
+///
+/// Since the value is converted after the condition, `Cond` and `Verify` cannot be combined
+/// with `Into` or `TryInto`:
+///
+/// ```compile_fail
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Nom)]
+/// struct S{
+///     pub flags: u8,
+///     #[Cond="flags & 1 != 0"]
+///     #[Verify="score <= 100"]
+///     #[Into]
+///     pub score: Option<u8>,
+/// }
+/// #
+/// # fn main() {}
+/// ```
+///
+/// ## Versioned formats
+///
+/// The `NomVersionField` attribute on a structure (or an enum variant) names the field giving
+/// the version of the format, which must be parsed before the versioned fields. A field with
+/// the `Since(v)` attribute is only parsed if the version is at least `v`, and a field with
+/// the `Until(v)` attribute only if the version is at most `v` (both bounds are included).
+///
+/// If the field is not parsed, an `Option` field is `None`, and other fields have their
+/// default value. These attributes can be combined with `Cond`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomVersionField="version"]
+/// struct S{
+///     pub version: u8,
+///     #[Since(2)]
+///     pub flags: Option<u16>,
+///     #[Until(1)]
+///     pub reserved: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[2..],S{version:1, flags:None, reserved:0})));
+/// let input = b"\x02\x00\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[3..],S{version:2, flags:Some(1), reserved:0})));
+/// # }
+/// ```
+///
+/// ## Parsing context
+///
+/// Some formats need state from outside the structure, for ex. a version negotiated
+/// earlier. The `NomContext` attribute on a structure gives the type of this context: a
+/// `parse_with_context(i, ctx)` function is generated, where `ctx` is a reference to the
+/// context, and can be used in the attributes of the fields (`Parse`, `Cond`, `Count`, etc.).
+///
+/// The `parse` function is still generated: it calls `parse_with_context` with the default
+/// value of the context, so the type must implement `Default`. The `NomContext` attribute
+/// cannot be used with `DynamicEndian`, `NomBothEndian`, `NomEndianAware`, `NomResumable`,
+/// `Columnar`, `NomTraceErrors`, `NomParseTraced` or `NomUnsafeFastPath`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Default)]
+/// pub struct Session {
+///     pub version: u8,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomContext="Session"]
+/// struct S{
+///     pub a: u8,
+///     #[Cond="ctx.version >= 2"]
+///     pub b: Option<u16>,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x02";
+/// let res = S::parse_with_context(input, &Session{version: 2});
+/// assert_eq!(res, Ok((&input[3..],S{a:1, b:Some(2)})));
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[1..],S{a:1, b:None})));
+/// # }
+/// ```
+///
+/// ## Selecting the parser of a field
+///
+/// The `Match` attribute selects the parser of a field using the value of an expression,
+/// like a `match` expression: `#[Match("expr", "pattern => parser", ...)]`. The first arm
+/// whose pattern matches is used, and its parser must return the type of the field. This
+/// combines conditions and parser selection, for ex. an `Option` field can be present with
+/// a different size, or absent, depending on a previous field. If no arm matches,
+/// `ErrorKind::Switch` is returned.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub kind: u8,
+///     #[Match("kind", "1 => map!(be_u8, |v| Some(u16::from(v)))", "2 => map!(be_u16, Some)", "_ => value!(None)")]
+///     pub value: Option<u16>,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x02\x00\x01";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[3..],S{kind:2, value:Some(1)})));
+/// let input = b"\x00";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[1..],S{kind:0, value:None})));
+/// # }
+/// ```
+///
+/// ## Running statements
+///
+/// The `PreExec="..."` and `PostExec="..."` attributes insert statements before or after the
+/// parser of a field. The statements can use the previous fields, `i` (the input of the
+/// parse function) and `i_` (the remaining input at this point). The variables they declare
+/// are visible in the attributes of the next fields. In `PostExec`, the variable of the field
+/// is mutable, so its value can be adjusted.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S<'a>{
+///     #[PreExec="let start = i_;"]
+///     pub len: u8,
+///     #[Parse="take!(len)"]
+///     #[PostExec="let header_len = start.len() - i_.len();"]
+///     pub name: &'a [u8],
+///     #[Default="header_len"]
+///     pub header_len: usize,
+///     #[PostExec="count += 1;"]
+///     pub count: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x02ab\x07";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[4..],S{len:2,name:b"ab",header_len:3,count:8})));
+/// # }
+/// ```
+///
+/// The parsers following the statements are run in a closure, which is why these attributes
+/// cannot be used with `NomResumable` or `Columnar`.
+///
+/// ## Adding verifications
+///
+/// The `Verify` custom attribute allows for specifying a verifying function.
+/// The generated parser behaves like the `verify!` combinator: it calls the
+/// child parser, and checks that the result verifies a condition (and otherwise raises
+/// an `ErrorKind::Verify` error).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,map,error_position,be_u8,be_u16,call};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Verify="a == 1"]
+///     pub a: u8,
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[1..],S{a:1})));
+/// # }
+/// ```
+///
+/// The condition is an expression, in which the name of the field is bound to the parsed
+/// value (it is not a closure). The value is not moved, so the condition can call methods
+/// on non-`Copy` types or slices, and can refer to previously parsed fields:
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::{do_parse,IResult,map,error_position,be_u8,call,take};
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S<'a>{
+///     pub len: u8,
+///     #[Parse="take!(2)"]
+///     #[Verify="reserved.iter().all(|b| *b == 0)"]
+///     pub reserved: &'a[u8],
+///     #[Parse="take!(len)"]
+///     #[Verify="data.len() == len as usize"]
+///     pub data: &'a[u8],
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x01\x00\x00\x01";
+/// # let res = S::parse(input);
+/// # assert_eq!(res, Ok((&input[4..],S{len:1,reserved:&input[1..3],data:&input[3..]})));
+/// # }
+/// ```
+///
+/// To know which verification failed, the `VerifyError` attribute gives the error kind
+/// returned instead of `ErrorKind::Verify`. It is an expression of type `nom::ErrorKind`,
+/// usually `ErrorKind::Custom(n)` (or a constant):
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// const BAD_VERSION: ErrorKind = ErrorKind::Custom(1);
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     #[Verify="version == 1"]
+///     #[VerifyError="BAD_VERSION"]
+///     pub version: u8,
+///     #[Verify="flags & 0xf0 == 0"]
+///     #[VerifyError="ErrorKind::Custom(2)"]
+///     pub flags: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x02\x00";
+/// assert_eq!(S::parse(input), Err(Err::Error(error_position!(&input[..], BAD_VERSION))));
+/// let input = b"\x01\x10";
+/// assert_eq!(S::parse(input), Err(Err::Error(error_position!(&input[1..], ErrorKind::Custom(2)))));
+/// # }
+/// ```
+///
+/// The `Monotonic` attribute, on a `Vec` (or array) field, checks that the items are
+/// sorted: each item must be greater than or equal to the previous one. The items are
+/// checked after parsing the field, and the error kind can also be given by `VerifyError`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub n: u8,
+///     #[Count="n"]
+///     #[Monotonic]
+///     pub offsets: Vec<u16>,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x03\x00\x01\x00\x01\x00\x05";
+/// assert_eq!(S::parse(input), Ok((&input[7..],S{n:3, offsets:vec![1, 1, 5]})));
+/// let input = b"\x02\x00\x02\x00\x01";
+/// assert_eq!(S::parse(input), Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+/// # }
+/// ```
+///
+/// ## Checksums
+///
+/// The `Checksum(algo="...", over="...")` attribute verifies that the value of a field is
+/// the checksum of previous data. `over` is an expression giving the data (usually a
+/// previous `&[u8]` or `Vec<u8>` field), and `algo` is the algorithm:
+///   - `crc32`: CRC-32 (IEEE 802.3), the field must be a `u32`
+///   - `crc16-ccitt`: CRC-16/CCITT-FALSE, the field must be a `u16`
+///   - `sum8`: sum of the bytes modulo 256, the field must be a `u8`
+///   - any other value is the path of a function `fn(&[u8]) -> T`, where `T` is the type of
+///     the field
+///
+/// If the value is different, a `Verify` error is returned (or the error kind given by the
+/// `VerifyError` attribute). The built-in algorithms require the `checksums` feature
+/// (enabled by default).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// fn xor8(data: &[u8]) -> u8 {
+///     data.iter().fold(0, |acc, b| acc ^ b)
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S<'a>{
+///     pub len: u8,
+///     #[Parse="take!(len)"]
+///     pub data: &'a[u8],
+///     #[Checksum(algo="crc32", over="data")]
+///     pub crc: u32,
+///     #[Checksum(algo="xor8", over="data")]
+///     pub check: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x09123456789\xcb\xf4\x39\x26\x31";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[15..],S{len:9,data:b"123456789",crc:0xcbf4_3926,check:0x31})));
+/// # }
+/// ```
+///
+/// When the checksum is stored inside the data it covers (for ex. in an IP header), use the
+/// `SelfExcludingChecksum="algo"` attribute on the checksum field instead. The checksum is
+/// computed over all the bytes of the structure, with the bytes of the checksum field set to
+/// zero, and verified after the last field is parsed. On mismatch, the error is located at the
+/// checksum field. This attribute cannot be used with `BoundingLength`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// struct S{
+///     pub kind: u8,
+///     #[SelfExcludingChecksum="sum8"]
+///     pub sum: u8,
+///     pub value: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x06\x02\x03";
+/// let res = S::parse(input);
+/// assert_eq!(res, Ok((&input[4..],S{kind:1,sum:6,value:0x203})));
+/// # }
+/// ```
+///
+/// ## Resumable parsing
+///
+/// When the input is received in chunks, the `NomResumable` attribute can be set on the
+/// structure to generate a resumable parser. A state structure (named `<Struct>ResumeState`)
+/// is generated, containing the offset of the next field and the values of the fields
+/// already parsed.
+///
+/// The `parse_resumable` function takes the whole input received so far (from the start
+/// of the structure), and the state. If a field cannot be parsed because the input is
+/// incomplete, the error is returned, and the next call will resume parsing at this field.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomResumable]
+/// struct S{
+///     pub a: u16,
+///     pub b: u32,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01\x00\x00\x00\x02";
+/// let mut state = SResumeState::default();
+/// assert!(S::parse_resumable(&input[..3], &mut state).is_err());
+/// let res = S::parse_resumable(input, &mut state);
+/// assert_eq!(res, Ok((&input[6..],S{a:1,b:2})));
+/// # }
+/// ```
+///
+/// This is a minimal implementation, with the following limitations:
+///   - parsing resumes at field boundaries (a field is parsed again entirely)
+///   - the type of each field must implement `Clone`
+///   - a new state must be used for each object
+///   - it cannot be used on generic structures, or with the `Skip`, `Tag` (if the bytes
+///     are not stored), `BoundingLength`, `SetEndian`, `EndianFrom` or `NomByteOrderMark`
+///     attributes
+///
+/// ## Tracing errors
+///
+/// The `NomTraceErrors` attribute on a structure generates a `parse_trace` function, which
+/// parses the structure like `parse`. If an error occurs, it returns the error and the path
+/// to the failing field, as a `Vec<&'static str>` of `Struct.field` names (tuple struct
+/// fields are named by their index). The errors of the bytes which are not part of a field
+/// (magic, padding, skipped bytes, etc.) are reported under the next field, or under the
+/// name of the structure alone after the last field (for ex. a `SelfExcludingChecksum`).
+///
+/// The fields whose type is a nested structure are parsed using its `parse_trace` function,
+/// so the nested structures must also have the `NomTraceErrors` attribute (except if the
+/// parser is given by an attribute, like `Parse` or `Selector`).
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomTraceErrors]
+/// struct Header{
+///     #[Verify="version == 1"]
+///     pub version: u8,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomTraceErrors]
+/// struct S{
+///     pub header: Header,
+///     pub a: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x02\x00\x01";
+/// let res = S::parse_trace(input);
+/// assert_eq!(res, Err((Err::Error(error_position!(&input[..], ErrorKind::Verify)), vec!["S.header", "Header.version"])));
+/// # }
+/// ```
+///
+/// The `NomGroup` attribute gives a label to consecutive fields, for ex. the options of a
+/// packet. It does not change the parser, but the label is added to the errors of the
+/// fields in the group, to the path of these fields in `parse_trace` (`Struct.group.field`),
+/// and to the output of [NomDeriveDebug](derive.NomDeriveDebug.html). The fields of a group
+/// must be consecutive.
+///
+/// The errors can contain the label only if the `verbose-errors` feature of nom is enabled:
+/// an error with the code `ErrorKind::Custom(code)` is appended to the list of errors, where
+/// `code` is a hash of the label. A `group_label(code)` function is generated, returning the
+/// label of a group from its code. Without this feature, the errors are not modified.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomTraceErrors]
+/// struct S{
+///     pub len: u8,
+///     #[NomGroup="options"]
+///     pub kind: u8,
+///     #[NomGroup="options"]
+///     #[Verify="value < 8"]
+///     pub value: u8,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x02\x01\x09";
+/// let res = S::parse_trace(input);
+/// assert_eq!(res.map_err(|(_, path)| path), Err(vec!["S.options.value"]));
+/// // with the `verbose-errors` feature of nom
+/// match S::parse(input) {
+///     Err(Err::Error(Context::List(errors))) => {
+///         let labels : Vec<_> = errors.iter()
+///             .filter_map(|(_, kind)| match kind { ErrorKind::Custom(code) => S::group_label(*code), _ => None })
+///             .collect();
+///         assert_eq!(labels, vec!["options"]);
+///     },
+///     e => panic!("unexpected result {:?}", e),
+/// }
+/// # }
+/// ```
+///
+/// ## Field boundaries
+///
+/// For fuzzing or differential testing, the `NomParseTraced` attribute on a structure
+/// generates a `parse_traced(i, cb)` function, which parses the structure like `parse`,
+/// and calls `cb(name, start, end)` for each field once it is parsed. `name` is
+/// `Struct.field` (like in `parse_trace`), and the offsets are relative to the input (also
+/// for the fields after a `BoundingLength` field). The bytes which are not part of a field
+/// (magic, padding, skipped bytes, etc.) are not reported.
+///
+/// The fields whose type is a nested structure are parsed using its `parse_traced`
+/// function, so the nested structures must also have the `NomParseTraced` attribute. Their
+/// fields are reported first, with offsets relative to the input of the outer structure.
+/// The `parse` function is not modified.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomParseTraced]
+/// struct S{
+///     pub a: u8,
+///     pub b: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x02";
+/// let mut fields = Vec::new();
+/// let res = S::parse_traced(input, &mut |name, start, end| fields.push((name, start, end)));
+/// assert_eq!(res, Ok((&input[3..],S{a:1, b:2})));
+/// assert_eq!(fields, vec![("S.a", 0, 1), ("S.b", 1, 3)]);
+/// # }
+/// ```
+///
+/// ## Unsafe fast path
+///
+/// The generated code does not use `unsafe`.
+///
+/// For structures containing only integer and floating-point fields, the
+/// `NomUnsafeFastPath` attribute generates a `parse` function checking the input length
+/// once, and then reading all fields using unaligned pointer reads. This is the only case
+/// where the generated code contains `unsafe` blocks. Note that the `unsafe_code` lint does
+/// not apply to code generated by derive macros, so `#![forbid(unsafe_code)]` does not
+/// reject this attribute.
+///
+/// The fields can only have the `BigEndian`, `LittleEndian` and `NativeEndian` attributes,
+/// and the structure cannot have the `Tag`, `Magic`, `NomByteOrderMark` or
+/// `NomVersionField` attributes. If the input is too short, the error is
+/// `Incomplete(Needed::Size(n))`, where `n` is the size of the structure.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomUnsafeFastPath]
+/// struct Point{
+///     pub x: u32,
+///     #[LittleEndian]
+///     pub y: u16,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x01\x02\x00";
+/// let res = Point::parse(input);
+/// assert_eq!(res, Ok((&input[6..], Point{x: 1, y: 2})));
+/// assert_eq!(Point::parse(&input[..5]), Err(Err::Incomplete(Needed::Size(6))));
+/// # }
+/// ```
+///
+/// ## Known problems
+///
+/// The generated parsers use the [nom] combinators directly, so they must be
+/// visible in the current namespace (*i.e* imported in a `use` statement).
+///
+/// # Deriving parsers for `Enum`
+///
+/// The `Nom` attribute can also used to generate parser for `Enum` types.
+/// The generated parser will used a value (called *selector*) to determine
+/// which attribute variant is parsed.
+/// Named and unnamed enums are supported.
+///
+/// In addition of `derive(Nom)`, a `Selector` attribute must be used:
+///   - on the structure, to specify the type of selector to match
+///   - on each variant, to specify the value associated with this variant.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U1{
+///     #[Selector("0")] Field1(u32),
+///     #[Selector("1")] Field2(Option<u32>),
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x00\x00\x02";
+/// # let res = U1::parse(input, 0);
+/// # assert_eq!(res, Ok((&input[4..],U1::Field1(2))));
+/// # }
+/// ```
+///
+/// The generated function will look like:
+///
+/// <pre>
+/// impl U1{
+///     pub fn parse(i:&[u8), selector: u8) -> IResult<&[u8],U1> {
+///         match selector {
+///             ...
+///         }
+///     }
+/// }
+/// </pre>
+///
+/// It can be called either directly (`U1::parse(n)`) or using nom
+/// (`call!(U1::parse,n)`).
+///
+/// The selector can be a primitive type (`u8`), or any other type implementing the `PartialEq`
+/// trait.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Debug,PartialEq,Eq,Clone,Copy,Nom)]
+/// pub struct MessageType(pub u8);
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="MessageType"]
+/// pub enum U1{
+///     #[Selector("MessageType(0)")] Field1(u32),
+///     #[Selector("MessageType(1)")] Field2(Option<u32>),
+/// }
+///
+/// // Example of call from a struct:
+/// #[derive(Nom)]
+/// pub struct S1{
+///     pub msg_type: MessageType,
+///     #[Parse="call!(U1::parse,msg_type)"]
+///     pub msg_value: U1
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x00\x00\x02";
+/// # let res = U1::parse(input, MessageType(0));
+/// # assert_eq!(res, Ok((&input[4..],U1::Field1(2))));
+/// # }
+/// ```
+///
+/// Inside a struct, the `Selector` attribute can also be used on the enum field. Its value
+/// is an expression (usually an earlier field), passed by value to the enum parser, so it
+/// should be `Copy`. The selector can itself be an enum, for ex. a fieldless enum. This is
+/// the usual pattern for tagged messages: the kind of the message (a fieldless enum, read
+/// from one byte), followed by the body (an enum selected by the kind). Variants without
+/// fields (`Ping` below) are built without reading any input.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Clone,Copy,Eq,Nom)]
+/// #[repr(u8)]
+/// pub enum MessageType {
+///     Ping = 0,
+///     Data = 1,
+///     Reserved = 2,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="MessageType"]
+/// pub enum MessageValue {
+///     #[Selector("MessageType::Ping")] Ping,
+///     #[Selector("MessageType::Data")] Data(u32),
+///     #[Selector("_")] Unknown,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// pub struct Message {
+///     pub msg_type: MessageType,
+///     #[Selector="msg_type"]
+///     pub msg_value: MessageValue,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x01\x00\x00\x00\x02\x00\x02";
+/// let res = many0!(&input[..], complete!(Message::parse));
+/// assert_eq!(res, Ok((&input[7..], vec![
+///     Message{msg_type:MessageType::Data, msg_value:MessageValue::Data(2)},
+///     Message{msg_type:MessageType::Ping, msg_value:MessageValue::Ping},
+///     Message{msg_type:MessageType::Reserved, msg_value:MessageValue::Unknown},
+/// ])));
+/// # }
+/// ```
+///
+/// The expression can also use a field of an earlier structure, for ex. when the selector
+/// is part of a header:
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// pub struct Header {
+///     pub version: u8,
+///     pub msg_type: u8,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum MessageValue {
+///     #[Selector("0")] Ping(u8),
+///     #[Selector("1")] Data(u32),
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// pub struct Message {
+///     pub header: Header,
+///     #[Selector="header.msg_type"]
+///     pub msg_value: MessageValue,
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x02\x00\x05";
+/// let res = Message::parse(input);
+/// assert_eq!(res, Ok((&input[3..],Message{header:Header{version:2, msg_type:0}, msg_value:MessageValue::Ping(5)})));
+/// # }
+/// ```
+///
+/// ## Default case
+///
+/// By default, if no value of the selector matches the input value, a nom error
+/// `ErrorKind::Switch` is raised. This can be changed by using `_` as selector
+/// value for one the variants.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U2{
+///     #[Selector("0")] Field1(u32),
+///     #[Selector("_")] Field2(u32),
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x00\x00\x02";
+/// # let res = U2::parse(input, 123);
+/// # assert_eq!(res, Ok((&input[4..],U2::Field2(2))));
+/// # }
+/// ```
+///
+/// If the `_` selector is not the last variant, the generated code will use it
+/// as the last match to avoid unreachable code.
+///
+/// ## Ranges and multiple values
+///
+/// The value of a `Selector` is used as a match pattern, so it can be a range of values, or
+/// several values separated by `|`. The `_` default case is always tried last, wherever its
+/// variant is declared.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U5{
+///     #[Selector("_")] Other(u16),
+///     #[Selector("0x10..=0x1f")] Extension(u8),
+///     #[Selector("1 | 2 | 3")] Small(u16),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01";
+/// assert_eq!(U5::parse(input, 0x12), Ok((&input[1..],U5::Extension(0))));
+/// assert_eq!(U5::parse(input, 2), Ok((&input[2..],U5::Small(1))));
+/// assert_eq!(U5::parse(input, 0x20), Ok((&input[2..],U5::Other(1))));
+/// # }
+/// ```
+///
+/// Several values can also be given as a list: `#[Selector("1", "2", "3")]` (or
+/// `#[Selector(1, 2, 3)]`) is the same as `#[Selector("1 | 2 | 3")]`. The items of the list
+/// can be values, ranges or paths, but not the `_` default case, bitmasks or guards:
+///
+/// ```compile_fail
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U5{
+///     #[Selector("0", "x if x > 10")] Field1(u16),
+///     #[Selector("_")] Other(u8),
+/// }
+/// #
+/// # fn main() {}
+/// ```
+///
+/// ## Selector guards
+///
+/// A `Selector` can also bind the selector value, and use it in a guard (for ex.
+/// `x if x > 10`). The selector value is also available as `selector` (the name of the
+/// argument of the parse function), which can be changed using the `NomSelectorName`
+/// attribute on the enum. The fields of the variants can also use this name.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// #[NomSelectorName="len"]
+/// pub enum U6{
+///     #[Selector("x if x > 10")] Large(u16),
+///     #[Selector("_")] Small(#[Count="len"] Vec<u8>),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01";
+/// assert_eq!(U6::parse(input, 11), Ok((&input[2..],U6::Large(1))));
+/// assert_eq!(U6::parse(input, 2), Ok((&input[2..],U6::Small(vec![0, 1]))));
+/// # }
+/// ```
+///
+/// ## Bitmask selectors
+///
+/// If the value of a `Selector` starts with `&`, it is a bitmask test applied to the
+/// selector value (for ex. `& 0x80 != 0`), and not a pattern. The variants are tested in
+/// order, so the first matching variant is used.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U3{
+///     #[Selector("& 0x80 != 0")] Long(u32),
+///     #[Selector("_")] Short(u8),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x02";
+/// let res = U3::parse(input, 0x81);
+/// assert_eq!(res, Ok((&input[4..],U3::Long(2))));
+/// let res = U3::parse(input, 0x01);
+/// assert_eq!(res, Ok((&input[1..],U3::Short(0))));
+/// # }
+/// ```
+///
+/// ## Constant selectors
+///
+/// If the value of a `Selector` is a constant (for ex. `MAX`, or an associated constant like
+/// `Kind::DATA`), it is not used as a pattern: the selector is compared to it using `==` (in
+/// a match guard). The selector type must implement `PartialEq`, but does not need to be
+/// usable in patterns (it does not need to derive `PartialEq` and `Eq`). This also applies
+/// to several constants (for ex. `Kind::DATA | Kind::ACK`).
+///
+/// Constants are recognized by their name, which must be in upper case (for ex. `DATA`).
+/// Other paths, like enum variants (`Kind::Data`) or `true`, are still used as patterns, so
+/// they do not require `PartialEq`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// pub struct Kind(u8);
+///
+/// impl Kind {
+///     pub const DATA: Kind = Kind(1);
+/// }
+///
+/// // manual implementation: Kind cannot be used in patterns
+/// impl PartialEq for Kind {
+///     fn eq(&self, other: &Kind) -> bool { self.0 == other.0 }
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="Kind"]
+/// pub enum U4{
+///     #[Selector("Kind::DATA")] Data(u16),
+///     #[Selector("_")] Other(u8),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x01";
+/// let res = U4::parse(input, Kind(1));
+/// assert_eq!(res, Ok((&input[2..],U4::Data(1))));
+/// # }
+/// ```
+///
+/// ## Variant preambles
+///
+/// The `Skip` and `Tag` attributes can also be set on a variant: the bytes are skipped (or
+/// checked) before parsing the fields of this variant only.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U {
+///     #[Selector("0")] Short(u8),
+///     #[Selector("1")]
+///     #[Skip="2"]
+///     Long(u32),
+///     #[Selector("2")]
+///     #[Tag=b"\x00\x01"]
+///     Versioned(u8),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\xff\xff\x00\x00\x00\x02";
+/// assert_eq!(U::parse(input, 0), Ok((&input[1..],U::Short(0xff))));
+/// assert_eq!(U::parse(input, 1), Ok((&input[6..],U::Long(2))));
+/// let input = b"\x00\x01\x03";
+/// assert_eq!(U::parse(input, 2), Ok((&input[3..],U::Versioned(3))));
+/// assert!(U::parse(b"\x00\x02\x03", 2).is_err());
+/// # }
+/// ```
+///
+/// ## Getting the selector of a value
+///
+/// The `NomSelectorFn` attribute on the enum generates a `selector` function, returning
+/// the selector value of the variant of an instance (for ex. to serialize it again). The
+/// selector of each variant must be a single value: wildcards, ranges, several values,
+/// guards and bitmasks are rejected.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// #[NomSelectorFn]
+/// pub enum U {
+///     #[Selector("0")] Short(u8),
+///     #[Selector("1")] Long(u32),
+/// }
+/// #
+/// # fn main() {
+/// let input = b"\x00\x00\x00\x02";
+/// let (_, value) = U::parse(input, 1).unwrap();
+/// assert_eq!(value.selector(), 1);
+/// # }
+/// ```
+///
+/// ## Special case: specifying parsers for fields
 ///
 /// Sometimes, an unnamed field requires a custom parser. In that case, the
 /// *field* (not the variant) must be annotated with attribute `Parse`.
@@ -471,6 +2748,36 @@ use enums::impl_nom_enums;
 /// }
 /// ```
 ///
+/// ## Special case: using the length of the input
+///
+/// Variants often contain "the rest of the value", which can only be parsed if its length
+/// is known. In the generated enum parser, the length of the input (when entering the
+/// parser) is bound to the `__rest_len` variable, and can be used in field attributes:
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// pub enum U4{
+///     #[Selector("0")] Field1(u32),
+///     #[Selector("1")] Field2(
+///         #[Count="__rest_len / 2"] Vec<u16>
+///     ),
+/// }
+/// #
+/// # fn main() {
+/// # let input = b"\x00\x01\x00\x02";
+/// # let res = U4::parse(input, 1);
+/// # assert_eq!(res, Ok((&input[4..],U4::Field2(vec![1,2]))));
+/// # }
+/// ```
+///
+/// Usually, the input given to the enum parser is restricted to the value, for ex. using
+/// `#[Parse="flat_map!(take!(len),call!(U4::parse,sel))"]` in the parent structure.
+///
 /// ## Special case: fieldless enums
 ///
 /// If the entire enum is fieldless (a list of constant integer values), a
@@ -515,74 +2822,360 @@ use enums::impl_nom_enums;
 ///
 /// For ex, `U3::parse(b"\x02")` will return `Ok((&b""[..],U3::B))`.
 ///
+/// The `LittleEndian` attribute (or the `NomLE` derive) can be used to parse the value
+/// as Little Endian:
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[repr(u16)]
+/// #[derive(Eq,Nom)]
+/// #[LittleEndian]
+/// pub enum U5{
+///     A = 1,
+///     B = 0x0102,
+/// }
+/// #
+/// # fn main() {
+/// # let empty : &[u8] = b"";
+/// assert_eq!(U5::parse(b"\x02\x01"), Ok((empty,U5::B)));
+/// # }
+/// ```
+///
+/// The `NomVarintTag` attribute reads the value as a LEB128 varint instead (for ex.
+/// protobuf enums): 7 bits per byte, least significant bits first, and the high bit set
+/// if more bytes follow. Negative values are sign-extended to 64 bits before encoding.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[repr(u32)]
+/// #[derive(Eq,Nom)]
+/// #[NomVarintTag]
+/// pub enum U6{
+///     A = 1,
+///     B = 300,
+/// }
+/// #
+/// # fn main() {
+/// # let empty : &[u8] = b"";
+/// assert_eq!(U6::parse(b"\xac\x02"), Ok((empty,U6::B)));
+/// # }
+/// ```
+///
+/// ## Special case: enums without selector
+///
+/// If the variant cannot be determined from a selector, the `NomAlt` attribute can be set
+/// on the enum (instead of `Selector`): the variants are tried in order, and the first one
+/// which can be parsed is returned. The `parse` function does not take a selector.
+///
+/// One variant can have the `Selector("_")` attribute: it is the fallback, tried after all
+/// other variants. If it always succeeds (for ex. it stores the raw bytes), parsing the enum
+/// cannot fail. Without a fallback, an error is returned if no variant can be parsed.
+///
+/// Only errors cause the next variant to be tried: incomplete input or failures are
+/// returned immediately, like `alt!`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomAlt]
+/// pub enum Value {
+///     Small{
+///         #[Verify="v < 0x80"]
+///         v: u8
+///     },
+///     Large{
+///         #[Verify="(0x8000..0xc000).contains(&v)"]
+///         v: u16
+///     },
+///     #[Selector("_")]
+///     Raw(Vec<u8>),
+/// }
+/// #
+/// # fn main() {
+/// let empty : &[u8] = b"";
+/// assert_eq!(Value::parse(b"\x01"), Ok((empty,Value::Small{v:1})));
+/// assert_eq!(Value::parse(b"\x80\x01"), Ok((empty,Value::Large{v:0x8001})));
+/// assert_eq!(Value::parse(b"\xc0\x01"), Ok((empty,Value::Raw(vec![0xc0,0x01]))));
+/// # }
+/// ```
+///
+/// ## Function pointers
+///
+/// The signature of the `parse` function depends on the type (enums with a selector take
+/// an additional argument), so they cannot always be stored in the same table of function
+/// pointers. The `ParseErased` attribute generates an additional function, with the same
+/// signature for all types: `fn parse_erased(i: &[u8]) -> IResult<&[u8],Self>`.
+///
+/// For structs and fieldless enums, the attribute takes no value. For enums with a selector,
+/// the value of the attribute is the default selector given to `parse`.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[ParseErased]
+/// struct S{
+///     pub a: u8,
+/// }
+///
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[Selector="u8"]
+/// #[ParseErased="1"]
+/// pub enum U4{
+///     #[Selector("0")] Field1(u32),
+///     #[Selector("1")] Field2(u16),
+/// }
+/// #
+/// # fn main() {
+/// let parse_s : fn(&[u8]) -> IResult<&[u8],S> = S::parse_erased;
+/// let parse_u4 : fn(&[u8]) -> IResult<&[u8],U4> = U4::parse_erased;
+/// # let input = b"\x00\x01";
+/// # assert_eq!(parse_s(input), Ok((&input[1..],S{a:0})));
+/// # assert_eq!(parse_u4(input), Ok((&input[2..],U4::Field2(1))));
+/// # }
+/// ```
+///
+/// ## Async parsing
+///
+/// The `NomAsync` attribute generates an additional function,
+/// `async fn parse_async(i: Vec<u8>) -> Result<(Vec<u8>,Self),nom::Err<Vec<u8>>>`, for
+/// buffers already collected from an async byte stream. The input, the remaining bytes and
+/// the errors are owned, so the returned future does not borrow anything and can be held
+/// across `.await` points or sent to another task. The parsing itself never waits.
+///
+/// The attribute takes the same values as `ParseErased`, and cannot be used on types with
+/// lifetimes.
+///
+/// ```rust
+/// # use nom_derive::Nom;
+/// # use nom::*;
+/// #
+/// # #[derive(Debug,PartialEq)] // for assert_eq!
+/// #[derive(Nom)]
+/// #[NomAsync]
+/// struct S{
+///     pub a: u8,
+/// }
+///
+/// async fn read_s(data: Vec<u8>) -> Result<S,Err<Vec<u8>>> {
+///     let (_, s) = S::parse_async(data).await?;
+///     Ok(s)
+/// }
+/// ```
+///
 /// ## Limitations
 ///
-/// Except if the entire enum is fieldless (a list of constant integer values),
-/// unit fields are not supported.
-#[proc_macro_derive(Nom, attributes(Parse,Verify,Cond,Count,Selector))]
+/// Unit structures are not supported. Unit variants are supported in enums with a
+/// selector (no input is read for them), and in fieldless enums (a list of constant integer
+/// values).
+#[proc_macro_derive(Nom, attributes(Parse,Verify,Cond,Count,Selector,LittleEndian,BigEndian,Into,TryInto,Skip,Ignore,BoundingLength,SetEndian,UntilOffset,Map,ParseErased,NomBothEndian,NomResumable,DynamicEndian,Fold,NomEndianAware,Default,AsciiInt,NomByteOrderMark,LengthType,NomAlt,NomParseName,NativeEndian,Columnar,Checksum,Tag,NomMaxLen,Magic,VerifyError,Strict,RunLength,NomErr,ParseOuter,BoolWidth,Utf8,Nibbles,Width,NomVersionField,Since,Until,NomTraceErrors,TakeWhile,NomSelectorName,NomUnsafeFastPath,NomVarintTag,TakeUntil,NomSelectorFn,NomGroup,Match,Rest,Monotonic,Align,NomContext,NomParseTraced,NomAsync,EndianFrom,SelfExcludingChecksum,PreExec,PostExec))]
 pub fn nom(input: TokenStream) -> TokenStream {
     // Parse the input tokens into a syntax tree
     let ast = parse_macro_input!(input as DeriveInput);
 
     // Build the impl
-    let gen = impl_nom(&ast, false);
+    let gen = impl_nom(&ast, ParserEndianness::BigEndian, false);
 
     // Return the generated impl
-    gen
+    gen.into()
 }
 
-fn impl_nom(ast: &syn::DeriveInput, debug:bool) -> TokenStream {
+fn impl_nom(ast: &syn::DeriveInput, endianness: ParserEndianness, debug:bool) -> proc_macro2::TokenStream {
     // eprintln!("ast: {:#?}", ast);
+    let endianness = get_object_endianness(&ast.attrs, endianness);
     // test if struct has a lifetime
     let s =
-        match &ast.data {
-            &syn::Data::Enum(_)       => { return impl_nom_enums(ast, debug); },
-            &syn::Data::Struct(ref s) => parse_struct(s),
-            &syn::Data::Union(_)       => panic!("Unions not supported"),
+        match ast.data {
+            syn::Data::Enum(_)       => { return impl_nom_enums(ast, endianness, debug); },
+            syn::Data::Struct(ref s) => s,
+            syn::Data::Union(_)       => panic!("Unions not supported"),
     };
     // parse string items and prepare tokens for each field parser
-    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
     let name = &ast.ident;
-    let (idents,parser_tokens) : (Vec<_>,Vec<_>) = s.parsers.iter()
-        .map(|(name,parser)| {
-            let id = syn::Ident::new(name, Span::call_site());
-            (id,parser)
-        })
-        .unzip();
-    let idents2 = idents.clone();
     // Code generation
-    let struct_def = match s.unnamed {
-        false => quote!{ ( #name { #(#idents2),* } ) },
-        true  => quote!{ ( #name ( #(#idents2),* ) ) },
-    };
-    let tokens = quote! {
-        impl#generics #name#generics {
-            fn parse(i: &[u8]) -> IResult<&[u8],#name> {
-                do_parse!{
-                    i,
-                    #(#idents: #parser_tokens >>)*
-                    #struct_def
-                }
+    let fast_path = is_unsafe_fast_path(&ast.attrs);
+    let parse_functions = gen_parse_functions(
+        ast, endianness, &quote!{}, &quote!{},
+        |endianness| {
+            if fast_path {
+                gen_fast_path(ast, s, endianness)
+            } else if let Some(call) = gen_default_context_call(ast) {
+                call
+            } else {
+                parse_struct(s, &ast.attrs, endianness).gen_do_parse(&quote!{ #name })
             }
+        });
+    let parse_erased = gen_parse_erased(ast, false);
+    let parse_async = gen_parse_async(ast, false);
+    let (state_def, parse_resumable) = gen_parse_resumable(ast, s, endianness);
+    let parse_columnar = gen_parse_columnar(ast, s, endianness);
+    let parse_trace = gen_parse_trace(ast, s, endianness);
+    let parse_with_context = gen_parse_with_context(ast, s, endianness);
+    let parse_traced = gen_parse_traced(ast, s, endianness);
+    let group_label = gen_group_label(s);
+    let tokens = quote! {
+        impl#impl_generics #name#ty_generics #where_clause {
+            #parse_functions
+            #parse_erased
+            #parse_async
+            #parse_resumable
+            #parse_columnar
+            #parse_trace
+            #parse_with_context
+            #parse_traced
+            #group_label
         }
+        #state_def
     };
     if debug {
         eprintln!("tokens:\n{}", tokens);
+        for (group, steps) in parse_struct(s, &ast.attrs, endianness).groups {
+            eprintln!("group {}: {}", group, steps.join(", "));
+        }
     }
-    tokens.into()
+    tokens
 }
 
 /// This derive macro behaves exactly like [Nom derive](derive.Nom.html), except it
 /// prints the generated parser on stderr.
 /// This is helpful for debugging generated parsers.
-#[proc_macro_derive(NomDeriveDebug, attributes(Parse,Verify,Cond,Count,Selector))]
+#[proc_macro_derive(NomDeriveDebug, attributes(Parse,Verify,Cond,Count,Selector,LittleEndian,BigEndian,Into,TryInto,Skip,Ignore,BoundingLength,SetEndian,UntilOffset,Map,ParseErased,NomBothEndian,NomResumable,DynamicEndian,Fold,NomEndianAware,Default,AsciiInt,NomByteOrderMark,LengthType,NomAlt,NomParseName,NativeEndian,Columnar,Checksum,Tag,NomMaxLen,Magic,VerifyError,Strict,RunLength,NomErr,ParseOuter,BoolWidth,Utf8,Nibbles,Width,NomVersionField,Since,Until,NomTraceErrors,TakeWhile,NomSelectorName,NomUnsafeFastPath,NomVarintTag,TakeUntil,NomSelectorFn,NomGroup,Match,Rest,Monotonic,Align,NomContext,NomParseTraced,NomAsync,EndianFrom,SelfExcludingChecksum,PreExec,PostExec))]
 pub fn nom_derive_debug(input: TokenStream) -> TokenStream {
     // Parse the input tokens into a syntax tree
     let ast = parse_macro_input!(input as DeriveInput);
 
     // Build the impl
-    let gen = impl_nom(&ast, true);
+    let gen = impl_nom(&ast, ParserEndianness::BigEndian, true);
+
+    // Return the generated impl
+    gen.into()
+}
+
+/// This derive macro behaves like [Nom derive](derive.Nom.html), except that the default
+/// byte order is little-endian (for structs, enum variants and fieldless enums).
+#[proc_macro_derive(NomLE, attributes(Parse,Verify,Cond,Count,Selector,LittleEndian,BigEndian,Into,TryInto,Skip,Ignore,BoundingLength,SetEndian,UntilOffset,Map,ParseErased,NomBothEndian,NomResumable,DynamicEndian,Fold,NomEndianAware,Default,AsciiInt,NomByteOrderMark,LengthType,NomAlt,NomParseName,NativeEndian,Columnar,Checksum,Tag,NomMaxLen,Magic,VerifyError,Strict,RunLength,NomErr,ParseOuter,BoolWidth,Utf8,Nibbles,Width,NomVersionField,Since,Until,NomTraceErrors,TakeWhile,NomSelectorName,NomUnsafeFastPath,NomVarintTag,TakeUntil,NomSelectorFn,NomGroup,Match,Rest,Monotonic,Align,NomContext,NomParseTraced,NomAsync,EndianFrom,SelfExcludingChecksum,PreExec,PostExec))]
+pub fn nom_le(input: TokenStream) -> TokenStream {
+    // Parse the input tokens into a syntax tree
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    // Build the impl
+    let gen = impl_nom(&ast, ParserEndianness::LittleEndian, false);
+
+    // Return the generated impl
+    gen.into()
+}
+
+/// This derive macro behaves like [Nom derive](derive.Nom.html), except that the default
+/// byte order is explicitly big-endian.
+#[proc_macro_derive(NomBE, attributes(Parse,Verify,Cond,Count,Selector,LittleEndian,BigEndian,Into,TryInto,Skip,Ignore,BoundingLength,SetEndian,UntilOffset,Map,ParseErased,NomBothEndian,NomResumable,DynamicEndian,Fold,NomEndianAware,Default,AsciiInt,NomByteOrderMark,LengthType,NomAlt,NomParseName,NativeEndian,Columnar,Checksum,Tag,NomMaxLen,Magic,VerifyError,Strict,RunLength,NomErr,ParseOuter,BoolWidth,Utf8,Nibbles,Width,NomVersionField,Since,Until,NomTraceErrors,TakeWhile,NomSelectorName,NomUnsafeFastPath,NomVarintTag,TakeUntil,NomSelectorFn,NomGroup,Match,Rest,Monotonic,Align,NomContext,NomParseTraced,NomAsync,EndianFrom,SelfExcludingChecksum,PreExec,PostExec))]
+pub fn nom_be(input: TokenStream) -> TokenStream {
+    // Parse the input tokens into a syntax tree
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    // Build the impl
+    let gen = impl_nom(&ast, ParserEndianness::BigEndian, false);
 
     // Return the generated impl
-    gen
+    gen.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains_unsafe(tokens: proc_macro2::TokenStream) -> bool {
+        tokens.into_iter().any(|tt| match tt {
+            proc_macro2::TokenTree::Ident(ref id) => id == "unsafe",
+            proc_macro2::TokenTree::Group(ref g) => contains_unsafe(g.stream()),
+            _ => false
+        })
+    }
+
+    /// Derive the parsers of the type, and test if the generated code contains `unsafe`
+    fn derive_contains_unsafe(input: &str) -> bool {
+        let ast : DeriveInput = syn::parse_str(input).expect("invalid test input");
+        contains_unsafe(impl_nom(&ast, ParserEndianness::BigEndian, false))
+    }
+
+    // `#![forbid(unsafe_code)]` does not apply to the code generated by derive macros, so
+    // the generated tokens are checked instead
+    #[test]
+    fn test_generated_code_without_unsafe() {
+        let inputs = [
+            r#"#[Magic(b"HDR")]
+            struct Header<'a> {
+                pub a: u16,
+                #[LittleEndian] pub b: u32,
+                pub flag: bool,
+                pub c: Option<u8>,
+                #[Count="2"] pub d: Vec<u16>,
+                pub e: [u8; 2],
+                pub f: [u16; 2],
+                #[LengthType="u8"] pub name: String,
+                #[Parse="take!(2)"] #[Verify="data[0] == 0"] pub data: &'a [u8],
+                #[Checksum(algo="sum8", over="data")] pub sum: u8,
+                #[SelfExcludingChecksum="crc32"] pub crc: u32,
+            }"#,
+            r#"#[Selector="u8"]
+            #[ParseErased="0"]
+            #[NomAsync="0"]
+            enum Message {
+                #[Selector("0")] Empty(u8),
+                #[Selector("1..=3")] Value{ v: u16 },
+            }"#,
+            r#"#[NomAlt]
+            enum Alt {
+                A(#[Tag=b"\x01"] (), u8),
+                B(u16),
+            }"#,
+            r#"#[repr(u8)]
+            enum Kind { A = 1, B = 2 }"#,
+            r#"#[NomResumable]
+            #[NomTraceErrors]
+            #[NomParseTraced]
+            #[Columnar]
+            struct Record {
+                pub a: u16,
+                pub b: f32,
+            }"#,
+            r#"#[NomByteOrderMark(le="b\"II\"", be="b\"MM\"")]
+            #[NomErr="u32"]
+            struct Tiff {
+                pub version: u16,
+                #[RunLength] pub data: Vec<u8>,
+            }"#,
+            r#"#[NomContext="usize"]
+            struct WithContext {
+                #[SetEndian="flags & 1 != 0"] pub flags: u8,
+                #[BoundingLength] pub len: u8,
+                #[Align=4] pub a: u32,
+                #[UntilOffset="len"] pub items: Vec<u8>,
+                #[PreExec="let start = i_;"] #[Nibbles] pub hi: u8,
+                #[Nibbles] #[PostExec="let n = start.len() - i_.len();"] pub lo: u8,
+                pub c: char,
+                #[Default="n"] pub n: usize,
+            }"#,
+        ];
+        for input in inputs.iter() {
+            assert!(!derive_contains_unsafe(input), "unsafe code generated for {}", input);
+        }
+    }
+
+    #[test]
+    fn test_fast_path_with_unsafe() {
+        assert!(derive_contains_unsafe("#[NomUnsafeFastPath] struct S { a: u16, b: u32 }"));
+    }
 }
@@ -4,30 +4,291 @@ use quote::ToTokens;
 #[derive(Debug)]
 pub enum ParserTree {
     Cond(Box<ParserTree>, String),
-    Verify(Box<ParserTree>, String, String),
+    /// Like `Cond`, but the errors of the parser are returned (instead of `None`)
+    CondWithError(Box<ParserTree>, String),
+    Verify(Box<ParserTree>, String, String, String),
     Complete(Box<ParserTree>),
     Opt(Box<ParserTree>),
     Many0(Box<ParserTree>),
     CallParse(String),
+    CallParseSelector(String, String),
+    CallParseEndian(String),
     Count(Box<ParserTree>, String),
+    UntilOffset(Box<ParserTree>, String),
+    Array(Box<ParserTree>, String),
+    Map(Box<ParserTree>, String),
+    Pair(Box<ParserTree>, Box<ParserTree>),
+    /// The elements of a tuple, parsed in order
+    Tuple(Vec<ParserTree>),
+    Fold(Box<ParserTree>, Option<String>, String, String),
+    Take(String),
+    AsciiInt(String, String),
+    LengthString(Box<ParserTree>),
+    MapRes(Box<ParserTree>, String),
+    /// (count, value) byte pairs, expanded until a zero count, or until the given length
+    RunLength(Option<String>),
+    /// A `usize` or `isize`, stored in an integer (parser, type) given by the `Width` attribute
+    SizeInt(String, Option<(String, String)>),
+    /// A `char` encoded in UTF-8 (1 to 4 bytes)
+    Utf8Char,
+    /// Padding bytes, until the offset (relative to the input of the parse function) is a
+    /// multiple of the given alignment
+    Align(String),
+    /// An unsigned LEB128 varint (1 to 10 bytes), read as a `u64`
+    Varint,
+    /// A `bool` stored in an integer (parser, type): non-zero values are true. If strict,
+    /// values other than 0 and 1 are rejected
+    Bool(String, String, bool),
     Raw(String)
 }
 
+impl ParserTree {
+    /// Call `f` on the nodes of the tree which do not contain other parsers
+    pub fn for_each_leaf<F: FnMut(&mut ParserTree)>(&mut self, f: &mut F) {
+        match self {
+            ParserTree::Cond(p, _) |
+            ParserTree::CondWithError(p, _) |
+            ParserTree::Verify(p, _, _, _) |
+            ParserTree::Complete(p) |
+            ParserTree::Opt(p) |
+            ParserTree::Many0(p) |
+            ParserTree::Count(p, _) |
+            ParserTree::UntilOffset(p, _) |
+            ParserTree::Array(p, _) |
+            ParserTree::Map(p, _) |
+            ParserTree::Fold(p, _, _, _) |
+            ParserTree::LengthString(p) |
+            ParserTree::MapRes(p, _) => p.for_each_leaf(f),
+            ParserTree::Pair(a, b) => {
+                a.for_each_leaf(f);
+                b.for_each_leaf(f);
+            },
+            ParserTree::Tuple(v) => v.iter_mut().for_each(|p| p.for_each_leaf(f)),
+            p => f(p)
+        }
+    }
+
+    /// Make the `bool` parsers of the tree strict. Returns false if there is none
+    pub fn set_strict(&mut self) -> bool {
+        let mut found = false;
+        self.for_each_leaf(&mut |p| {
+            if let ParserTree::Bool(_, _, ref mut strict) = p {
+                *strict = true;
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// Read the `bool` values of the tree from an integer of type `ty`, using the parser `p`.
+    /// Returns false if there is none
+    pub fn set_bool_width(&mut self, p: &str, ty: &str) -> bool {
+        let mut found = false;
+        self.for_each_leaf(&mut |node| {
+            if let ParserTree::Bool(ref mut p_, ref mut ty_, _) = node {
+                *p_ = p.to_owned();
+                *ty_ = ty.to_owned();
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// Read the `usize` and `isize` values of the tree from integers of `width` bytes, using
+    /// the parsers given by `parser` (for an integer type). Returns false if there is none
+    pub fn set_width<F: Fn(&str) -> String>(&mut self, width: &str, parser: F) -> bool {
+        let mut found = false;
+        self.for_each_leaf(&mut |node| {
+            if let ParserTree::SizeInt(ref ty, ref mut int) = node {
+                let int_ty = match (ty.as_ref(), width) {
+                    ("usize", "1") => "u8",
+                    ("usize", "2") => "u16",
+                    ("usize", "4") => "u32",
+                    ("usize", "8") => "u64",
+                    ("isize", "1") => "i8",
+                    ("isize", "2") => "i16",
+                    ("isize", "4") => "i32",
+                    ("isize", "8") => "i64",
+                    _ => panic!("Invalid 'Width' attribute value {} (expected 1, 2, 4 or 8)", width)
+                };
+                *int = Some((parser(int_ty), int_ty.to_owned()));
+                found = true;
+            }
+        });
+        found
+    }
+
+    /// Test if the tree contains `usize` or `isize` values without a width
+    pub fn has_unsized_int(&mut self) -> bool {
+        let mut found = false;
+        self.for_each_leaf(&mut |node| {
+            if let ParserTree::SizeInt(_, None) = node {
+                found = true;
+            }
+        });
+        found
+    }
+}
+
 impl fmt::Display for ParserTree {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             ParserTree::Cond(p, c)      => write!(f, "cond!({}, {})", c, p),
-            ParserTree::Verify(p, i, c) => write!(f, "verify!({}, |{}| {{ {} }})", p, i, c),
+            ParserTree::CondWithError(p, c) => write!(f, "cond_with_error!({}, {})", c, p),
+            ParserTree::Verify(p, i, c, e) => {
+                // like verify!, but the value is bound (not moved) so non-Copy types can be used
+                write!(f, "call!(|i_| {{ let i_: &[u8] = i_; \
+                          match map!(i_, {p}, |x| x) {{ \
+                            Ok((rem, {i})) => if {{ {c} }} {{ Ok((rem, {i})) }} \
+                              else {{ Err(nom::Err::Error(error_position!(i_, {e}))) }}, \
+                            Err(e) => Err(e) \
+                          }} }})", p=p, i=i, c=c, e=e)
+            },
             ParserTree::Complete(p)     => write!(f, "complete!({})", p),
             ParserTree::Opt(p)          => write!(f, "opt!({})", p),
             ParserTree::Many0(p)        => write!(f, "many0!({})", p),
             ParserTree::CallParse(s)    => write!(f, "call!({}::parse)", s),
-            ParserTree::Count(s,n)      => write!(f, "count!({}, {{ {} }} as usize)", s, n),
+            ParserTree::CallParseSelector(s, sel) => write!(f, "call!({}::parse, {})", s, sel),
+            ParserTree::CallParseEndian(s) => write!(f, "call!({}::parse_endian, __endianness)", s),
+            ParserTree::Count(s,n)      => fmt_count(f, s, n),
+            ParserTree::UntilOffset(p, o) => {
+                // the offset is relative to the input of the parse function (`__input`)
+                write!(f, "call!(|i_| {{ let i_: &[u8] = i_; \
+                          let __offset = __input.len() - i_.len(); \
+                          let __end = {{ {o} }} as usize; \
+                          if __end < __offset {{ return Err(nom::Err::Error(error_position!(i_, nom::ErrorKind::Verify))); }} \
+                          if __end > __input.len() {{ return Err(nom::Err::Error(error_position!(i_, nom::ErrorKind::Eof))); }} \
+                          let (__window, __rest) = i_.split_at(__end - __offset); \
+                          match map!(__window, {p}, |x| x) {{ \
+                            Ok((_, v)) => Ok((__rest, v)), \
+                            Err(e) => Err(e) \
+                          }} }})", p=p, o=o)
+            },
+            ParserTree::Array(s,n)      => {
+                f.write_str("map_res!(")?;
+                fmt_count(f, s, n)?;
+                f.write_str(", ::std::convert::TryInto::try_into)")
+            },
+            ParserTree::Map(p, m)       => write!(f, "map!({}, {})", p, m),
+            ParserTree::Pair(a, b)      => write!(f, "pair!({}, {})", a, b),
+            // tuple! does not return a tuple for a single element
+            ParserTree::Tuple(v) if v.len() == 1 => write!(f, "map!({}, |x| (x,))", v[0]),
+            ParserTree::Tuple(v)        => {
+                let v : Vec<_> = v.iter().map(|p| p.to_string()).collect();
+                write!(f, "tuple!({})", v.join(", "))
+            },
+            ParserTree::Fold(p, None, init, g) => write!(f, "fold_many0!(complete!({}), {}, {})", p, init, g),
+            ParserTree::Fold(p, Some(n), init, g) => {
+                write!(f, "fold_many_m_n!({{ {n} }} as usize, {{ {n} }} as usize, {p}, {init}, {g})", n=n, p=p, init=init, g=g)
+            },
+            ParserTree::Take(n)         => write!(f, "take!({{ {} }} as usize)", n),
+            ParserTree::AsciiInt(n, t)  => {
+                // parse digits, and check for overflows
+                write!(f, "map_res!(take!({{ {n} }} as usize), |s: &[u8]| \
+                          s.iter().try_fold(0 as {t}, |acc, c| \
+                            if c.is_ascii_digit() {{ acc.checked_mul(10).and_then(|a| a.checked_add((c - b'0') as {t})) }} \
+                            else {{ None }}) \
+                          .ok_or(()))", n=n, t=t)
+            },
+            ParserTree::LengthString(p) => {
+                // invalid UTF-8 sequences are replaced, instead of returning an error
+                write!(f, "map!(length_data!({}), |b: &[u8]| String::from_utf8_lossy(b).into_owned())", p)
+            },
+            ParserTree::MapRes(p, m)    => write!(f, "map_res!({}, {})", p, m),
+            ParserTree::RunLength(None) => {
+                // the terminator is a single zero byte
+                f.write_str("call!(|i_| { let i_: &[u8] = i_; \
+                             let mut __items = Vec::new(); \
+                             let mut __rem = i_; \
+                             loop { \
+                               let (rem, n) = match be_u8(__rem) { Ok(r) => r, Err(e) => return Err(e) }; \
+                               if n == 0 { return Ok((rem, __items)); } \
+                               let (rem, v) = match be_u8(rem) { Ok(r) => r, Err(e) => return Err(e) }; \
+                               __items.extend(::std::iter::repeat(v).take(n as usize)); \
+                               __rem = rem; \
+                             } })")
+            },
+            ParserTree::RunLength(Some(len)) => {
+                // a run must not exceed the declared length
+                write!(f, "call!(|i_| {{ let i_: &[u8] = i_; \
+                          let __len = {{ {len} }} as usize; \
+                          let mut __items = Vec::new(); \
+                          let mut __rem = i_; \
+                          while __items.len() < __len {{ \
+                            let (rem, n) = match be_u8(__rem) {{ Ok(r) => r, Err(e) => return Err(e) }}; \
+                            let (rem, v) = match be_u8(rem) {{ Ok(r) => r, Err(e) => return Err(e) }}; \
+                            if __items.len() + n as usize > __len {{ \
+                              return Err(nom::Err::Error(error_position!(__rem, nom::ErrorKind::Verify))); \
+                            }} \
+                            __items.extend(::std::iter::repeat(v).take(n as usize)); \
+                            __rem = rem; \
+                          }} \
+                          Ok((__rem, __items)) }})", len=len)
+            },
+            ParserTree::SizeInt(ty, Some((p, t))) => {
+                // the conversion fails if the value does not fit
+                write!(f, "map_res!({}, <{} as ::std::convert::TryFrom<{}>>::try_from)", p, ty, t)
+            },
+            ParserTree::SizeInt(ty, None) => panic!("Nom-derive: {} values have no fixed size, use the 'Width' attribute", ty),
+            ParserTree::Utf8Char        => {
+                // the length of the sequence is given by the first byte
+                f.write_str("call!(|i_| { let i_: &[u8] = i_; \
+                             let __len = match i_.first() { \
+                               None => return Err(nom::Err::Incomplete(nom::Needed::Size(1))), \
+                               Some(b) if *b < 0x80 => 1, \
+                               Some(b) if *b >= 0xf0 => 4, \
+                               Some(b) if *b >= 0xe0 => 3, \
+                               Some(_) => 2 \
+                             }; \
+                             if i_.len() < __len { return Err(nom::Err::Incomplete(nom::Needed::Size(__len))); } \
+                             match ::std::str::from_utf8(&i_[..__len]).ok().and_then(|s| s.chars().next()) { \
+                               Some(c) => Ok((&i_[__len..], c)), \
+                               None => Err(nom::Err::Error(error_position!(i_, nom::ErrorKind::Char))) \
+                             } })")
+            },
+            ParserTree::Align(n)        => {
+                // the offset is relative to the input of the parse function (`__input`)
+                write!(f, "call!(|i_| {{ let i_: &[u8] = i_; \
+                          let __align = {{ {n} }} as usize; \
+                          let __offset = __input.len() - i_.len(); \
+                          take!(i_, (__align - __offset % __align) % __align) }})", n=n)
+            },
+            ParserTree::Varint          => {
+                // 7 bits per byte, least significant group first, high bit set if more bytes follow
+                f.write_str("call!(|i_| { let i_: &[u8] = i_; \
+                             let mut __value = 0u64; \
+                             for (__n, b) in i_.iter().enumerate() { \
+                               if __n == 9 && *b > 1 { return Err(nom::Err::Error(error_position!(i_, nom::ErrorKind::TooLarge))); } \
+                               __value |= u64::from(*b & 0x7f) << (7 * __n); \
+                               if *b & 0x80 == 0 { return Ok((&i_[__n + 1..], __value)); } \
+                             } \
+                             Err(nom::Err::Incomplete(nom::Needed::Size(i_.len() + 1))) })")
+            },
+            ParserTree::Bool(p, t, false) => write!(f, "map!({p}, |b: {t}| b != 0)", p=p, t=t),
+            ParserTree::Bool(p, t, true) => write!(f, "map!(verify!({p}, |b: {t}| b < 2), |b: {t}| b != 0)", p=p, t=t),
             ParserTree::Raw(s)          => f.write_str(s)
         }
     }
 }
 
+/// Parse `n` items using `p`
+///
+/// Like `count!`, but the error of the failing item is returned unchanged (`count!` replaces
+/// it by an `ErrorKind::Count` error at the start of the input), so its position is kept.
+fn fmt_count(f: &mut fmt::Formatter, p: &ParserTree, n: &str) -> fmt::Result {
+    write!(f, "call!(|i_| {{ let i_: &[u8] = i_; \
+              let __n = {{ {n} }} as usize; \
+              let mut __items = Vec::new(); \
+              let mut __rem = i_; \
+              for _ in 0..__n {{ \
+                match map!(__rem, {p}, |x| x) {{ \
+                  Ok((rem, v)) => {{ __items.push(v); __rem = rem; }}, \
+                  Err(e) => return Err(e) \
+                }} \
+              }} \
+              Ok((__rem, __items)) }})", p=p, n=n)
+}
+
 impl ToTokens for ParserTree {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let s = format!("{}",self);
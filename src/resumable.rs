@@ -0,0 +1,77 @@
+use syn::export::Span;
+
+use crate::endian::{is_dynamic_endian,ParserEndianness};
+use crate::structs::parse_struct;
+
+fn is_resumable(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomResumable",
+            _ => false
+        }
+    })
+}
+
+/// Generate the resume state structure, and the `parse_resumable` function (if requested)
+///
+/// The state stores the offset of the next field in the input, and the values of the fields
+/// already parsed. Each call parses the fields which are not in the state yet, so parsing
+/// resumes at the first field which could not be parsed.
+pub(crate) fn gen_parse_resumable(ast: &syn::DeriveInput, s: &syn::DataStruct, endianness: ParserEndianness) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    if !is_resumable(&ast.attrs) {
+        return (quote!{}, quote!{});
+    }
+    if !ast.generics.params.is_empty() {
+        panic!("Nom-derive: the 'NomResumable' attribute is not supported for generic types");
+    }
+    if is_dynamic_endian(&ast.attrs) {
+        panic!("Nom-derive: the 'NomResumable' and 'DynamicEndian' attributes are mutually exclusive");
+    }
+    let tree = parse_struct(s, &ast.attrs, endianness);
+    // only fields can be stored in the state
+    let parsed_fields : Vec<_> = tree.fields.iter().zip(s.fields.iter())
+        .filter(|((_, init), _)| init.is_none())
+        .map(|((name, _), field)| (syn::Ident::new(name, Span::call_site()), &field.ty))
+        .collect();
+    if tree.parsers.len() != parsed_fields.len() || tree.bounding_length.is_some() || !tree.execs.is_empty() {
+        panic!("Nom-derive: the 'NomResumable' attribute cannot be used with 'Skip', 'Tag', 'BoundingLength', 'SetEndian', 'EndianFrom', 'PreExec', 'PostExec' or 'NomByteOrderMark'");
+    }
+    let name = &ast.ident;
+    let vis = &ast.vis;
+    let state_name = syn::Ident::new(&format!("{}ResumeState", name), Span::call_site());
+    let (fields, types) : (Vec<_>, Vec<_>) = parsed_fields.into_iter().unzip();
+    let steps : Vec<_> = tree.parsers.iter()
+        .map(|(field, parser)| {
+            let id = syn::Ident::new(field, Span::call_site());
+            quote!{
+                let #id = match state.#id {
+                    Some(ref v) => v.clone(),
+                    None        => {
+                        let i_ = &__input[state.offset..];
+                        let (rem, v) = map!(i_, #parser, |x| x)?;
+                        state.offset = __input.len() - rem.len();
+                        state.#id = Some(v.clone());
+                        v
+                    }
+                };
+            }
+        })
+        .collect();
+    let struct_def = tree.gen_struct_def(&quote!{ #name });
+    let state_def = quote!{
+        #[derive(Default)]
+        #vis struct #state_name {
+            offset: usize,
+            #(#fields: Option<#types>,)*
+        }
+    };
+    let parse_fn = quote!{
+        fn parse_resumable<'a>(i: &'a [u8], state: &mut #state_name) -> IResult<&'a [u8],#name> {
+            // the variables of the fields may shadow `i`
+            let __input = i;
+            #(#steps)*
+            Ok((&__input[state.offset..], #struct_def))
+        }
+    };
+    (state_def, parse_fn)
+}
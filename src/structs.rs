@@ -1,15 +1,144 @@
 use syn::*;
+use syn::export::Span;
 
+use crate::checksum::{add_checksum,gen_offset,gen_self_excluding_check,get_self_excluding_checksum};
+use crate::endian::{get_byte_order_mark,get_local_endianness,is_dynamic_endian,ParserEndianness};
 use crate::parsertree::ParserTree;
 
 #[derive(Debug)]
 pub(crate) struct StructParserTree{
     pub unnamed: bool,
+    /// Parsers of the `do_parse` block, with the name of the variable they are bound to
     pub parsers: Vec<(String,ParserTree)>,
+    /// Names of the fields used to build the struct, with the initializer of the fields which
+    /// are not parsed (for other fields, the variable with the same name is used)
+    pub fields: Vec<(String,Option<String>)>,
+    /// Index of the first parser restricted by a bounding length, and name of the length
+    pub bounding_length: Option<(usize,String)>,
+    /// Statements (`PreExec` and `PostExec` attributes), with the index of the parser they
+    /// are run before (or the number of parsers, if run after the last one)
+    pub execs: Vec<(usize,String)>,
+    /// Groups of consecutive fields (`NomGroup` attribute), with the names of the variables
+    /// bound by the parsers of these fields. The label of the group is added to the errors of
+    /// these parsers, if the errors can contain a list.
+    pub groups: Vec<(String,Vec<String>)>,
+    /// Parsers reading the fields (the other parsers read tags, padding, etc.), with the name
+    /// of the variable they are bound to and the name of the field
+    pub field_parsers: Vec<(String,String)>,
 }
 
-fn get_type_parser(ty: &Type) -> Option<ParserTree> {
+impl StructParserTree {
+    /// Generate the expression building the object, from the variables of the parsed fields
+    pub(crate) fn gen_struct_def(&self, name: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let fields : Vec<_> = self.fields.iter()
+            .map(|(name, init)| {
+                let id = syn::Ident::new(name, Span::call_site());
+                match (init, self.unnamed) {
+                    (None, _)           => quote!{ #id },
+                    (Some(init), false) => {
+                        let init : proc_macro2::TokenStream = init.parse().expect("invalid field initializer");
+                        quote!{ #id: #init }
+                    },
+                    (Some(init), true)  => init.parse().expect("invalid field initializer"),
+                }
+            })
+            .collect();
+        match self.unnamed {
+            false => quote!{ #name { #(#fields),* } },
+            true  => quote!{ #name ( #(#fields),* ) },
+        }
+    }
+
+    /// Generate the `do_parse` block parsing the fields, and building the object
+    /// (`name` is the name of the struct, or the path of the enum variant)
+    pub(crate) fn gen_do_parse(&self, name: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let struct_def = self.gen_struct_def(name);
+        let body = self.gen_parsers_from(&quote!{ __input }, 0, 0, &struct_def);
+        // the offsets are computed from the input of the parse function, which may be shadowed
+        // by the variable of a field named `i`
+        quote!{{
+            let __input = i;
+            #body
+        }}
+    }
+
+    /// Generate the `do_parse` block running the parsers starting at index `from`, and the
+    /// statements run before the parsers starting at index `first_exec`
+    ///
+    /// The block is split at the first statements or bounding length: the next parsers are
+    /// run in a closure, so the variables declared by the statements are visible to them.
+    fn gen_parsers_from(&self, input: &proc_macro2::TokenStream, from: usize, first_exec: usize, struct_def: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let next_exec = self.execs.iter()
+            .map(|(idx, _)| *idx)
+            .filter(|idx| *idx >= first_exec)
+            .min();
+        // the statements run before the first bounded parser are run in the sub-slice
+        let bounded = self.bounding_length.as_ref()
+            .filter(|(idx, _)| *idx > from && match next_exec {
+                Some(e) => *idx <= e,
+                None    => true,
+            });
+        let (split, rest) = match (next_exec, bounded) {
+            // parse the remaining fields in a sub-slice, and skip the bytes left
+            (_, Some((idx, len))) => {
+                let len = syn::Ident::new(len, Span::call_site());
+                let rest = self.gen_parsers_from(&quote!{ i_ }, *idx, *idx, struct_def);
+                // the input is truncated to the end of the sub-slice, so the offsets computed
+                // from `__input` in the sub-slice are still relative to its start
+                (*idx, quote!{ call!(|i_| {
+                    let i_: &[u8] = i_;
+                    let __end = (__input.len() - i_.len()).saturating_add(#len as usize);
+                    let __input = &__input[..::std::cmp::min(__end, __input.len())];
+                    flat_map!(i_, take!(#len as usize), call!(|i_| { let i_: &[u8] = i_; #rest }))
+                }) })
+            },
+            (Some(idx), None) => {
+                let stmts : Vec<proc_macro2::TokenStream> = self.execs.iter()
+                    .filter(|(e, _)| *e == idx)
+                    .map(|(_, stmts)| stmts.parse().expect("invalid statements"))
+                    .collect();
+                let rest = self.gen_parsers_from(&quote!{ i_ }, idx, idx + 1, struct_def);
+                (idx, quote!{ call!(|i_| { let i_: &[u8] = i_; #(#stmts)* #rest }) })
+            },
+            (None, None) => (self.parsers.len(), quote!{}),
+        };
+        let (idents,parser_tokens) : (Vec<_>,Vec<_>) = self.parsers[from..split].iter()
+            .map(|(name,parser)| {
+                let id = syn::Ident::new(name, Span::call_site());
+                let tokens = match self.groups.iter().find(|(_, names)| names.contains(name)) {
+                    Some((group, _)) => gen_group_context(parser, group),
+                    None             => quote!{ #parser },
+                };
+                (id,tokens)
+            })
+            .unzip();
+        if split == self.parsers.len() && rest.is_empty() {
+            quote!{
+                do_parse!{
+                    #input,
+                    #(#idents: #parser_tokens >>)*
+                    ( #struct_def )
+                }
+            }
+        } else {
+            quote!{
+                do_parse!{
+                    #input,
+                    #(#idents: #parser_tokens >>)*
+                    __rest: #rest >>
+                    ( __rest )
+                }
+            }
+        }
+    }
+}
+
+fn get_type_parser(ty: &Type, endianness: ParserEndianness) -> Option<ParserTree> {
     match ty {
+        // markers of generic types consume nothing
+        Type::Path(_) if is_phantom_data(ty) => {
+            Some(ParserTree::Raw("value!(::std::marker::PhantomData)".to_owned()))
+        },
         Type::Path(ref typepath) => {
             let path = &typepath.path;
             if path.segments.len() != 1 {
@@ -23,10 +152,28 @@ fn get_type_parser(ty: &Type) -> Option<ParserTree> {
                 "u16" |
                 "u32" |
                 "u64" |
+                "u128" |
                 "i8"  |
                 "i16" |
                 "i32" |
-                "i64"    => Some(ParserTree::Raw(format!("be_{}", ident_s))),
+                "i64" |
+                "i128" |
+                "f32" |
+                "f64"    => Some(ParserTree::Raw(endianness.primitive_parser(&ident_s))),
+                // the value is read as an integer, and must not be zero
+                "NonZeroU8" | "NonZeroU16" | "NonZeroU32" | "NonZeroU64" | "NonZeroU128" |
+                "NonZeroI8" | "NonZeroI16" | "NonZeroI32" | "NonZeroI64" | "NonZeroI128" => {
+                    let int_ty = ident_s["NonZero".len()..].to_lowercase();
+                    let p = endianness.primitive_parser(&int_ty);
+                    Some(ParserTree::Raw(format!("map_opt!({}, ::std::num::{}::new)", p, ident_s)))
+                },
+                // the size must be given by the 'Width' attribute
+                "usize" |
+                "isize"  => Some(ParserTree::SizeInt(ident_s, None)),
+                // a code point, which must be valid
+                "char"   => Some(ParserTree::Raw(format!("map_opt!({}, ::std::char::from_u32)", endianness.primitive_parser("u32")))),
+                // a single byte, non-zero values are true
+                "bool"   => Some(ParserTree::Bool(endianness.primitive_parser("u8"), "u8".to_owned(), false)),
                 "Option" => {
                     match segment.arguments {
                         PathArguments::AngleBracketed(ref ab) => {
@@ -34,7 +181,7 @@ fn get_type_parser(ty: &Type) -> Option<ParserTree> {
                             if ab.args.len() != 1 { panic!("Option type with multiple types are unsupported"); }
                             match &ab.args[0] {
                                 GenericArgument::Type(ref ty) => {
-                                    let s = get_type_parser(ty);
+                                    let s = get_type_parser(ty, endianness);
                                     // eprintln!("    recursion: {:?}", s);
                                     s.map(|x| ParserTree::Opt(Box::new(ParserTree::Complete(Box::new(x)))))
                                 },
@@ -51,7 +198,7 @@ fn get_type_parser(ty: &Type) -> Option<ParserTree> {
                             if ab.args.len() != 1 { panic!("Vec type with multiple types are unsupported"); }
                             match &ab.args[0] {
                                 GenericArgument::Type(ref ty) => {
-                                    let s = get_type_parser(ty);
+                                    let s = get_type_parser(ty, endianness);
                                     // eprintln!("    recursion: {:?}", s);
                                     s.map(|x| ParserTree::Many0(Box::new(ParserTree::Complete(Box::new(x)))))
                                 },
@@ -61,147 +208,1174 @@ fn get_type_parser(ty: &Type) -> Option<ParserTree> {
                         _ => panic!("Unsupported Vec/parameterized type"),
                     }
                 },
-                "PhantomData" => {
-                    Some(ParserTree::Raw("value!(PhantomData)".to_owned()))
-                }
+                "Box" | "Rc" | "Arc" => {
+                    // the inner value is parsed as usual, and moved to the heap
+                    let (wrap, inner) = get_pointer_type(ty).expect("invalid pointer type");
+                    get_type_parser(inner, endianness).map(|x| ParserTree::Map(Box::new(x), wrap.to_owned()))
+                },
+                "String" => {
+                    // length-prefixed UTF-8 bytes
+                    Some(ParserTree::LengthString(Box::new(ParserTree::Raw(endianness.primitive_parser("u32")))))
+                },
+                "CString" => {
+                    // NUL-terminated bytes (the terminator is consumed)
+                    Some(ParserTree::Raw("map_res!(terminated!(take_until!(\"\\x00\"), tag!(\"\\x00\")), |b: &[u8]| ::std::ffi::CString::new(b))".to_owned()))
+                },
                 s        => {
-                    Some(ParserTree::CallParse(s.to_owned()))
+                    // with a runtime endianness, the parser of the type must accept it
+                    match endianness {
+                        ParserEndianness::Runtime => Some(ParserTree::CallParseEndian(s.to_owned())),
+                        _                         => Some(ParserTree::CallParse(s.to_owned())),
+                    }
                 },
             }
         },
+        Type::Array(ref typearray) => {
+            let len = &typearray.len;
+            // byte arrays are copied from the input
+            let elem = &typearray.elem;
+            if quote!{ #elem }.to_string() == "u8" {
+                let len = quote!{ #len }.to_string();
+                return Some(ParserTree::Raw(format!(
+                    "map!(take!({n}), |b: &[u8]| {{ let mut a = [0u8; {n}]; a.copy_from_slice(b); a }})", n=len)));
+            }
+            // parse the items, and convert the resulting Vec to an array
+            let s = get_type_parser(&typearray.elem, endianness);
+            s.map(|x| ParserTree::Array(Box::new(x), quote!{ #len }.to_string()))
+        },
+        Type::Tuple(ref typetuple) if !typetuple.elems.is_empty() => {
+            // parse the elements in order
+            let v : Option<Vec<_>> = typetuple.elems.iter().map(|ty| get_type_parser(ty, endianness)).collect();
+            v.map(ParserTree::Tuple)
+        },
         _ => None
     }
 }
 
-fn get_parser(field: &::syn::Field) -> Option<ParserTree> {
+/// If the type is a map type (`HashMap<K,V>` or `BTreeMap<K,V>`), return the name of the
+/// map type, and the types of keys and values
+fn get_map_types(ty: &Type) -> Option<(String, &Type, &Type)> {
+    if let Type::Path(ref typepath) = ty {
+        let segment = typepath.path.segments.last()?.into_value();
+        if segment.ident != "HashMap" && segment.ident != "BTreeMap" {
+            return None;
+        }
+        if let PathArguments::AngleBracketed(ref ab) = segment.arguments {
+            if let (Some(GenericArgument::Type(k)), Some(GenericArgument::Type(v))) = (ab.args.iter().next(), ab.args.iter().nth(1)) {
+                return Some((segment.ident.to_string(), k, v));
+            }
+        }
+        panic!("Unsupported {}/parameterized type", segment.ident);
+    }
+    None
+}
+
+/// Test if the type is a byte slice (`&[u8]`, with any lifetime)
+fn is_byte_slice(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(ref r) => match *r.elem {
+            Type::Slice(ref s) => quote!{ #s }.to_string().replace(' ', "") == "[u8]",
+            _ => false
+        },
+        _ => false
+    }
+}
+
+/// If the type is an `Option<T>`, return the type `T`
+fn get_option_type(ty: &Type) -> Option<&Type> {
+    if let Type::Path(ref typepath) = ty {
+        let segment = typepath.path.segments.last()?.into_value();
+        if segment.ident != "Option" {
+            return None;
+        }
+        if let PathArguments::AngleBracketed(ref ab) = segment.arguments {
+            if let (1, Some(GenericArgument::Type(t))) = (ab.args.len(), ab.args.iter().next()) {
+                return Some(t);
+            }
+        }
+        panic!("Unsupported Option/parameterized type");
+    }
+    None
+}
+
+/// Test if the type is `PhantomData<T>`, possibly given by its path (for ex.
+/// `std::marker::PhantomData<T>`)
+fn is_phantom_data(ty: &Type) -> bool {
+    match ty {
+        Type::Path(ref typepath) if typepath.qself.is_none() => {
+            let segments : Vec<_> = typepath.path.segments.iter().map(|s| s.ident.to_string()).collect();
+            let segments : Vec<&str> = segments.iter().map(|s| s.as_ref()).collect();
+            matches!(segments.as_slice(),
+                ["PhantomData"] | ["marker", "PhantomData"] |
+                ["std", "marker", "PhantomData"] | ["core", "marker", "PhantomData"])
+        },
+        _ => false
+    }
+}
+
+/// If the type is a pointer type (`Box<T>`, `Rc<T>` or `Arc<T>`), return the function
+/// creating the pointer, and the inner type
+fn get_pointer_type(ty: &Type) -> Option<(&'static str, &Type)> {
+    if let Type::Path(ref typepath) = ty {
+        let segment = typepath.path.segments.last()?.into_value();
+        let wrap = match segment.ident.to_string().as_ref() {
+            "Box" => "::std::boxed::Box::new",
+            "Rc"  => "::std::rc::Rc::new",
+            "Arc" => "::std::sync::Arc::new",
+            _     => return None
+        };
+        if let PathArguments::AngleBracketed(ref ab) = segment.arguments {
+            if let (1, Some(GenericArgument::Type(t))) = (ab.args.len(), ab.args.iter().next()) {
+                return Some((wrap, t));
+            }
+        }
+        panic!("Unsupported {}/parameterized type", segment.ident);
+    }
+    None
+}
+
+fn get_parser(field: &::syn::Field, endianness: ParserEndianness) -> Option<ParserTree> {
     // eprintln!("field: {:?}", field);
     let ty = &field.ty;
+    // forward the runtime endianness to the parser of the field, if requested
+    let forward_endianness = is_dynamic_endian(&field.attrs);
+    if forward_endianness && endianness != ParserEndianness::Runtime {
+        panic!("'DynamicEndian' attribute can only be used on a field if the endianness is selected at runtime");
+    }
+    if let Some(p) = get_match_parser(field) {
+        return Some(p);
+    }
+    // the attributes of an Option field apply to the parser of the inner type, unless the
+    // parser of the whole field is given by 'ParseOuter'
+    if let Some(inner) = get_option_type(ty) {
+        if get_string_attribute(field, "ParseOuter").is_none() {
+            let mut inner_field = field.clone();
+            inner_field.ty = inner.clone();
+            return get_parser(&inner_field, endianness)
+                .map(|p| ParserTree::Opt(Box::new(ParserTree::Complete(Box::new(p)))));
+        }
+    }
+    // the attributes of a pointer field (Box, Rc or Arc) apply to the parser of the inner
+    // type, unless the parser of the whole field is given
+    if let Some((wrap, inner)) = get_pointer_type(ty) {
+        if get_string_attribute(field, "Parse").is_none() && get_string_attribute(field, "ParseOuter").is_none() {
+            let mut inner_field = field.clone();
+            inner_field.ty = inner.clone();
+            return get_parser(&inner_field, endianness)
+                .map(|p| ParserTree::Map(Box::new(p), wrap.to_owned()));
+        }
+    }
+    if let Some(p) = get_fold_parser(field) {
+        return Some(p);
+    }
+    if let Some(p) = get_columnar_parser(field) {
+        return Some(p);
+    }
+    if is_utf8(field) {
+        if quote!{ #ty }.to_string() != "char" {
+            panic!("'Utf8' attribute can only be used on char fields");
+        }
+        return Some(ParserTree::Utf8Char);
+    }
+    if is_rest(field) {
+        let rest = ParserTree::Raw("call!(nom::rest)".to_owned());
+        return match ty {
+            _ if is_byte_slice(ty) => Some(rest),
+            _ if quote!{ #ty }.to_string().replace(' ', "") == "Vec<u8>" => {
+                Some(ParserTree::Map(Box::new(rest), "|b: &[u8]| b.to_vec()".to_owned()))
+            },
+            _ => panic!("'Rest' attribute can only be used on &[u8] or Vec<u8> fields")
+        };
+    }
+    if let Some(len) = get_run_length(field) {
+        if quote!{ #ty }.to_string().replace(' ', "") != "Vec<u8>" {
+            panic!("'RunLength' attribute can only be used on Vec<u8> fields");
+        }
+        return Some(ParserTree::RunLength(len));
+    }
     // first check if we have an attribute
     // eprintln!("attrs: {:?}", field.attrs);
     for attr in &field.attrs {
         // eprintln!("meta: {:?}", attr.parse_meta());
-        if let Ok(ref meta) = attr.parse_meta() {
-            match meta {
-                Meta::NameValue(ref namevalue) => {
-                    if &namevalue.ident == &"Parse" {
-                        match &namevalue.lit {
-                            Lit::Str(s) => {
-                                return Some(ParserTree::Raw(s.value()))
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "Parse" || namevalue.ident == "ParseOuter" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        return Some(ParserTree::Raw(s.value()))
+                    },
+                    _ => panic!("Invalid '{}' attribute type/value", namevalue.ident)
+                }
+            }
+            if namevalue.ident == "Selector" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        // the field type must have a parse function taking a selector
+                        let sel = if forward_endianness { format!("{}, __endianness", s.value()) } else { s.value() };
+                        return match get_type_parser(ty, endianness) {
+                            Some(ParserTree::CallParse(t)) => Some(ParserTree::CallParseSelector(t, sel)),
+                            Some(ParserTree::CallParseEndian(t)) => {
+                                if forward_endianness {
+                                    Some(ParserTree::CallParseSelector(t, sel))
+                                } else {
+                                    Some(ParserTree::Raw(format!("call!({}::parse_endian, {}, __endianness)", t, sel)))
+                                }
                             },
-                            _ => panic!("Invalid 'Parse' attribute type/value")
+                            _ => panic!("'Selector' attribute can only be used on fields with a parse function (enums)")
+                        };
+                    },
+                    _ => panic!("Invalid 'Selector' attribute type/value")
+                }
+            }
+            if namevalue.ident == "AsciiInt" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        return match get_type_parser(ty, endianness) {
+                            Some(ParserTree::Raw(_)) => Some(ParserTree::AsciiInt(s.value(), quote!{ #ty }.to_string())),
+                            _ => panic!("'AsciiInt' attribute can only be used on integer fields")
+                        };
+                    },
+                    _ => panic!("Invalid 'AsciiInt' attribute type/value")
+                }
+            }
+            if namevalue.ident == "LengthType" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        let len_ty = s.value();
+                        match len_ty.as_ref() {
+                            "u8" | "u16" | "u32" | "u64" => (),
+                            _ => panic!("'LengthType' attribute must be an unsigned integer type")
                         }
-                    }
-                    if &namevalue.ident == &"Count" {
-                        match &namevalue.lit {
-                            Lit::Str(s) => {
-                                // try to infer subparser
-                                let sub = get_type_parser(ty);
-                                let s1 = match sub {
-                                    Some(ParserTree::Many0(m)) => { m },
-                                    _ => panic!("Unable to infer parser for 'Count' attribute. Is item type a Vec ?")
-                                };
-                                let s2 = match *s1 {
-                                    ParserTree::Complete(m) => { m },
-                                    _ => panic!("Unable to infer parser for 'Count' attribute. Is item type a Vec ?")
-                                };
-                                return Some(ParserTree::Count(s2, s.value()));
+                        return match get_type_parser(ty, endianness) {
+                            Some(ParserTree::LengthString(_)) => {
+                                Some(ParserTree::LengthString(Box::new(ParserTree::Raw(endianness.primitive_parser(&len_ty)))))
                             },
-                            _ => panic!("Invalid 'Count' attribute type/value")
-                        }
-                    }
+                            _ => panic!("'LengthType' attribute can only be used on String fields")
+                        };
+                    },
+                    _ => panic!("Invalid 'LengthType' attribute type/value")
                 }
-                _ => ()
             }
+            if namevalue.ident == "UntilOffset" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        return match get_type_parser(ty, endianness) {
+                            Some(p @ ParserTree::Many0(_)) => Some(ParserTree::UntilOffset(Box::new(p), s.value())),
+                            _ => panic!("Unable to infer parser for 'UntilOffset' attribute. Is item type a Vec ?")
+                        };
+                    },
+                    _ => panic!("Invalid 'UntilOffset' attribute type/value")
+                }
+            }
+            if namevalue.ident == "TakeWhile" || namevalue.ident == "TakeUntil" {
+                let value = match &namevalue.lit {
+                    Lit::Str(s) => s.value(),
+                    _ => panic!("Invalid '{}' attribute type/value", namevalue.ident)
+                };
+                let take = if namevalue.ident == "TakeWhile" {
+                    ParserTree::Raw(format!("take_while!({})", value))
+                } else {
+                    // the pattern can be a byte string, an array, a slice or a str
+                    ParserTree::Raw(format!("take_until!(&({})[..])", value))
+                };
+                return match ty {
+                    _ if is_byte_slice(ty) => Some(take),
+                    _ if quote!{ #ty }.to_string().replace(' ', "") == "Vec<u8>" => {
+                        Some(ParserTree::Map(Box::new(take), "|b: &[u8]| b.to_vec()".to_owned()))
+                    },
+                    _ => panic!("'{}' attribute can only be used on &[u8] or Vec<u8> fields", namevalue.ident)
+                };
+            }
+        }
+    }
+    // the number of items can be any expression, converted to usize
+    if let Some(n) = get_count(field) {
+        // maps are parsed as a list of (key, value) pairs
+        if let Some((map, k, v)) = get_map_types(ty) {
+            let pair = match (get_type_parser(k, endianness), get_type_parser(v, endianness)) {
+                (Some(k), Some(v)) => ParserTree::Pair(Box::new(k), Box::new(v)),
+                _ => panic!("Unable to infer parser for 'Count' attribute on map type")
+            };
+            let collect = format!("|v: Vec<_>| v.into_iter().collect::<::std::collections::{}<_,_>>()", map);
+            return Some(ParserTree::Map(Box::new(ParserTree::Count(Box::new(pair), n)), collect));
         }
+        // strings are read from the given number of bytes, and must be valid UTF-8
+        if let Some(ParserTree::LengthString(_)) = get_type_parser(ty, endianness) {
+            let to_string = "|b: &[u8]| ::std::str::from_utf8(b).map(|s| s.to_string())".to_owned();
+            return Some(ParserTree::MapRes(Box::new(ParserTree::Take(n)), to_string));
+        }
+        // in a tuple, the number of items applies to the list
+        if let Some(ParserTree::Tuple(mut v)) = get_type_parser(ty, endianness) {
+            let lists : Vec<_> = v.iter().enumerate()
+                .filter_map(|(idx, p)| match p { ParserTree::Many0(_) => Some(idx), _ => None })
+                .collect();
+            if lists.len() != 1 {
+                panic!("'Count' attribute on a tuple requires exactly one Vec element");
+            }
+            let list = ::std::mem::replace(&mut v[lists[0]], ParserTree::Raw(String::new()));
+            v[lists[0]] = match list {
+                ParserTree::Many0(m) => match *m {
+                    ParserTree::Complete(m) => ParserTree::Count(m, n),
+                    m => ParserTree::Count(Box::new(m), n),
+                },
+                _ => unreachable!()
+            };
+            return Some(ParserTree::Tuple(v));
+        }
+        // try to infer subparser
+        let sub = get_type_parser(ty, endianness);
+        let s1 = match sub {
+            Some(ParserTree::Many0(m)) => { m },
+            _ => panic!("Unable to infer parser for 'Count' attribute. Is item type a Vec ?")
+        };
+        let s2 = match *s1 {
+            ParserTree::Complete(m) => { m },
+            _ => panic!("Unable to infer parser for 'Count' attribute. Is item type a Vec ?")
+        };
+        return Some(ParserTree::Count(s2, n));
+    }
+    if forward_endianness {
+        return match get_type_parser(ty, endianness) {
+            Some(ParserTree::CallParseEndian(t)) => Some(ParserTree::CallParseSelector(t, "__endianness".to_owned())),
+            _ => panic!("'DynamicEndian' attribute can only be used on fields with a parse function")
+        };
     }
     // else try primitive types knowledge
-    get_type_parser(ty)
+    get_type_parser(ty, endianness)
 }
 
 fn add_verify(field: &syn::Field, p: ParserTree) -> ParserTree {
-    if field.ident == None { return p; }
+    if field.ident.is_none() { return p; }
     let ident = field.ident.as_ref().expect("empty field ident (add_verify)");
     for attr in &field.attrs {
-        if let Ok(ref meta) = attr.parse_meta() {
-            match meta {
-                Meta::NameValue(ref namevalue) => {
-                    if &namevalue.ident == &"Verify" {
-                        match &namevalue.lit {
-                            Lit::Str(s) => {
-                                return ParserTree::Verify(Box::new(p), format!("{}",ident), s.value())
-                            },
-                            _ => panic!("Invalid 'Verify' attribute type/value")
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "Verify" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        let verify = |p| ParserTree::Verify(Box::new(p), format!("{}",ident), s.value(), get_verify_error(field));
+                        if get_string_attribute(field, "Cond").is_none() {
+                            return verify(p);
                         }
-                    }
-                },
-                _ => ()
+                        // with a condition, the value is verified only if it is present, and
+                        // invalid values are errors (not None)
+                        return match p {
+                            ParserTree::Cond(sub, c) => ParserTree::CondWithError(Box::new(verify(*sub)), c),
+                            _ => panic!("The 'Cond' and 'Verify' attributes cannot be used with 'Into' or 'TryInto' (field {})", ident)
+                        };
+                    },
+                    _ => panic!("Invalid 'Verify' attribute type/value")
+                }
+            }
+        }
+    }
+    p
+}
+
+fn is_monotonic(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) => ident == "Monotonic",
+            _ => false
+        }
+    })
+}
+
+/// Check that the items of the field are sorted (each item is greater than or equal to the
+/// previous one), if the field has the `Monotonic` attribute
+fn add_monotonic(field: &syn::Field, ident: &str, p: ParserTree) -> ParserTree {
+    if !is_monotonic(field) {
+        return p;
+    }
+    // the type of the items cannot be inferred in the check
+    let ty = &field.ty;
+    let check = format!("{{ let v: &{} = &{}; v.windows(2).all(|w| w[0] <= w[1]) }}", quote!{ #ty }, ident);
+    ParserTree::Verify(Box::new(p), ident.to_owned(), check, get_verify_error(field))
+}
+
+fn add_map(field: &syn::Field, p: ParserTree) -> ParserTree {
+    for attr in &field.attrs {
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "Map" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        // with a condition, map the value inside the Option
+                        return match p {
+                            ParserTree::Cond(sub, c) => ParserTree::Cond(Box::new(ParserTree::Map(sub, s.value())), c),
+                            p => ParserTree::Map(Box::new(p), s.value()),
+                        };
+                    },
+                    _ => panic!("Invalid 'Map' attribute type/value")
+                }
+            }
+        }
+    }
+    p
+}
+
+fn add_conversion(field: &syn::Field, p: ParserTree) -> ParserTree {
+    for attr in &field.attrs {
+        if let Ok(Meta::Word(ref ident)) = attr.parse_meta() {
+            if ident == "Into" {
+                return ParserTree::Map(Box::new(p), "::std::convert::Into::into".to_owned());
+            }
+            if ident == "TryInto" {
+                return ParserTree::MapRes(Box::new(p), "::std::convert::TryInto::try_into".to_owned());
             }
         }
     }
     p
 }
 
+/// Get the initializer of a field which is not parsed (`Ignore` or `Default` attribute)
+fn get_initializer(field: &syn::Field) -> Option<String> {
+    for attr in &field.attrs {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) if ident == "Ignore" || ident == "Default" => {
+                return Some("Default::default()".to_owned());
+            },
+            Ok(Meta::NameValue(ref namevalue)) if namevalue.ident == "Default" => {
+                match &namevalue.lit {
+                    Lit::Str(s) => return Some(s.value()),
+                    _ => panic!("Invalid 'Default' attribute type/value")
+                }
+            },
+            _ => ()
+        }
+    }
+    None
+}
+
+fn is_bounding_length(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) => ident == "BoundingLength",
+            _ => false
+        }
+    })
+}
+
+/// Get the value of the `name = "value"` attribute of a field, if present
+fn get_string_attribute(field: &syn::Field, name: &str) -> Option<String> {
+    for attr in &field.attrs {
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == name {
+                match &namevalue.lit {
+                    Lit::Str(s) => return Some(s.value()),
+                    _ => panic!("Invalid '{}' attribute type/value", name)
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Get the number of items of a field: `Count="expr"`, `Count=N` or `Count(expr)`. The
+/// expression is converted to `usize` by the generated parser
+fn get_count(field: &syn::Field) -> Option<String> {
+    get_expr_attribute(&field.attrs, "Count")
+}
+
+/// Get the error kind returned if a verification fails: the `VerifyError` attribute is an
+/// expression of type `nom::ErrorKind` (by default, `ErrorKind::Verify`)
+pub(crate) fn get_verify_error(field: &syn::Field) -> String {
+    get_string_attribute(field, "VerifyError").unwrap_or_else(|| "nom::ErrorKind::Verify".to_owned())
+}
+
+fn get_set_endian(field: &syn::Field) -> Option<String> {
+    get_string_attribute(field, "SetEndian")
+}
+
+/// Get the name of the previous field giving the byte order of this field and the next ones
+/// (`EndianFrom` attribute)
+fn get_endian_from(field: &syn::Field) -> Option<String> {
+    get_string_attribute(field, "EndianFrom")
+}
+
+/// Get the expression given by an attribute: `Name="expr"`, `Name=N`, `Name("expr")`,
+/// `Name(N)` or `Name(expr)`
+fn get_expr_attribute(attrs: &[Attribute], name: &str) -> Option<String> {
+    let lit_value = |lit: &Lit| -> String {
+        match lit {
+            Lit::Str(s) => s.value(),
+            Lit::Int(i) => i.value().to_string(),
+            _ => panic!("Invalid '{}' attribute type/value", name)
+        }
+    };
+    for attr in attrs {
+        if !attr.path.is_ident(name) {
+            continue;
+        }
+        let value = match attr.parse_meta() {
+            Ok(Meta::NameValue(ref namevalue)) => lit_value(&namevalue.lit),
+            Ok(Meta::List(ref metalist)) if metalist.nested.len() == 1 => {
+                match metalist.nested.iter().next() {
+                    Some(NestedMeta::Literal(lit)) => lit_value(lit),
+                    _ => get_expr_tokens(attr, name)
+                }
+            },
+            // an expression which is not a literal, for ex. `Name(a * 2 + 1)`
+            _ => get_expr_tokens(attr, name)
+        };
+        if value.parse::<proc_macro2::TokenStream>().is_err() {
+            panic!("Invalid '{}' attribute value: {}", name, value);
+        }
+        return Some(value);
+    }
+    None
+}
+
+/// Get the tokens of an expression given between parentheses: `Name(expr)`
+fn get_expr_tokens(attr: &Attribute, name: &str) -> String {
+    let mut tts = attr.tts.clone().into_iter();
+    match (tts.next(), tts.next()) {
+        (Some(proc_macro2::TokenTree::Group(ref g)), None) if g.delimiter() == proc_macro2::Delimiter::Parenthesis => {
+            g.stream().to_string()
+        },
+        _ => panic!("Invalid '{}' attribute type/value", name)
+    }
+}
+
+/// Get the number of bytes to skip before a field: `Skip="expr"`, `Skip=N`, `Skip("expr")`
+/// or `Skip(N)`
+fn get_skip(attrs: &[Attribute]) -> Option<String> {
+    get_expr_attribute(attrs, "Skip")
+}
+
+/// Get the alignment of a field: `Align=N`, `Align(N)` or `Align("expr")`
+fn get_align(attrs: &[Attribute]) -> Option<String> {
+    get_expr_attribute(attrs, "Align")
+}
+
+/// Build the parser checking the bytes of a `Tag` or `Magic` attribute value: a byte string,
+/// or a string (which can also contain a byte string literal, for ex. `"b\"\\x00\\x01\""`)
+fn get_tag_parser(lit: &Lit, attr_name: &str) -> ParserTree {
+    let bytes = match lit {
+        Lit::ByteStr(b) => b.value(),
+        Lit::Str(s) => {
+            let value = s.value();
+            if value.starts_with("b\"") {
+                syn::parse_str::<LitByteStr>(&value)
+                    .unwrap_or_else(|_| panic!("Invalid '{}' byte string", attr_name))
+                    .value()
+            } else {
+                value.into_bytes()
+            }
+        },
+        _ => panic!("Invalid '{}' attribute type/value", attr_name)
+    };
+    let escaped : String = bytes.iter().map(|b| format!("\\x{:02x}", b)).collect();
+    ParserTree::Raw(format!("tag!(&b\"{}\"[..])", escaped))
+}
+
+/// Get the parser of the `Tag` attribute, if present: `Tag=b"..."` or `Tag="..."`
+fn get_tag(attrs: &[Attribute]) -> Option<ParserTree> {
+    for attr in attrs {
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "Tag" {
+                return Some(get_tag_parser(&namevalue.lit, "Tag"));
+            }
+        }
+    }
+    None
+}
+
+/// Get the parser of the constant bytes at the start of a structure or variant, given by the
+/// `Tag=b"..."` or `Magic(b"...")` attribute
+fn get_magic(attrs: &[Attribute]) -> Option<ParserTree> {
+    let mut magic = None;
+    for attr in attrs {
+        if let Ok(Meta::List(ref metalist)) = attr.parse_meta() {
+            if metalist.ident == "Magic" {
+                magic = match metalist.nested.iter().next() {
+                    Some(NestedMeta::Literal(lit)) if metalist.nested.len() == 1 => Some(get_tag_parser(lit, "Magic")),
+                    _ => panic!("Invalid 'Magic' attribute type/value")
+                };
+            }
+        }
+    }
+    match (magic, get_tag(attrs)) {
+        (Some(_), Some(_)) => panic!("Nom-derive: 'Magic' and 'Tag' attributes are mutually exclusive"),
+        (magic, tag) => magic.or(tag),
+    }
+}
+
+/// Get the parser of a field with the `Fold` attribute: items are parsed using the `Parse`
+/// attribute, and folded into the field value (without allocating a `Vec`)
+fn get_fold_parser(field: &syn::Field) -> Option<ParserTree> {
+    let fold = get_string_attribute(field, "Fold")?;
+    // split the attribute value into the initial value and the function, at the first
+    // top-level comma (commas in the arguments of the function are also top-level)
+    let tokens : proc_macro2::TokenStream = fold.parse().expect("Invalid 'Fold' attribute value");
+    let mut tokens = tokens.into_iter();
+    let init : proc_macro2::TokenStream = tokens.by_ref()
+        .take_while(|t| match t {
+            proc_macro2::TokenTree::Punct(p) => p.as_char() != ',',
+            _ => true
+        })
+        .collect();
+    let f : proc_macro2::TokenStream = tokens.collect();
+    if init.is_empty() || f.is_empty() {
+        panic!("Invalid 'Fold' attribute value. Expected \"init, |acc, item| ...\"");
+    }
+    let (init, f) = (init.to_string(), f.to_string());
+    let item = get_string_attribute(field, "Parse")
+        .unwrap_or_else(|| panic!("The 'Fold' attribute requires a 'Parse' attribute, giving the parser of items"));
+    let count = get_count(field);
+    Some(ParserTree::Fold(Box::new(ParserTree::Raw(item)), count, init, f))
+}
+
+/// Get the `RunLength` attribute: `Some(None)` if the run-length encoded data ends with a zero
+/// count, or `Some(Some(len))` if the length of the decoded data is given
+fn get_run_length(field: &syn::Field) -> Option<Option<String>> {
+    for attr in &field.attrs {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) if ident == "RunLength" => return Some(None),
+            Ok(Meta::NameValue(ref namevalue)) if namevalue.ident == "RunLength" => {
+                match &namevalue.lit {
+                    Lit::Str(s) => return Some(Some(s.value())),
+                    _ => panic!("Invalid 'RunLength' attribute type/value")
+                }
+            },
+            _ => ()
+        }
+    }
+    None
+}
+
+/// Get the parser of a field with the `Match("expr", "pattern => parser", ...)` attribute:
+/// the value of the expression selects the parser of the field (the first matching arm).
+/// If no arm matches, `ErrorKind::Switch` is returned.
+fn get_match_parser(field: &syn::Field) -> Option<ParserTree> {
+    for attr in &field.attrs {
+        if let Ok(Meta::List(ref metalist)) = attr.parse_meta() {
+            if metalist.ident != "Match" {
+                continue;
+            }
+            let values : Vec<_> = metalist.nested.iter()
+                .map(|n| match n {
+                    NestedMeta::Literal(Lit::Str(s)) => s.value(),
+                    _ => panic!("Invalid 'Match' attribute type/value")
+                })
+                .collect();
+            let (expr, arms) = match values.split_first() {
+                Some((expr, arms)) if !arms.is_empty() => (expr, arms),
+                _ => panic!("Invalid 'Match' attribute. Expected #[Match(\"expr\", \"pattern => parser\", ...)]")
+            };
+            let arms : Vec<_> = arms.iter()
+                .map(|arm| {
+                    let pos = arm.find("=>").unwrap_or_else(|| panic!("Invalid 'Match' arm '{}'. Expected \"pattern => parser\"", arm));
+                    let (pattern, parser) = (&arm[..pos], &arm[pos + 2..]);
+                    format!("{} => map!(i_, {}, |x| x),", pattern.trim(), parser.trim())
+                })
+                .collect();
+            // the default arm is unreachable if the arms are exhaustive
+            return Some(ParserTree::Raw(format!(
+                "call!(|i_| {{ let i_: &[u8] = i_; \
+                  match {} {{ \
+                    {} \
+                    #[allow(unreachable_patterns)] \
+                    _ => Err(nom::Err::Error(error_position!(i_, nom::ErrorKind::Switch))) \
+                  }} }})", expr, arms.join(" "))));
+        }
+    }
+    None
+}
+
+/// Get the parser of a field with the `Columnar(count="n")` attribute: the items of the
+/// `Vec` are stored by columns, and parsed using the `parse_columnar` function of their type
+fn get_columnar_parser(field: &syn::Field) -> Option<ParserTree> {
+    for attr in &field.attrs {
+        if let Ok(Meta::List(ref metalist)) = attr.parse_meta() {
+            if metalist.ident != "Columnar" {
+                continue;
+            }
+            let count = match metalist.nested.iter().next() {
+                Some(NestedMeta::Meta(Meta::NameValue(ref namevalue))) if namevalue.ident == "count" => {
+                    match &namevalue.lit {
+                        Lit::Str(s) => s.value(),
+                        _ => panic!("Invalid 'Columnar' attribute type/value")
+                    }
+                },
+                _ => panic!("Invalid 'Columnar' attribute. Expected #[Columnar(count=\"n\")]")
+            };
+            // the item type is given by the parser of the Vec
+            return match get_type_parser(&field.ty, ParserEndianness::BigEndian) {
+                Some(ParserTree::Many0(ref m)) => match **m {
+                    ParserTree::Complete(ref c) => match **c {
+                        ParserTree::CallParse(ref t) => {
+                            Some(ParserTree::Raw(format!("call!({}::parse_columnar, {{ {} }} as usize)", t, count)))
+                        },
+                        _ => panic!("'Columnar' attribute can only be used on a Vec of structures with the 'Columnar' attribute")
+                    },
+                    _ => unreachable!()
+                },
+                _ => panic!("'Columnar' attribute can only be used on a Vec of structures with the 'Columnar' attribute")
+            };
+        }
+    }
+    None
+}
+
 fn patch_condition(field: &syn::Field, p: ParserTree) -> ParserTree {
-    if field.ident == None { return p; }
+    if field.ident.is_none() { return p; }
     let ident = field.ident.as_ref().expect("empty field ident (patch condition)");
     for attr in &field.attrs {
-        if let Ok(ref meta) = attr.parse_meta() {
-            match meta {
-                Meta::NameValue(ref namevalue) => {
-                    if &namevalue.ident == &"Cond" {
-                        match &namevalue.lit {
-                            Lit::Str(s) => {
-                                match p {
-                                    ParserTree::Opt(sub) => {
-                                        return ParserTree::Cond(sub, s.value());
-                                    }
-                                    _ => panic!("A condition was given on field {}, which is not an option type. Hint: use Option<...>", ident),
-                                }
-                            },
-                            _ => panic!("Invalid 'Cond' attribute type/value")
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "Cond" {
+                match &namevalue.lit {
+                    Lit::Str(s) => {
+                        match p {
+                            ParserTree::Opt(sub) => {
+                                return ParserTree::Cond(sub, s.value());
+                            }
+                            _ => panic!("A condition was given on field {}, which is not an option type. Hint: use Option<...>", ident),
                         }
-                    }
-                },
-                _ => ()
+                    },
+                    _ => panic!("Invalid 'Cond' attribute type/value")
+                }
+            }
+        }
+    }
+    p
+}
+
+/// Get the name of the field giving the version of the format, from the `NomVersionField`
+/// attribute of the structure
+fn get_version_field(attrs: &[Attribute]) -> Option<String> {
+    for attr in attrs {
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "NomVersionField" {
+                match &namevalue.lit {
+                    Lit::Str(s) => return Some(s.value()),
+                    _ => panic!("Invalid 'NomVersionField' attribute type/value")
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Get the condition on the version given by the `Since(v)` (included) and `Until(v)`
+/// (included) attributes of a field, if any
+fn get_version_condition(field: &syn::Field, version: Option<&str>) -> Option<String> {
+    let (mut since, mut until) = (None, None);
+    for attr in &field.attrs {
+        if let Ok(Meta::List(ref metalist)) = attr.parse_meta() {
+            if metalist.ident != "Since" && metalist.ident != "Until" {
+                continue;
+            }
+            let v = match metalist.nested.iter().next() {
+                Some(NestedMeta::Literal(Lit::Int(i))) if metalist.nested.len() == 1 => i.value().to_string(),
+                Some(NestedMeta::Literal(Lit::Str(s))) if metalist.nested.len() == 1 => s.value(),
+                _ => panic!("Invalid '{}' attribute type/value", metalist.ident)
+            };
+            if metalist.ident == "Since" { since = Some(v); } else { until = Some(v); }
+        }
+    }
+    if since.is_none() && until.is_none() {
+        return None;
+    }
+    let version = version.expect("The 'Since' and 'Until' attributes require the 'NomVersionField' attribute");
+    match (since, until) {
+        (Some(s), Some(u)) => Some(format!("({}..={}).contains(&{})", s, u, version)),
+        (Some(s), None)    => Some(format!("{} >= {}", version, s)),
+        (None, Some(u))    => Some(format!("{} <= {}", version, u)),
+        (None, None)       => None
+    }
+}
+
+/// Parse the field only for the versions given by the `Since` and `Until` attributes.
+/// Otherwise, an `Option` field is `None`, and other fields have their default value
+fn add_version_condition(field: &syn::Field, version: Option<&str>, p: ParserTree) -> ParserTree {
+    match (get_version_condition(field, version), p) {
+        (None, p) => p,
+        (Some(c), ParserTree::Cond(sub, c0)) => ParserTree::Cond(sub, format!("({}) && ({})", c0, c)),
+        (Some(c), ParserTree::Opt(sub)) => ParserTree::Cond(sub, c),
+        (Some(c), p) => {
+            let default = "|v: Option<_>| v.unwrap_or_default()".to_owned();
+            ParserTree::Map(Box::new(ParserTree::Cond(Box::new(p), c)), default)
+        }
+    }
+}
+
+fn is_strict(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) => ident == "Strict",
+            _ => false
+        }
+    })
+}
+
+fn add_strict(field: &syn::Field, mut p: ParserTree) -> ParserTree {
+    if is_strict(field) && !p.set_strict() {
+        panic!("'Strict' attribute can only be used on bool fields");
+    }
+    p
+}
+
+fn add_width(field: &syn::Field, ident: &str, mut p: ParserTree, endianness: ParserEndianness) -> ParserTree {
+    for attr in &field.attrs {
+        if let Ok(Meta::NameValue(ref namevalue)) = attr.parse_meta() {
+            if namevalue.ident == "Width" {
+                let width = match &namevalue.lit {
+                    Lit::Str(s) => s.value(),
+                    Lit::Int(i) => i.value().to_string(),
+                    _ => panic!("Invalid 'Width' attribute type/value")
+                };
+                if !p.set_width(&width, |ty| endianness.primitive_parser(ty)) {
+                    panic!("'Width' attribute can only be used on usize or isize fields");
+                }
+            }
+        }
+    }
+    if p.has_unsized_int() {
+        panic!("Nom-derive: field {} has no fixed size. Hint: use the 'Width' attribute to give the number of bytes (for ex. #[Width=4])", ident);
+    }
+    p
+}
+
+fn is_nibbles(field: &syn::Field) -> bool {
+    let nibbles = field.attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) => ident == "Nibbles",
+            _ => false
+        }
+    });
+    let ty = &field.ty;
+    if nibbles && quote!{ #ty }.to_string() != "u8" {
+        panic!("'Nibbles' attribute can only be used on u8 fields");
+    }
+    nibbles
+}
+
+/// Get the label of the group of the field, given by the `NomGroup` attribute
+fn get_group(field: &syn::Field) -> Option<String> {
+    get_string_attribute(field, "NomGroup")
+}
+
+/// Get the error code of a group (32-bit FNV-1a hash of its label)
+fn get_group_code(group: &str) -> u32 {
+    group.bytes().fold(0x811c_9dc5, |h, b| (h ^ u32::from(b)).wrapping_mul(0x0100_0193))
+}
+
+/// Add the label of a group to the errors of a parser, as an `ErrorKind::Custom` code
+///
+/// With the `verbose-errors` feature of nom, the code is appended to the list of errors.
+/// Otherwise, the error would be replaced by the code, so it is returned unchanged.
+fn gen_group_context(parser: &ParserTree, group: &str) -> proc_macro2::TokenStream {
+    let code = get_group_code(group);
+    quote!{ call!(|i_| {
+        let i_: &[u8] = i_;
+        let (e, failure) = match map!(i_, #parser, |x| x) {
+            Err(nom::Err::Error(e))   => (e, false),
+            Err(nom::Err::Failure(e)) => (e, true),
+            r => return r,
+        };
+        #[allow(unreachable_patterns)]
+        let e = match error_node_position!(i_, nom::ErrorKind::Custom(#code), e.clone()) {
+            nom::Context::Code(..) => e,
+            labeled => labeled,
+        };
+        if failure { Err(nom::Err::Failure(e)) } else { Err(nom::Err::Error(e)) }
+    }) }
+}
+
+/// Generate the `group_label` function, returning the label of a group given its error code
+pub(crate) fn gen_group_label(s: &DataStruct) -> proc_macro2::TokenStream {
+    let mut labels : Vec<String> = vec![];
+    for group in s.fields.iter().filter_map(get_group) {
+        if !labels.contains(&group) {
+            labels.push(group);
+        }
+    }
+    if labels.is_empty() {
+        return quote!{};
+    }
+    let codes : Vec<_> = labels.iter().map(|group| get_group_code(group)).collect();
+    quote!{
+        fn group_label(code: u32) -> Option<&'static str> {
+            match code {
+                #(#codes => Some(#labels),)*
+                _ => None,
             }
         }
     }
+}
+
+fn is_rest(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) => ident == "Rest",
+            _ => false
+        }
+    })
+}
+
+fn is_utf8(field: &syn::Field) -> bool {
+    field.attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(Meta::Word(ref ident)) => ident == "Utf8",
+            _ => false
+        }
+    })
+}
+
+fn add_bool_width(field: &syn::Field, mut p: ParserTree, endianness: ParserEndianness) -> ParserTree {
+    if let Some(ty) = get_string_attribute(field, "BoolWidth") {
+        match ty.as_ref() {
+            "u8" | "u16" | "u32" | "u64" => (),
+            _ => panic!("'BoolWidth' attribute must be an unsigned integer type")
+        }
+        if !p.set_bool_width(&endianness.primitive_parser(&ty), &ty) {
+            panic!("'BoolWidth' attribute can only be used on bool fields");
+        }
+    }
     p
 }
 
-pub(crate) fn parse_fields(f: &Fields) -> StructParserTree {
+fn parse_fields(f: &Fields, attrs: &[Attribute], mut endianness: ParserEndianness) -> StructParserTree {
+    let version = get_version_field(attrs);
     let mut parsers = vec![];
+    let mut fields = vec![];
+    let mut bounding_length = None;
+    let mut field_parsers = vec![];
     let mut unnamed = false;
     match f {
         Fields::Named(_) => (),
         Fields::Unnamed(_) => {
             unnamed = true;
         },
-        Fields::Unit => panic!("Unit struct, nothing to generate")
+        // unit variants are built using an empty struct expression (`Enum::Variant {}`)
+        Fields::Unit => ()
     }
+    let mut unparsed = vec![];
+    let mut nibbles = None;
+    let mut execs = vec![];
+    // verifications of self-excluding checksums, after the last field
+    let mut checks = vec![];
+    // index of the first parser of each field, and group of the field
+    let mut group_spans : Vec<(usize,Option<String>)> = vec![];
     for (idx,field) in f.iter().enumerate() {
         let ident_str = match field.ident.as_ref() {
             Some(s) => s.to_string(),
             None    => format!("_{}",idx)
         };
-        let opt_parser = get_parser(&field);
+        // the remaining bytes can only be read by the last field
+        if is_rest(field) && idx + 1 != f.iter().count() {
+            panic!("Nom-derive: the 'Rest' attribute can only be used on the last field");
+        }
+        let group = get_group(field);
+        if let Some(ref g) = group {
+            let previous = group_spans.last().and_then(|(_, g)| g.as_ref());
+            if previous != Some(g) && group_spans.iter().any(|(_, other)| other.as_ref() == Some(g)) {
+                panic!("Nom-derive: the fields of the group '{}' must be consecutive", g);
+            }
+        }
+        group_spans.push( (parsers.len(), group) );
+        // run statements before the field, if requested
+        if let Some(stmts) = get_string_attribute(field, "PreExec") {
+            execs.push( (parsers.len(), stmts) );
+        }
+        let post_exec = get_string_attribute(field, "PostExec");
+        // the byte order of this field and the next ones is converted from a previous field
+        if let Some(order) = get_endian_from(field) {
+            let set_endian = format!("value!(nom::Endianness::from({}))", order);
+            parsers.push( ("__endianness".to_owned(), ParserTree::Raw(set_endian)) );
+            endianness = ParserEndianness::Runtime;
+        }
+        // ignored fields are not parsed, and their type is not inspected
+        if let Some(init) = get_initializer(field) {
+            if post_exec.is_some() {
+                panic!("Nom-derive: the 'PostExec' attribute cannot be used on fields which are not parsed (field {})", ident_str);
+            }
+            fields.push( (ident_str, Some(init)) );
+            continue;
+        }
+        // skip bytes before the field, if requested
+        if let Some(n) = get_skip(&field.attrs) {
+            let skip_str = format!("_skip_{}", idx);
+            parsers.push( (skip_str, ParserTree::Take(n)) );
+        }
+        // skip padding bytes, until the offset of the field is aligned
+        if let Some(n) = get_align(&field.attrs) {
+            parsers.push( (format!("_align_{}", idx), ParserTree::Align(n)) );
+        }
+        // constant bytes: store them only if the field can contain them
+        if let Some(tag) = get_tag(&field.attrs) {
+            match field.ty {
+                Type::Tuple(ref t) if t.elems.is_empty() => {
+                    parsers.push( (format!("_tag_{}", idx), tag) );
+                    field_parsers.push( (format!("_tag_{}", idx), ident_str.clone()) );
+                    fields.push( (ident_str, Some("()".to_owned())) );
+                },
+                Type::Path(_) if is_phantom_data(&field.ty) => {
+                    parsers.push( (format!("_tag_{}", idx), tag) );
+                    field_parsers.push( (format!("_tag_{}", idx), ident_str.clone()) );
+                    fields.push( (ident_str, Some("::std::marker::PhantomData".to_owned())) );
+                },
+                Type::Array(_) => {
+                    let try_into = "|b: &[u8]| ::std::convert::TryInto::try_into(b)".to_owned();
+                    parsers.push( (ident_str.clone(), ParserTree::MapRes(Box::new(tag), try_into)) );
+                    field_parsers.push( (ident_str.clone(), ident_str.clone()) );
+                    fields.push( (ident_str, None) );
+                },
+                _ => {
+                    parsers.push( (ident_str.clone(), tag) );
+                    field_parsers.push( (ident_str.clone(), ident_str.clone()) );
+                    fields.push( (ident_str, None) );
+                }
+            }
+            if let Some(stmts) = post_exec {
+                execs.push( (parsers.len(), stmts) );
+            }
+            continue;
+        }
+        let local_endianness = get_local_endianness(&field.attrs, endianness);
+        // two consecutive fields sharing a byte: the first field reads it
+        let opt_parser = match (is_nibbles(field), nibbles.take()) {
+            (true, None) => {
+                let byte = format!("_nibbles_{}", idx);
+                parsers.push( (byte.clone(), ParserTree::Raw("be_u8".to_owned())) );
+                let p = ParserTree::Raw(format!("value!({} >> 4)", byte));
+                nibbles = Some(byte);
+                Some(p)
+            },
+            (true, Some(byte)) => Some(ParserTree::Raw(format!("value!({} & 0x0f)", byte))),
+            (false, None) => get_parser(field, local_endianness),
+            (false, Some(_)) => panic!("The 'Nibbles' attribute must be set on two consecutive fields"),
+        };
         match opt_parser {
             Some(p) => {
+                // read bool values from larger integers, and reject invalid values, if requested
+                let p = add_bool_width(field, p, local_endianness);
+                // read usize and isize values from fixed-size integers
+                let p = add_width(field, &ident_str, p, local_endianness);
+                let p = add_strict(field, p);
                 // Check if a condition was given, and set it
-                let p = patch_condition(&field, p);
+                let p = patch_condition(field, p);
+                // parse the field only for some versions, if requested
+                let p = add_version_condition(field, version.as_ref().map(|v| v.as_ref()), p);
+                // transform the parsed value, if requested
+                let p = add_map(field, p);
+                // convert value to the field type, if requested
+                let p = add_conversion(field, p);
                 // add verify field, if present
-                let p = add_verify(&field, p);
-                parsers.push( (ident_str, p) )
+                let p = add_verify(field, p);
+                // check the order of the items, if requested
+                let p = add_monotonic(field, &ident_str, p);
+                // verify the checksum of previous data, if requested
+                let p = add_checksum(field, &ident_str, p);
+                // the checksum covers the whole structure, and is verified after the last field
+                if get_self_excluding_checksum(field).is_some() {
+                    let (start, end) = (format!("_cksum_start_{}", idx), format!("_cksum_end_{}", idx));
+                    parsers.push( (start.clone(), gen_offset()) );
+                    parsers.push( (ident_str.clone(), p) );
+                    parsers.push( (end.clone(), gen_offset()) );
+                    checks.push( (format!("_cksum_check_{}", idx), gen_self_excluding_check(field, &ident_str, &start, &end)) );
+                } else {
+                    parsers.push( (ident_str.clone(), p) );
+                }
+                // the next fields are parsed in a sub-slice of this length
+                if is_bounding_length(field) {
+                    if bounding_length.is_some() {
+                        panic!("Only one field can have the 'BoundingLength' attribute");
+                    }
+                    bounding_length = Some((parsers.len(), ident_str.clone()));
+                }
+                // the byte order of the next fields is selected at runtime by the condition
+                if let Some(cond) = get_set_endian(field) {
+                    let (same, swapped) = local_endianness.runtime_values();
+                    let set_endian = format!("value!(if {} {{ {} }} else {{ {} }})", cond, swapped, same);
+                    parsers.push( ("__endianness".to_owned(), ParserTree::Raw(set_endian)) );
+                    endianness = ParserEndianness::Runtime;
+                }
+                // run statements after the field: the variable can be modified
+                if let Some(stmts) = post_exec {
+                    execs.push( (parsers.len(), format!("#[allow(unused_mut)] let mut {id} = {id}; {}", stmts, id=ident_str)) );
+                }
+                field_parsers.push( (ident_str.clone(), ident_str.clone()) );
+                fields.push( (ident_str, None) );
             },
-            None    => panic!("Could not infer parser for field {}", ident_str)
+            None    => unparsed.push(ident_str)
+        }
+    }
+    if nibbles.is_some() {
+        panic!("The 'Nibbles' attribute must be set on two consecutive fields");
+    }
+    if !unparsed.is_empty() {
+        panic!("Could not infer parser for field(s) {}. Hint: use the 'Parse' or 'Ignore' attributes", unparsed.join(", "));
+    }
+    let mut groups : Vec<(String,Vec<String>)> = vec![];
+    for (i, (start, group)) in group_spans.iter().enumerate() {
+        let end = group_spans.get(i + 1).map_or(parsers.len(), |(next, _)| *next);
+        if let Some(group) = group {
+            let names = parsers[*start..end].iter().map(|(name, _)| name.clone());
+            match groups.last_mut() {
+                Some((g, v)) if g == group => v.extend(names),
+                _ => groups.push( (group.clone(), names.collect()) ),
+            }
         }
     }
+    if !checks.is_empty() && bounding_length.is_some() {
+        panic!("The 'SelfExcludingChecksum' and 'BoundingLength' attributes are mutually exclusive");
+    }
+    parsers.extend(checks);
     StructParserTree{
         unnamed,
-        parsers
+        parsers,
+        fields,
+        bounding_length,
+        execs,
+        groups,
+        field_parsers
     }
 }
 
-pub(crate) fn parse_struct(s: &DataStruct) -> StructParserTree {
-    parse_fields(&s.fields)
+pub(crate) fn parse_struct(s: &DataStruct, attrs: &[Attribute], endianness: ParserEndianness) -> StructParserTree {
+    if let Fields::Unit = s.fields {
+        panic!("Unit struct, nothing to generate");
+    }
+    // parsers of the structure attributes, run before the fields
+    let mut prefix = Vec::new();
+    if let Some(tag) = get_magic(attrs) {
+        prefix.push(("_tag".to_owned(), tag));
+    }
+    let mut tree = match get_byte_order_mark(attrs) {
+        None => parse_fields(&s.fields, attrs, endianness),
+        Some((le, be)) => {
+            // parse the mark first, and select the byte order of all fields at runtime
+            let mark = format!("alt!(value!(nom::Endianness::Little, tag!({})) | value!(nom::Endianness::Big, tag!({})))", le, be);
+            prefix.push(("__endianness".to_owned(), ParserTree::Raw(mark)));
+            parse_fields(&s.fields, attrs, ParserEndianness::Runtime)
+        }
+    };
+    prepend_parsers(&mut tree, prefix);
+    tree
+}
+
+/// Parse the fields of an enum variant, after the bytes given by its `Skip` and `Tag` (or
+/// `Magic`) attributes
+pub(crate) fn parse_variant_fields(v: &Variant, endianness: ParserEndianness) -> StructParserTree {
+    let mut prefix = Vec::new();
+    if let Some(n) = get_skip(&v.attrs) {
+        prefix.push(("_skip".to_owned(), ParserTree::Take(n)));
+    }
+    if let Some(tag) = get_magic(&v.attrs) {
+        prefix.push(("_tag".to_owned(), tag));
+    }
+    let mut tree = parse_fields(&v.fields, &v.attrs, endianness);
+    prepend_parsers(&mut tree, prefix);
+    tree
+}
+
+/// Insert parsers before the parsers of the fields
+fn prepend_parsers(tree: &mut StructParserTree, prefix: Vec<(String,ParserTree)>) {
+    let n = prefix.len();
+    tree.parsers.splice(0..0, prefix);
+    if let Some((ref mut idx, _)) = tree.bounding_length {
+        *idx += n;
+    }
+    for (idx, _) in tree.execs.iter_mut() {
+        *idx += n;
+    }
 }
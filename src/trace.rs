@@ -0,0 +1,153 @@
+use crate::endian::{gen_input_bound_type,ParserEndianness};
+use crate::parsertree::ParserTree;
+use crate::structs::{parse_struct,StructParserTree};
+
+fn is_parse_traced(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomParseTraced",
+            _ => false
+        }
+    })
+}
+
+fn is_trace_errors(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::Word(ref ident)) => ident == "NomTraceErrors",
+            _ => false
+        }
+    })
+}
+
+/// Get the name of the field read by each parser, and the name of the variable bound by its
+/// parser (`None` for the parsers which do not read a field, like tags or padding)
+fn get_parser_fields(tree: &StructParserTree) -> Vec<Option<(String,String)>> {
+    tree.parsers.iter()
+        .map(|(var, _)| {
+            tree.field_parsers.iter()
+                .find(|(v, _)| v == var)
+                .map(|(v, field)| {
+                    // tuple struct fields are named by their index
+                    let field = match field.trim_start_matches('_').parse::<usize>() {
+                        Ok(idx) if tree.unnamed => idx.to_string(),
+                        _ => field.clone(),
+                    };
+                    (v.clone(), field)
+                })
+        })
+        .collect()
+}
+
+/// Generate the `parse_trace` function (if requested)
+///
+/// The function parses the structure like `parse`, but if an error occurs, it also returns
+/// the path to the failing field (for ex. `["Outer.inner", "Inner.value"]`, or
+/// `Outer.group.inner` if the field is in a group). The path is stored in `__path`: each
+/// field inserts its name on failure, after the nested structures (which are parsed using
+/// their `parse_trace` function) added theirs. It is cleared when a field succeeds, since a
+/// nested error may have been recovered (for ex. by `opt!`).
+pub(crate) fn gen_parse_trace(ast: &syn::DeriveInput, s: &syn::DataStruct, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    if !is_trace_errors(&ast.attrs) {
+        return quote!{};
+    }
+    let name = &ast.ident;
+    let mut tree = parse_struct(s, &ast.attrs, endianness);
+    let parser_fields = get_parser_fields(&tree);
+    let groups = tree.groups.clone();
+    tree.parsers = tree.parsers.into_iter()
+        .enumerate()
+        .map(|(idx, (var, mut p))| {
+            // nested structures must also have the `NomTraceErrors` attribute
+            p.for_each_leaf(&mut |leaf| {
+                if let ParserTree::CallParse(t) = leaf {
+                    *leaf = ParserTree::Raw(format!(
+                        "call!(|i_| {{ let i_: &[u8] = i_; \
+                          match {}::parse_trace(i_) {{ \
+                            Ok(r) => Ok(r), \
+                            Err((e, p)) => {{ __path.borrow_mut().extend(p); Err(e) }} \
+                          }} }})", t));
+                }
+            });
+            // the other parsers (tags, padding, etc.) are reported under the next field, or
+            // under the name of the structure after the last field
+            let path = match parser_fields[idx..].iter().find_map(|f| f.as_ref()) {
+                // fields in a group are prefixed by its label
+                Some((v, field)) => match groups.iter().find(|(_, names)| names.contains(v)) {
+                    Some((group, _)) => format!("{}.{}.{}", name, group, field),
+                    None             => format!("{}.{}", name, field),
+                },
+                None => name.to_string(),
+            };
+            let path = format!("{:?}", path);
+            let traced = ParserTree::Raw(format!(
+                "call!(|i_| {{ let i_: &[u8] = i_; \
+                  match map!(i_, {p}, |x| x) {{ \
+                    Ok(r) => {{ __path.borrow_mut().clear(); Ok(r) }}, \
+                    Err(e) => {{ __path.borrow_mut().insert(0, {path}); Err(e) }} \
+                  }} }})", p=p, path=path));
+            (var, traced)
+        })
+        .collect();
+    let body = tree.gen_do_parse(&quote!{ #name });
+    let output = gen_input_bound_type(ast, &quote!{ '_ });
+    quote!{
+        fn parse_trace(i: &[u8]) -> Result<(&[u8],#output), (nom::Err<&[u8]>, Vec<&'static str>)> {
+            let __path = ::std::cell::RefCell::new(Vec::new());
+            let res : IResult<&[u8],#output> = #body;
+            res.map_err(|e| (e, __path.into_inner()))
+        }
+    }
+}
+
+/// Generate the `parse_traced` function (if requested)
+///
+/// The function parses the structure like `parse`, and calls `cb` with the name
+/// (`Struct.field`), start and end offsets of each field, once it is parsed. The offsets
+/// are relative to the input of the function. The nested structures are parsed using their
+/// `parse_traced` function, so their fields are reported (before the field containing them),
+/// with offsets translated to the input of the outer structure.
+pub(crate) fn gen_parse_traced(ast: &syn::DeriveInput, s: &syn::DataStruct, endianness: ParserEndianness) -> proc_macro2::TokenStream {
+    if !is_parse_traced(&ast.attrs) {
+        return quote!{};
+    }
+    let name = &ast.ident;
+    let mut tree = parse_struct(s, &ast.attrs, endianness);
+    let parser_fields = get_parser_fields(&tree);
+    tree.parsers = tree.parsers.into_iter()
+        .zip(parser_fields)
+        .map(|((var, mut p), field)| {
+            // nested structures must also have the `NomParseTraced` attribute
+            p.for_each_leaf(&mut |leaf| {
+                if let ParserTree::CallParse(t) = leaf {
+                    *leaf = ParserTree::Raw(format!(
+                        "call!(|i_| {{ let i_: &[u8] = i_; \
+                          let __base = __input.len() - i_.len(); \
+                          {}::parse_traced(i_, &mut |n, s, e| cb(n, __base + s, __base + e)) }})", t));
+                }
+            });
+            // the other parsers (tags, padding, etc.) are not reported
+            let field = match field {
+                Some((_, field)) => field,
+                None => return (var, p),
+            };
+            let path = format!("{:?}", format!("{}.{}", name, field));
+            let traced = ParserTree::Raw(format!(
+                "call!(|i_| {{ let i_: &[u8] = i_; \
+                  let __start = __input.len() - i_.len(); \
+                  match map!(i_, {p}, |x| x) {{ \
+                    Ok((rem, v)) => {{ cb({path}, __start, __input.len() - rem.len()); Ok((rem, v)) }}, \
+                    Err(e) => Err(e) \
+                  }} }})", p=p, path=path));
+            (var, traced)
+        })
+        .collect();
+    let body = tree.gen_do_parse(&quote!{ #name });
+    // the lifetime of the output cannot be elided (there are two references)
+    let output = gen_input_bound_type(ast, &quote!{ '__i });
+    quote!{
+        fn parse_traced<'__i>(i: &'__i [u8], cb: &mut dyn FnMut(&'static str, usize, usize)) -> IResult<&'__i [u8],#output> {
+            #body
+        }
+    }
+}
@@ -0,0 +1,81 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate nom_derive;
+
+extern crate nom;
+
+use nom::*;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+/// A structure with an async parser
+#[derive(Debug,PartialEq,Nom)]
+#[NomAsync]
+struct AsyncHeader {
+    pub a: u8,
+    pub b: u16,
+}
+
+/// An enum with an async parser, using a default selector
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+#[NomAsync="1"]
+enum AsyncBody {
+    #[Selector("0")] A(u32),
+    #[Selector("1")] B(u16),
+}
+
+/// A message, parsed from an async function
+#[derive(Debug,PartialEq)]
+struct AsyncMessage {
+    pub header: AsyncHeader,
+    pub body: AsyncBody,
+}
+
+async fn read_message(data: Vec<u8>) -> Result<AsyncMessage,Err<Vec<u8>>> {
+    let (rem, header) = AsyncHeader::parse_async(data).await?;
+    let (_, body) = AsyncBody::parse_async(rem).await?;
+    Ok(AsyncMessage{ header, body })
+}
+
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+/// Run a future to completion (the generated parsers never wait)
+fn block_on<F: Future>(f: F) -> F::Output {
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = Context::from_waker(&waker);
+    let mut f = Box::pin(f);
+    loop {
+        if let Poll::Ready(res) = Pin::as_mut(&mut f).poll(&mut cx) {
+            return res;
+        }
+    }
+}
+
+fn assert_send_static<T: Send + 'static>(_: &T) {}
+
+#[test]
+fn test_parse_async() {
+    let input = b"\x01\x00\x02\x00\x03\xff".to_vec();
+    let fut = read_message(input);
+    // the future does not borrow the input
+    assert_send_static(&fut);
+    let res = block_on(fut);
+    assert_eq!(res, Ok(AsyncMessage{ header: AsyncHeader{a:1, b:2}, body: AsyncBody::B(3) }));
+    // the remaining bytes are returned
+    let res = block_on(AsyncHeader::parse_async(b"\x01\x00\x02\xff".to_vec()));
+    assert_eq!(res, Ok((vec![0xff], AsyncHeader{a:1, b:2})));
+    // errors are converted to owned errors
+    let res = block_on(read_message(b"\x01\x00".to_vec()));
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(2))));
+}
@@ -0,0 +1,129 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate nom_derive;
+
+extern crate nom;
+
+use nom::*;
+
+/// A frame with a CRC-32 of the payload
+#[derive(Debug,PartialEq,Nom)]
+struct FrameCrc32<'a> {
+    pub len: u8,
+    #[Parse="take!(len)"]
+    pub payload: &'a [u8],
+    #[Checksum(algo="crc32", over="payload")]
+    pub crc: u32,
+}
+
+/// A frame with a little-endian CRC-16 of the payload (stored in a Vec)
+#[derive(Debug,PartialEq,Nom)]
+struct FrameCrc16 {
+    pub len: u8,
+    #[Count="len"]
+    pub payload: Vec<u8>,
+    #[LittleEndian]
+    #[Checksum(algo="crc16-ccitt", over="payload")]
+    pub crc: u16,
+}
+
+fn xor8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, b| acc ^ b)
+}
+
+/// A tuple struct with checksums computed using a built-in and a custom algorithm
+#[derive(Debug,PartialEq,Nom)]
+struct FrameSum8<'a>(
+    #[Parse="take!(4)"] &'a [u8],
+    #[Checksum(algo="sum8", over="_0")] u8,
+    #[Checksum(algo="xor8", over="_0")] u8,
+);
+
+/// A record with a checksum in the middle, covering all the bytes of the record
+#[derive(Debug,PartialEq,Nom)]
+struct RecordSum8 {
+    pub kind: u8,
+    #[SelfExcludingChecksum="sum8"]
+    pub sum: u8,
+    pub value: u16,
+}
+
+/// The Internet checksum (one's complement of the one's complement sum of 16-bit words)
+fn internet_checksum(data: &[u8]) -> u16 {
+    let mut sum = data.chunks(2)
+        .map(|w| u32::from(w[0]) << 8 | u32::from(*w.get(1).unwrap_or(&0)))
+        .sum::<u32>();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// An IPv4-like header, where the checksum is computed with the checksum field set to zero
+#[derive(Debug,PartialEq,Nom)]
+struct HeaderWithChecksum<'a> {
+    pub version: u8,
+    pub len: u8,
+    pub id: u16,
+    #[SelfExcludingChecksum="internet_checksum"]
+    pub checksum: u16,
+    pub ttl: u8,
+    #[Parse="take!(len)"]
+    pub options: &'a [u8],
+}
+
+#[test]
+fn test_checksum_crc32() {
+    let input = b"\x09123456789\xcb\xf4\x39\x26";
+    let res = FrameCrc32::parse(input);
+    assert_eq!(res, Ok((&input[14..],FrameCrc32{len:9, payload:b"123456789", crc:0xcbf4_3926})));
+    // corrupted payload
+    let input = b"\x09123456788\xcb\xf4\x39\x26";
+    let res = FrameCrc32::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[10..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_checksum_crc16() {
+    let input = b"\x09123456789\xb1\x29";
+    let res = FrameCrc16::parse(input);
+    assert_eq!(res, Ok((&input[12..],FrameCrc16{len:9, payload:b"123456789".to_vec(), crc:0x29b1})));
+    let input = b"\x09123456789\x29\xb1";
+    assert!(FrameCrc16::parse(input).is_err());
+}
+
+#[test]
+fn test_checksum_sum8() {
+    let input = b"\x01\x02\x03\xff\x05\xff";
+    let res = FrameSum8::parse(input);
+    assert_eq!(res, Ok((&input[6..],FrameSum8(b"\x01\x02\x03\xff", 0x05, 0xff))));
+    let input = b"\x01\x02\x03\xff\x06\xff";
+    assert!(FrameSum8::parse(input).is_err());
+    let input = b"\x01\x02\x03\xff\x05\xfe";
+    assert!(FrameSum8::parse(input).is_err());
+}
+
+#[test]
+fn test_self_excluding_checksum() {
+    let input = b"\x01\x06\x02\x03\xff";
+    let res = RecordSum8::parse(input);
+    assert_eq!(res, Ok((&input[4..],RecordSum8{kind:1, sum:6, value:0x203})));
+    // corrupted data after the checksum
+    let input = b"\x01\x06\x02\x04";
+    let res = RecordSum8::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+
+    let mut input = b"\x45\x03\x12\x34\x00\x00\x40abc".to_vec();
+    let checksum = internet_checksum(&input);
+    input[4..6].copy_from_slice(&checksum.to_be_bytes());
+    let res = HeaderWithChecksum::parse(&input);
+    assert_eq!(res, Ok((&input[10..],HeaderWithChecksum{version:0x45, len:3, id:0x1234, checksum, ttl:0x40, options:b"abc"})));
+    // the checksum of the whole header (including the checksum) is zero
+    assert_eq!(internet_checksum(&input), 0);
+    input[9] = b'd';
+    let res = HeaderWithChecksum::parse(&input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[4..], ErrorKind::Verify))));
+}
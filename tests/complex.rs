@@ -13,10 +13,26 @@ use nom::*;
 #[derive(Debug,PartialEq,Nom)]
 struct StructWithComplexParser {
     pub a: u32,
-    #[Parse="cond!(a > 0,be_u64)"]
+    #[ParseOuter="cond!(a > 0,be_u64)"]
     pub b: Option<u64>,
 }
 
+/// A structure with a field which cannot be parsed
+#[derive(Nom)]
+struct StructWithCallback {
+    pub a: u32,
+    #[Ignore]
+    pub callback: Option<Box<dyn Fn()>>,
+}
+
+/// An enum with a field which cannot be parsed
+#[derive(Nom)]
+#[Selector="u8"]
+enum EnumWithCallback {
+    #[Selector("0")] Field1(u32, #[Ignore] Option<Box<dyn Fn()>>),
+    #[Selector("1")] Field2(u16),
+}
+
 
 #[test]
 fn test_struct_complex_parse() {
@@ -24,3 +40,28 @@ fn test_struct_complex_parse() {
     let res = StructWithComplexParser::parse(input);
     assert_eq!(res, Ok((&input[12..],StructWithComplexParser{a:1,b:Some(0x1234567812345678)})));
 }
+
+#[test]
+fn test_struct_ignore() {
+    let input = b"\x00\x00\x00\x01";
+    let (rem, s) = StructWithCallback::parse(input).expect("parsing failed");
+    assert!(rem.is_empty());
+    assert_eq!(s.a, 1);
+    assert!(s.callback.is_none());
+}
+
+#[test]
+fn test_enum_ignore() {
+    let input = b"\x00\x00\x00\x01";
+    let (rem, e) = EnumWithCallback::parse(input, 0).expect("parsing failed");
+    assert!(rem.is_empty());
+    match e {
+        EnumWithCallback::Field1(a, cb) => { assert_eq!(a, 1); assert!(cb.is_none()); },
+        EnumWithCallback::Field2(_) => panic!("wrong variant"),
+    }
+    let input = b"\x00\x02";
+    match EnumWithCallback::parse(input, 1) {
+        Ok((_, EnumWithCallback::Field2(b))) => assert_eq!(b, 2),
+        _ => panic!("parsing failed"),
+    }
+}
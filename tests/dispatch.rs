@@ -0,0 +1,86 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate nom_derive;
+
+extern crate nom;
+
+use nom::*;
+
+/// A message with a fixed layout
+#[derive(Debug,PartialEq,Nom)]
+#[ParseErased]
+struct Hello {
+    pub version: u8,
+    pub flags: u16,
+}
+
+/// A message with variants, parsed using a default selector
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+#[ParseErased="1"]
+enum Data {
+    #[Selector("0")] Empty(u8),
+    #[Selector("1")] Value(u32),
+}
+
+/// A message containing only a status code
+#[derive(Debug,PartialEq,Eq,Nom)]
+#[repr(u8)]
+#[ParseErased]
+enum Status {
+    Ok = 0,
+    Failed = 1,
+}
+
+#[derive(Debug,PartialEq)]
+enum Message {
+    Hello(Hello),
+    Data(Data),
+    Status(Status),
+}
+
+type MessageParser = fn(&[u8]) -> IResult<&[u8],Message>;
+
+static HELLO_PARSER : fn(&[u8]) -> IResult<&[u8],Hello> = Hello::parse_erased;
+static DATA_PARSER : fn(&[u8]) -> IResult<&[u8],Data> = Data::parse_erased;
+static STATUS_PARSER : fn(&[u8]) -> IResult<&[u8],Status> = Status::parse_erased;
+
+/// Parsers of messages, indexed by the message type
+static DISPATCH : [(u8, MessageParser); 3] = [
+    (1, |i| map!(i, HELLO_PARSER, Message::Hello)),
+    (2, |i| map!(i, DATA_PARSER, Message::Data)),
+    (3, |i| map!(i, STATUS_PARSER, Message::Status)),
+];
+
+fn parse_message(i: &[u8]) -> IResult<&[u8],Message> {
+    let (rem, msg_type) = be_u8(i)?;
+    match DISPATCH.iter().find(|(t,_)| *t == msg_type) {
+        Some((_, parser)) => parser(rem),
+        None              => Err(Err::Error(error_position!(i, ErrorKind::Switch))),
+    }
+}
+
+#[test]
+fn test_parse_erased() {
+    let input = b"\x01\x00\x02";
+    assert_eq!(Hello::parse_erased(input), Ok((&input[3..],Hello{version:1, flags:2})));
+    let input = b"\x00\x00\x00\x02";
+    assert_eq!(Data::parse_erased(input), Ok((&input[4..],Data::Value(2))));
+    let input = b"\x01";
+    assert_eq!(Status::parse_erased(input), Ok((&input[1..],Status::Failed)));
+}
+
+#[test]
+fn test_dispatch_table() {
+    let input = b"\x01\x01\x00\x02";
+    assert_eq!(parse_message(input), Ok((&input[4..],Message::Hello(Hello{version:1, flags:2}))));
+    let input = b"\x02\x00\x00\x00\x02";
+    assert_eq!(parse_message(input), Ok((&input[5..],Message::Data(Data::Value(2)))));
+    let input = b"\x03\x00";
+    assert_eq!(parse_message(input), Ok((&input[2..],Message::Status(Status::Ok))));
+    let input = b"\x04\x00";
+    assert!(parse_message(input).is_err());
+}
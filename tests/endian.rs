@@ -0,0 +1,661 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate nom_derive;
+
+extern crate nom;
+
+use nom::*;
+
+/// A simple structure, with little-endian integers
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructLE {
+    pub a: u16,
+    pub b: u16,
+    pub c: u16,
+}
+
+/// A little-endian structure, with an explicit parser for one field
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructLEWithParser {
+    pub a: u32,
+    #[Parse="be_u32"]
+    pub b: u32,
+}
+
+/// A big-endian structure, with one little-endian field
+#[derive(Debug,PartialEq,Nom)]
+struct StructFieldLE {
+    pub a: u16,
+    #[LittleEndian]
+    pub b: u32,
+}
+
+/// A little-endian structure, with one big-endian field
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructFieldBE {
+    pub a: u16,
+    #[BigEndian]
+    pub b: u32,
+}
+
+/// A structure with mixed endianness on container fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructFieldContainers {
+    pub a: u8,
+    #[LittleEndian]
+    #[Count="a"]
+    pub b: Vec<u16>,
+    #[BigEndian]
+    #[Count="a"]
+    pub c: Vec<u16>,
+    #[LittleEndian]
+    pub d: Option<u32>,
+}
+
+/// A big-endian structure, with little-endian fields of nested container types
+#[derive(Debug,PartialEq,Nom)]
+struct StructFieldNestedContainers {
+    #[LittleEndian]
+    pub a: [u16; 2],
+    #[LittleEndian]
+    pub b: Option<[u16; 2]>,
+    #[LittleEndian]
+    pub c: Vec<u32>,
+}
+
+/// A little-endian structure, with floating-point values
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructLEFloat {
+    pub a: u8,
+    #[Count="a"]
+    pub b: Vec<f32>,
+}
+
+/// A structure with floating-point values, and one little-endian field
+#[derive(Debug,PartialEq,Nom)]
+struct FloatHeader {
+    pub scale: f32,
+    #[LittleEndian]
+    pub offset: f64,
+}
+
+/// A little-endian structure, with container types
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructLEContainers {
+    pub a: u16,
+    #[Count="a"]
+    pub b: Vec<u16>,
+    pub c: Option<u32>,
+    pub d: Vec<u16>,
+}
+
+/// A little-endian header, with many fields
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct HeaderLE {
+    pub magic: u32,
+    pub version: u16,
+    pub flags: u16,
+    pub f1: u16,
+    pub f2: u16,
+    pub f3: u32,
+    pub f4: u32,
+    pub f5: u16,
+    pub f6: u16,
+    pub f7: u32,
+    pub f8: u32,
+    pub f9: u16,
+}
+
+/// A little-endian structure, with 128-bit integers
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructLE128 {
+    pub a: u128,
+    #[BigEndian]
+    pub b: i128,
+}
+
+/// A structure using the little-endian derive
+#[derive(Debug,PartialEq,NomLE)]
+struct StructNomLE {
+    pub a: u16,
+    pub b: Vec<u32>,
+}
+
+/// A structure using the big-endian derive
+#[derive(Debug,PartialEq,NomBE)]
+struct StructNomBE {
+    pub a: u16,
+    #[LittleEndian]
+    pub b: u32,
+}
+
+/// An enum using the little-endian derive
+#[derive(Debug,PartialEq,NomLE)]
+#[Selector="u8"]
+enum EnumNomLE {
+    #[Selector("0")] Field1(u16),
+    #[Selector("1")] Field2{ a: u32 },
+}
+
+/// A fieldless enum using the little-endian derive
+#[derive(Debug,PartialEq,Eq,NomLE)]
+#[repr(u16)]
+enum FieldlessNomLE {
+    A = 1,
+    B = 0x0102,
+}
+
+/// A PCAP global header: the byte order is given by the magic value
+#[derive(Debug,PartialEq,Nom)]
+struct PcapGlobalHeader {
+    #[SetEndian="magic == 0xd4c3_b2a1"]
+    pub magic: u32,
+    pub version_major: u16,
+    pub version_minor: u16,
+    pub thiszone: i32,
+    pub sigfigs: u32,
+    pub snaplen: u32,
+    pub network: u32,
+}
+
+/// A little-endian structure with runtime byte order, and one fixed big-endian field
+#[derive(Debug,PartialEq,NomLE)]
+struct StructSetEndianLE {
+    pub a: u8,
+    #[SetEndian="bom == 0xfeff"]
+    pub bom: u16,
+    pub b: Vec<f32>,
+    #[BigEndian]
+    pub c: u16,
+}
+
+/// A structure parsed in both byte orders
+#[derive(Debug,PartialEq,NomLE)]
+#[NomBothEndian]
+struct StructBothEndian {
+    pub a: u16,
+    pub b: Vec<u32>,
+    #[BigEndian]
+    pub c: u16,
+}
+
+/// An enum parsed in both byte orders
+#[derive(Debug,PartialEq,Nom)]
+#[NomBothEndian]
+#[Selector="u8"]
+enum EnumBothEndian {
+    #[Selector("0")] Field1(u16),
+    #[Selector("1")] Field2{ a: u32 },
+}
+
+/// A fieldless enum with a little-endian u16 discriminant
+#[derive(Debug,PartialEq,Eq,Nom)]
+#[LittleEndian]
+#[repr(u16)]
+enum FieldlessLE16 {
+    A = 1,
+    B = 0x0102,
+}
+
+/// A fieldless enum with a little-endian u32 discriminant
+#[derive(Debug,PartialEq,Eq,Nom)]
+#[LittleEndian]
+#[repr(u32)]
+enum FieldlessLE32 {
+    A = 1,
+    B = 0x0102_0304,
+}
+
+/// A TIFF-like header, whose byte order is given by a mark
+#[derive(Debug,PartialEq,Nom)]
+#[NomByteOrderMark(le="b\"II\"", be="b\"MM\"")]
+struct TiffHeader {
+    pub version: u16,
+    pub offset: u32,
+    pub first: Record,
+}
+
+/// A structure using the byte order of the target, with one big-endian field
+#[derive(Debug,PartialEq,Nom)]
+#[NativeEndian]
+struct StructNative {
+    pub a: u32,
+    #[Count="2"]
+    pub b: Vec<u16>,
+    #[BigEndian]
+    pub c: u16,
+}
+
+/// A big-endian structure, with one field using the byte order of the target
+#[derive(Debug,PartialEq,Nom)]
+struct StructFieldNative {
+    pub a: u16,
+    #[NativeEndian]
+    pub b: u32,
+}
+
+/// A structure parsed in both byte orders, using the byte order of the target by default
+#[derive(Debug,PartialEq,Nom)]
+#[NomBothEndian]
+#[NativeEndian]
+struct StructBothNative {
+    pub a: u32,
+}
+
+/// A fieldless enum using the byte order of the target
+#[derive(Debug,PartialEq,Eq,Nom)]
+#[NativeEndian]
+#[repr(u16)]
+enum FieldlessNative {
+    A = 1,
+    B = 0x0102,
+}
+
+/// A fieldless enum parsed in both byte orders
+#[derive(Debug,PartialEq,Eq,Nom)]
+#[NomBothEndian]
+#[repr(u16)]
+enum FieldlessBothEndian {
+    A = 1,
+    B = 0x0100,
+}
+
+/// A structure with the byte order given as argument
+#[derive(Debug,PartialEq,Nom)]
+#[DynamicEndian]
+struct StructDynamic {
+    pub a: u16,
+    pub b: Option<u32>,
+}
+
+/// A structure with the byte order given as argument, forwarded to the nested structure
+#[derive(Debug,PartialEq,Nom)]
+#[DynamicEndian]
+struct StructDynamicNested {
+    pub a: u16,
+    #[Parse="be_u16"]
+    pub b: u16,
+    #[DynamicEndian]
+    pub inner: StructDynamic,
+    #[DynamicEndian]
+    #[Selector="a"]
+    pub e: EnumDynamic,
+}
+
+/// An enum with the byte order given as argument
+#[derive(Debug,PartialEq,Nom)]
+#[DynamicEndian]
+#[Selector="u16"]
+enum EnumDynamic {
+    #[Selector("1")] Field1(u16),
+    #[Selector("_")] Field2(u8),
+}
+
+/// A record which inherits the byte order of its parent
+#[derive(Debug,PartialEq,Nom)]
+#[NomEndianAware]
+struct Record {
+    pub len: u16,
+    #[Count="len"]
+    pub data: Vec<u16>,
+}
+
+/// A record which always uses its own byte order
+#[derive(Debug,PartialEq,Nom)]
+struct FixedRecord {
+    pub a: u16,
+}
+
+/// An enum which inherits the byte order of its parent
+#[derive(Debug,PartialEq,Nom)]
+#[NomEndianAware]
+#[Selector="u8"]
+enum RecordKind {
+    #[Selector("0")] Empty(u8),
+    #[Selector("1")] Value(u32),
+}
+
+/// A file, with the byte order of all records given by the magic value
+#[derive(Debug,PartialEq,Nom)]
+struct FileWithRecords {
+    #[SetEndian="magic == 0xd4c3_b2a1"]
+    pub magic: u32,
+    pub first: Record,
+    pub n: u8,
+    #[Count="n"]
+    pub others: Vec<Record>,
+    pub kind: u8,
+    #[Selector="kind"]
+    pub value: RecordKind,
+    #[BigEndian]
+    pub fixed: FixedRecord,
+}
+
+#[test]
+fn test_struct_little_endian() {
+    let input = b"\x01\x00\x34\x12\xff\x00";
+    let res = StructLE::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructLE{a:1, b:0x1234, c:0xff})));
+}
+
+#[test]
+fn test_struct_little_endian_with_parser() {
+    let input = b"\x01\x00\x00\x00\x00\x00\x00\x01";
+    let res = StructLEWithParser::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructLEWithParser{a:1, b:1})));
+}
+
+#[test]
+fn test_field_little_endian() {
+    let input = b"\x00\x01\x01\x00\x00\x00";
+    let res = StructFieldLE::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructFieldLE{a:1, b:1})));
+}
+
+#[test]
+fn test_field_big_endian() {
+    let input = b"\x01\x00\x00\x00\x00\x01";
+    let res = StructFieldBE::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructFieldBE{a:1, b:1})));
+}
+
+#[test]
+fn test_struct_little_endian_containers() {
+    let input = b"\x02\x00\x01\x00\x02\x00\x03\x00\x00\x00\x04\x00\x05\x00";
+    let res = StructLEContainers::parse(input);
+    assert_eq!(res, Ok((&input[14..],StructLEContainers{a:2, b:vec![1,2], c:Some(3), d:vec![4,5]})));
+}
+
+#[test]
+fn test_header_little_endian() {
+    let input = b"\x04\x03\x02\x01\x02\x00\x01\x80\x01\x00\x02\x00\x03\x00\x00\x00\x04\x00\x00\x00\x05\x00\x06\x00\x07\x00\x00\x00\x08\x00\x00\x00\x09\x00";
+    let res = HeaderLE::parse(input);
+    assert_eq!(res, Ok((&input[34..],HeaderLE{
+        magic:0x01020304, version:2, flags:0x8001,
+        f1:1, f2:2, f3:3, f4:4, f5:5, f6:6, f7:7, f8:8, f9:9
+    })));
+}
+
+#[test]
+fn test_field_endianness_containers() {
+    let input = b"\x02\x01\x00\x02\x00\x00\x01\x00\x02\x03\x00\x00\x00";
+    let res = StructFieldContainers::parse(input);
+    assert_eq!(res, Ok((&input[13..],StructFieldContainers{a:2, b:vec![1,2], c:vec![1,2], d:Some(3)})));
+}
+
+#[test]
+fn test_field_endianness_nested_containers() {
+    let input = b"\x01\x00\x02\x00\x03\x00\x04\x00\x05\x00\x00\x00\x06\x00\x00\x00";
+    let res = StructFieldNestedContainers::parse(input);
+    assert_eq!(res, Ok((&input[16..],StructFieldNestedContainers{a:[1,2], b:Some([3,4]), c:vec![5,6]})));
+}
+
+#[test]
+fn test_derive_nom_le() {
+    let input = b"\x01\x00\x02\x00\x00\x00";
+    let res = StructNomLE::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructNomLE{a:1, b:vec![2]})));
+}
+
+#[test]
+fn test_derive_nom_be() {
+    let input = b"\x00\x01\x02\x00\x00\x00";
+    let res = StructNomBE::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructNomBE{a:1, b:2})));
+}
+
+#[test]
+fn test_derive_nom_le_enum() {
+    let input = b"\x01\x00\x00\x00";
+    assert_eq!(EnumNomLE::parse(input, 0), Ok((&input[2..],EnumNomLE::Field1(1))));
+    assert_eq!(EnumNomLE::parse(input, 1), Ok((&input[4..],EnumNomLE::Field2{a:1})));
+}
+
+#[test]
+fn test_derive_nom_le_fieldless_enum() {
+    let empty : &[u8] = b"";
+    assert_eq!(FieldlessNomLE::parse(b"\x01\x00"), Ok((empty,FieldlessNomLE::A)));
+    assert_eq!(FieldlessNomLE::parse(b"\x02\x01"), Ok((empty,FieldlessNomLE::B)));
+    assert!(FieldlessNomLE::parse(b"\x00\x01").is_err());
+}
+
+#[test]
+fn test_struct_little_endian_float() {
+    let input = b"\x02\x00\x00\xc0\x3f\x00\x00\x20\xc0";
+    let res = StructLEFloat::parse(input);
+    assert_eq!(res, Ok((&input[9..],StructLEFloat{a:2, b:vec![1.5,-2.5]})));
+}
+
+#[test]
+fn test_field_little_endian_float() {
+    let input = b"\x3f\xc0\x00\x00\x00\x00\x00\x00\x00\x00\x04\xc0";
+    let res = FloatHeader::parse(input);
+    assert_eq!(res, Ok((&input[12..],FloatHeader{scale:1.5, offset:-2.5})));
+}
+
+#[test]
+fn test_set_endian_pcap() {
+    let expected_be = PcapGlobalHeader{
+        magic: 0xa1b2_c3d4, version_major: 2, version_minor: 4,
+        thiszone: -1, sigfigs: 0, snaplen: 0xffff, network: 1,
+    };
+    let input_be = b"\xa1\xb2\xc3\xd4\x00\x02\x00\x04\xff\xff\xff\xff\x00\x00\x00\x00\x00\x00\xff\xff\x00\x00\x00\x01";
+    let res = PcapGlobalHeader::parse(input_be);
+    assert_eq!(res, Ok((&input_be[24..],expected_be)));
+    let expected_le = PcapGlobalHeader{
+        magic: 0xd4c3_b2a1, version_major: 2, version_minor: 4,
+        thiszone: -1, sigfigs: 0, snaplen: 0xffff, network: 1,
+    };
+    let input_le = b"\xd4\xc3\xb2\xa1\x02\x00\x04\x00\xff\xff\xff\xff\x00\x00\x00\x00\xff\xff\x00\x00\x01\x00\x00\x00";
+    let res = PcapGlobalHeader::parse(input_le);
+    assert_eq!(res, Ok((&input_le[24..],expected_le)));
+}
+
+#[test]
+fn test_set_endian_little_endian() {
+    let empty : &[u8] = b"";
+    let input = b"\x01\xfe\xff\x00\x00\xc0\x3f\x00\x01";
+    let res = StructSetEndianLE::parse(input);
+    assert_eq!(res, Ok((empty,StructSetEndianLE{a:1, bom:0xfffe, b:vec![1.5], c:1})));
+    let input = b"\x01\xff\xfe\x3f\xc0\x00\x00\x00\x01";
+    let res = StructSetEndianLE::parse(input);
+    assert_eq!(res, Ok((empty,StructSetEndianLE{a:1, bom:0xfeff, b:vec![1.5], c:1})));
+}
+
+#[test]
+fn test_struct_both_endian() {
+    let empty : &[u8] = b"";
+    let input = b"\x00\x01\x00\x00\x00\x02\x00\x03";
+    assert_eq!(StructBothEndian::parse_be(input), Ok((empty,StructBothEndian{a:1, b:vec![2], c:3})));
+    assert_eq!(StructBothEndian::parse_le(input),
+               Ok((empty,StructBothEndian{a:0x100, b:vec![0x0200_0000], c:3})));
+    // the default byte order of the derive is little-endian
+    assert_eq!(StructBothEndian::parse(input), StructBothEndian::parse_le(input));
+}
+
+#[test]
+fn test_enum_both_endian() {
+    let input = b"\x00\x01\x00\x00";
+    assert_eq!(EnumBothEndian::parse_be(input, 0), Ok((&input[2..],EnumBothEndian::Field1(1))));
+    assert_eq!(EnumBothEndian::parse_le(input, 0), Ok((&input[2..],EnumBothEndian::Field1(0x100))));
+    assert_eq!(EnumBothEndian::parse_le(input, 1), Ok((&input[4..],EnumBothEndian::Field2{a:0x100})));
+    assert_eq!(EnumBothEndian::parse(input, 1), EnumBothEndian::parse_be(input, 1));
+}
+
+#[test]
+fn test_fieldless_enum_both_endian() {
+    let empty : &[u8] = b"";
+    assert_eq!(FieldlessBothEndian::parse_be(b"\x00\x01"), Ok((empty,FieldlessBothEndian::A)));
+    assert_eq!(FieldlessBothEndian::parse_le(b"\x00\x01"), Ok((empty,FieldlessBothEndian::B)));
+    assert_eq!(FieldlessBothEndian::parse(b"\x01\x00"), Ok((empty,FieldlessBothEndian::B)));
+    assert!(FieldlessBothEndian::parse_le(b"\x00\x02").is_err());
+}
+
+#[test]
+fn test_struct_little_endian_128() {
+    let input = b"\xff\xee\xdd\xcc\xbb\xaa\x99\x88\x77\x66\x55\x44\x33\x22\x11\x00\
+                  \xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xfe";
+    let res = StructLE128::parse(input);
+    assert_eq!(res, Ok((&input[32..],StructLE128{a:0x0011_2233_4455_6677_8899_aabb_ccdd_eeff, b:-2})));
+}
+
+#[test]
+fn test_struct_dynamic_endian() {
+    let input = b"\x00\x01\x00\x00\x00\x02";
+    let res = StructDynamic::parse(input, Endianness::Big);
+    assert_eq!(res, Ok((&input[6..],StructDynamic{a:1, b:Some(2)})));
+    let res = StructDynamic::parse(input, Endianness::Little);
+    assert_eq!(res, Ok((&input[6..],StructDynamic{a:0x100, b:Some(0x0200_0000)})));
+}
+
+#[test]
+fn test_struct_dynamic_endian_nested() {
+    let input = b"\x01\x00\x00\x01\x02\x00\x03\x00\x00\x00\x04\x00";
+    let res = StructDynamicNested::parse(input, Endianness::Little);
+    assert_eq!(res, Ok((&input[12..],StructDynamicNested{
+        a: 1, b: 1,
+        inner: StructDynamic{a:2, b:Some(3)},
+        e: EnumDynamic::Field1(4),
+    })));
+    let res = StructDynamicNested::parse(input, Endianness::Big);
+    assert_eq!(res, Ok((&input[11..],StructDynamicNested{
+        a: 0x100, b: 1,
+        inner: StructDynamic{a:0x200, b:Some(0x0300_0000)},
+        e: EnumDynamic::Field2(4),
+    })));
+}
+
+#[test]
+fn test_endian_aware_nested() {
+    let input_be = b"\xa1\xb2\xc3\xd4\x00\x01\x00\x02\x01\x00\x02\x00\x03\x00\x04\x01\x00\x00\x00\x05\x00\x06";
+    let input_le = b"\xd4\xc3\xb2\xa1\x01\x00\x02\x00\x01\x02\x00\x03\x00\x04\x00\x01\x05\x00\x00\x00\x00\x06";
+    let res = FileWithRecords::parse(input_be);
+    assert_eq!(res, Ok((&input_be[22..],FileWithRecords{
+        magic: 0xa1b2_c3d4,
+        first: Record{len:1, data:vec![2]},
+        n: 1,
+        others: vec![Record{len:2, data:vec![3,4]}],
+        kind: 1,
+        value: RecordKind::Value(5),
+        fixed: FixedRecord{a:6},
+    })));
+    let res = FileWithRecords::parse(input_le);
+    assert_eq!(res, Ok((&input_le[22..],FileWithRecords{
+        magic: 0xd4c3_b2a1,
+        first: Record{len:1, data:vec![2]},
+        n: 1,
+        others: vec![Record{len:2, data:vec![3,4]}],
+        kind: 1,
+        value: RecordKind::Value(5),
+        fixed: FixedRecord{a:6},
+    })));
+    // the parse function of the nested types still uses their default byte order
+    let input = b"\x00\x01\x00\x02";
+    assert_eq!(Record::parse(input), Ok((&input[4..],Record{len:1, data:vec![2]})));
+    assert_eq!(RecordKind::parse(input, 0), Ok((&input[1..],RecordKind::Empty(0))));
+}
+
+#[test]
+fn test_fieldless_enum_little_endian() {
+    let empty : &[u8] = b"";
+    assert_eq!(FieldlessLE16::parse(b"\x01\x00"), Ok((empty,FieldlessLE16::A)));
+    assert_eq!(FieldlessLE16::parse(b"\x02\x01"), Ok((empty,FieldlessLE16::B)));
+    let input = b"\x01\x02";
+    assert_eq!(FieldlessLE16::parse(input), Err(Err::Error(error_position!(&input[..], ErrorKind::MapOpt))));
+
+    assert_eq!(FieldlessLE32::parse(b"\x01\x00\x00\x00"), Ok((empty,FieldlessLE32::A)));
+    assert_eq!(FieldlessLE32::parse(b"\x04\x03\x02\x01"), Ok((empty,FieldlessLE32::B)));
+    let input = b"\x01\x02\x03\x04";
+    assert_eq!(FieldlessLE32::parse(input), Err(Err::Error(error_position!(&input[..], ErrorKind::MapOpt))));
+}
+
+#[test]
+fn test_struct_byte_order_mark() {
+    let input_le = b"II\x2a\x00\x08\x00\x00\x00\x01\x00\x02\x00";
+    let input_be = b"MM\x00\x2a\x00\x00\x00\x08\x00\x01\x00\x02";
+    let expected = TiffHeader{version: 42, offset: 8, first: Record{len:1, data:vec![2]}};
+    let res_le = TiffHeader::parse(input_le);
+    let res_be = TiffHeader::parse(input_be);
+    assert_eq!(res_le, Ok((&input_le[12..],expected)));
+    assert_eq!(res_le, res_be.map(|(_, h)| (&input_le[12..], h)));
+    let input = b"IM\x00\x2a\x00\x00\x00\x08\x00\x01\x00\x02";
+    assert!(TiffHeader::parse(input).is_err());
+}
+
+#[test]
+fn test_native_endian() {
+    let input = [&0x0102_0304u32.to_ne_bytes()[..], &5u16.to_ne_bytes()[..], &6u16.to_ne_bytes()[..], b"\x00\x07"].concat();
+    let res = StructNative::parse(&input);
+    assert_eq!(res, Ok((&input[10..],StructNative{a:0x0102_0304, b:vec![5,6], c:7})));
+
+    let input = [&b"\x00\x01"[..], &0x0102_0304u32.to_ne_bytes()[..]].concat();
+    let res = StructFieldNative::parse(&input);
+    assert_eq!(res, Ok((&input[6..],StructFieldNative{a:1, b:0x0102_0304})));
+
+    let empty : &[u8] = b"";
+    assert_eq!(FieldlessNative::parse(&1u16.to_ne_bytes()), Ok((empty,FieldlessNative::A)));
+    assert_eq!(FieldlessNative::parse(&0x0102u16.to_ne_bytes()), Ok((empty,FieldlessNative::B)));
+    assert!(FieldlessNative::parse(&0x0201u16.to_ne_bytes()).is_err());
+
+    let input = 0x0102_0304u32.to_ne_bytes();
+    assert_eq!(StructBothNative::parse(&input), Ok((empty,StructBothNative{a:0x0102_0304})));
+    let input = b"\x01\x02\x03\x04";
+    assert_eq!(StructBothNative::parse_be(input), Ok((empty,StructBothNative{a:0x0102_0304})));
+    assert_eq!(StructBothNative::parse_le(input), Ok((empty,StructBothNative{a:0x0403_0201})));
+}
+
+/// The byte order of a file, given by a fieldless enum
+#[derive(Debug,PartialEq,Clone,Copy,Nom)]
+#[repr(u8)]
+enum ByteOrder {
+    Big = 0,
+    Little = 1,
+}
+
+impl From<ByteOrder> for Endianness {
+    fn from(order: ByteOrder) -> Endianness {
+        match order {
+            ByteOrder::Big    => Endianness::Big,
+            ByteOrder::Little => Endianness::Little,
+        }
+    }
+}
+
+/// A structure whose byte order is given by an enum field
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithEndianFrom {
+    pub order: ByteOrder,
+    pub a: u8,
+    #[EndianFrom="order"]
+    pub b: u16,
+    pub c: u32,
+    pub record: Record,
+    #[BigEndian]
+    pub d: u16,
+}
+
+#[test]
+fn test_endian_from() {
+    let empty : &[u8] = b"";
+    let input = b"\x00\x01\x00\x02\x00\x00\x00\x03\x00\x01\x00\x04\x00\x05";
+    let res = StructWithEndianFrom::parse(input);
+    assert_eq!(res, Ok((empty,StructWithEndianFrom{
+        order: ByteOrder::Big, a:1, b:2, c:3, record:Record{len:1, data:vec![4]}, d:5
+    })));
+    let input = b"\x01\x01\x02\x00\x03\x00\x00\x00\x01\x00\x04\x00\x00\x05";
+    let res = StructWithEndianFrom::parse(input);
+    assert_eq!(res, Ok((empty,StructWithEndianFrom{
+        order: ByteOrder::Little, a:1, b:2, c:3, record:Record{len:1, data:vec![4]}, d:5
+    })));
+    assert!(StructWithEndianFrom::parse(b"\x02\x01\x02\x00").is_err());
+}
@@ -15,6 +15,7 @@ pub struct MessageType(pub u8);
 /// An enum with unnamed fields
 #[derive(Debug,PartialEq,Nom)]
 #[Selector="MessageType"]
+#[NomSelectorFn]
 pub enum U1{
     #[Selector("MessageType(0)")] Field1(u32),
     #[Selector("MessageType(1)")] Field2(Option<u32>),
@@ -23,6 +24,7 @@ pub enum U1{
 /// An enum with unnamed fields and primitive selector
 #[derive(Debug,PartialEq,Nom)]
 #[Selector="u8"]
+#[NomSelectorFn]
 pub enum U1b{
     #[Selector("0")] Field1(u32),
     #[Selector("1")] Field2(Option<u32>),
@@ -102,6 +104,260 @@ pub struct U8S1 {
     pub a: u32,
 }
 
+/// A fieldless enum, used as selector for another enum
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Nom)]
+#[repr(u8)]
+pub enum U9Kind {
+    A = 1,
+    B = 2,
+}
+
+/// An enum, using a fieldless enum as selector
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="U9Kind"]
+pub enum U9 {
+    #[Selector("U9Kind::A")] Field1(u16),
+    #[Selector("U9Kind::B")] Field2(u32),
+}
+
+/// A structure using the value of a parsed enum as selector for the next field
+#[derive(Debug,PartialEq,Nom)]
+pub struct S9 {
+    pub kind: U9Kind,
+    #[Selector="kind"]
+    pub value: U9,
+}
+
+/// A structure using a previously parsed value as selector (chained)
+#[derive(Debug,PartialEq,Nom)]
+pub struct S10 {
+    pub msg_type: MessageType,
+    #[Selector="msg_type"]
+    pub msg_value: U1,
+}
+
+/// A header, containing the selector of the message
+#[derive(Debug,PartialEq,Nom)]
+pub struct Header9 {
+    pub version: u8,
+    pub kind: U9Kind,
+}
+
+/// A structure using a field of a previous structure as selector
+#[derive(Debug,PartialEq,Nom)]
+pub struct S9b {
+    pub header: Header9,
+    #[Selector="header.kind"]
+    pub value: U9,
+}
+
+/// An enum with a variant using the length of the input
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+pub enum U10 {
+    #[Selector("0")] Field1(u32),
+    #[Selector("1")] Field2(#[Count="__rest_len / 2"] Vec<u16>),
+}
+
+/// A structure giving a restricted input to an enum parser
+#[derive(Debug,PartialEq,Nom)]
+pub struct S11 {
+    pub sel: u8,
+    pub len: u8,
+    #[Parse="flat_map!(take!(len),call!(U10::parse,sel))"]
+    pub value: U10,
+    pub trailer: u8,
+}
+
+/// A selector type, which cannot be used in patterns
+#[derive(Debug,Clone,Copy)]
+pub struct Kind(u8);
+
+impl Kind {
+    pub const DATA: Kind = Kind(1);
+    pub const ACK: Kind = Kind(2);
+}
+
+impl PartialEq for Kind {
+    fn eq(&self, other: &Kind) -> bool { self.0 == other.0 }
+}
+
+pub const KIND_RESET: u8 = 3;
+
+/// An enum using associated constants as selectors
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="Kind"]
+pub enum U12 {
+    #[Selector("Kind::DATA")] Data(u16),
+    #[Selector("Kind::ACK")] Ack(u8),
+}
+
+/// An enum using constants as selectors
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+pub enum U13 {
+    #[Selector("KIND_RESET")] Reset(u8),
+    #[Selector("_")] Other(u16),
+}
+
+/// An enum without selector: variants are tried in order, with a fallback
+#[derive(Debug,PartialEq,Nom)]
+#[NomAlt]
+pub enum U14 {
+    #[Selector("_")]
+    Raw(Vec<u8>),
+    Ping{
+        #[Verify="magic == 0x50"]
+        magic: u8,
+        seq: u16,
+    },
+    Data{
+        #[Verify="magic == 0x44"]
+        magic: u8,
+        len: u8,
+        #[Count="len"]
+        data: Vec<u8>,
+    },
+}
+
+/// An enum without selector and without fallback
+#[derive(Debug,PartialEq,Nom)]
+#[NomAlt]
+pub enum U15 {
+    Small{
+        #[Verify="v < 0x80"]
+        v: u8,
+    },
+    Large{
+        #[Verify="(0x8000..0xc000).contains(&v)"]
+        v: u16,
+    },
+}
+
+/// An enum with variants starting with a preamble
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+pub enum U16 {
+    #[Selector("0")] Plain(u16),
+    #[Selector("1")]
+    #[Skip="2"]
+    Reserved(u16),
+    #[Selector("2")]
+    #[Tag="b\"\\x00\\x01\""]
+    Tagged{ a: u16 },
+}
+
+/// An error type for the parsers of the application
+#[derive(Debug,PartialEq)]
+pub struct EnumError(u32);
+
+impl From<u32> for EnumError {
+    fn from(n: u32) -> EnumError { EnumError(n) }
+}
+
+/// An enum parsed with a custom error type
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+#[NomErr="EnumError"]
+pub enum U17 {
+    #[Selector("0")] Short(u16),
+    #[Selector("1")] Long(u32),
+}
+
+/// An enum with ranges and multiple values as selectors
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+pub enum U18 {
+    #[Selector("_")] Unknown(u8),
+    #[Selector("0x10..=0x1f")] Extension(u16),
+    #[Selector("1 | 2 | 3")] Small(u8),
+    #[Selector("MSG_DATA | MSG_CONTROL")] Message(u16),
+}
+
+pub const MSG_DATA: u8 = 0x40;
+pub const MSG_CONTROL: u8 = 0x41;
+
+/// An enum with guards on the selector
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+#[NomSelectorName="kind"]
+pub enum U19 {
+    #[Selector("k if k > 10")] Large(u16),
+    #[Selector("& 0x01 == 1")] Odd(u8),
+    #[Selector("_")] Other{ #[Map="|v: u8| v + kind"] v: u8 },
+}
+
+/// A fieldless enum with a varint discriminant
+#[derive(Debug,PartialEq,Eq,Nom)]
+#[repr(i32)]
+#[NomVarintTag]
+pub enum U20{
+    Small = 1,
+    Medium = 300,
+    Large = 0x1234_5678,
+    Negative = -2,
+}
+
+/// The kind of a message
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Nom)]
+#[repr(u8)]
+pub enum MessageKind {
+    Ping = 1,
+    Data = 2,
+    Close = 3,
+    Reserved = 4,
+}
+
+/// The body of a message, selected by its kind
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="MessageKind"]
+pub enum MessageBody {
+    #[Selector("MessageKind::Ping")] Ping,
+    #[Selector("MessageKind::Data")] Data{ len: u8, #[Count="len"] data: Vec<u8> },
+    #[Selector("MessageKind::Close")] Close(u16),
+    #[Selector("_")] Unknown,
+}
+
+/// A tagged message: the kind, followed by the body
+#[derive(Debug,PartialEq,Nom)]
+pub struct Message {
+    pub kind: MessageKind,
+    #[Selector="kind"]
+    pub body: MessageBody,
+}
+
+/// A selector type without `PartialEq`
+#[derive(Debug,Clone,Copy)]
+pub enum Opcode {
+    Read,
+    Write,
+}
+
+/// An enum using enum variants as selectors (used as patterns)
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="Opcode"]
+pub enum Request {
+    #[Selector("Opcode::Read")] Read(u8),
+    #[Selector("Opcode::Write")] Write(u16),
+}
+
+/// An enum with the variant selected by the high bit of the selector
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+pub enum U11 {
+    #[Selector("& 0x80 != 0")] Extended(u16),
+    #[Selector("0")] Empty(u8),
+    #[Selector("& 0x7f == 1")] Short(u8),
+}
+
+/// A structure using its flags as selector of an enum
+#[derive(Debug,PartialEq,Nom)]
+pub struct S12 {
+    pub flags: u8,
+    #[Selector="flags"]
+    pub value: U11,
+}
+
 
 #[test]
 fn test_enum_unnamed() {
@@ -176,3 +432,260 @@ fn test_struct_in_enum() {
                         U8::Field2(2)
                         )));
 }
+
+#[test]
+fn test_enum_selector_field() {
+    let input = b"\x00\x00\x00\x00\x02";
+    let res = S10::parse(input);
+    assert_eq!(res, Ok((&input[5..],
+                        S10{msg_type:MessageType(0), msg_value:U1::Field1(2)}
+                        )));
+}
+
+#[test]
+fn test_enum_selector_enum() {
+    let input = b"\x01\x00\x02";
+    let res = S9::parse(input);
+    assert_eq!(res, Ok((&input[3..],
+                        S9{kind:U9Kind::A, value:U9::Field1(2)}
+                        )));
+    let input = b"\x02\x00\x00\x00\x02";
+    let res = S9::parse(input);
+    assert_eq!(res, Ok((&input[5..],
+                        S9{kind:U9Kind::B, value:U9::Field2(2)}
+                        )));
+    let input = b"\x03\x00\x00\x00\x02";
+    assert!(S9::parse(input).is_err());
+}
+
+#[test]
+fn test_enum_selector_nested_field() {
+    let input = b"\x04\x01\x00\x02";
+    let res = S9b::parse(input);
+    assert_eq!(res, Ok((&input[4..],
+                        S9b{header:Header9{version:4, kind:U9Kind::A}, value:U9::Field1(2)}
+                        )));
+    let input = b"\x04\x02\x00\x00\x00\x02";
+    let res = S9b::parse(input);
+    assert_eq!(res, Ok((&input[6..],
+                        S9b{header:Header9{version:4, kind:U9Kind::B}, value:U9::Field2(2)}
+                        )));
+}
+
+#[test]
+fn test_enum_rest_len() {
+    let input = b"\x01\x04\x00\x01\x00\x02\xff";
+    let res = S11::parse(input);
+    assert_eq!(res, Ok((&input[7..],
+                        S11{sel:1, len:4, value:U10::Field2(vec![1,2]), trailer:0xff}
+                        )));
+}
+
+#[test]
+fn test_enum_mask_selector() {
+    let input = b"\x81\x01\x02";
+    let res = S12::parse(input);
+    assert_eq!(res, Ok((&input[3..], S12{flags:0x81, value:U11::Extended(0x0102)})));
+    let input = b"\x00\x01\x02";
+    let res = S12::parse(input);
+    assert_eq!(res, Ok((&input[2..], S12{flags:0, value:U11::Empty(1)})));
+    let input = b"\x01\x01\x02";
+    let res = S12::parse(input);
+    assert_eq!(res, Ok((&input[2..], S12{flags:1, value:U11::Short(1)})));
+    let input = b"\x02\x01\x02";
+    assert!(S12::parse(input).is_err());
+}
+
+#[test]
+fn test_enum_const_selector() {
+    let input = b"\x00\x01";
+    assert_eq!(U12::parse(input, Kind::DATA), Ok((&input[2..],U12::Data(1))));
+    assert_eq!(U12::parse(input, Kind::ACK), Ok((&input[1..],U12::Ack(0))));
+    assert!(U12::parse(input, Kind(0)).is_err());
+    assert_eq!(U13::parse(input, 3), Ok((&input[1..],U13::Reset(0))));
+    assert_eq!(U13::parse(input, 4), Ok((&input[2..],U13::Other(1))));
+}
+
+#[test]
+fn test_enum_alt() {
+    let empty : &[u8] = b"";
+    let input = b"\x50\x00\x01\xff";
+    assert_eq!(U14::parse(input), Ok((&input[3..],U14::Ping{magic:0x50, seq:1})));
+    let input = b"\x44\x02\x01\x02";
+    assert_eq!(U14::parse(input), Ok((empty,U14::Data{magic:0x44, len:2, data:vec![1,2]})));
+    // unknown message: fallback
+    let input = b"\x12\x34";
+    assert_eq!(U14::parse(input), Ok((empty,U14::Raw(vec![0x12,0x34]))));
+    // incomplete message: no fallback
+    let input = b"\x50\x00";
+    assert_eq!(U14::parse(input), Err(Err::Incomplete(Needed::Size(2))));
+
+    let input = b"\x01\x02";
+    assert_eq!(U15::parse(input), Ok((&input[1..],U15::Small{v:1})));
+    let input = b"\x81\x02";
+    assert_eq!(U15::parse(input), Ok((empty,U15::Large{v:0x8102})));
+}
+
+#[test]
+fn test_enum_alt_error() {
+    let input = b"\xc0\x00";
+    assert_eq!(U15::parse(input), Err(Err::Error(error_position!(&input[..], ErrorKind::Alt))));
+}
+
+#[test]
+fn test_enum_variant_preamble() {
+    let input = b"\x00\x01\x00\x02";
+    assert_eq!(U16::parse(input, 0), Ok((&input[2..],U16::Plain(1))));
+    assert_eq!(U16::parse(input, 1), Ok((&input[4..],U16::Reserved(2))));
+    assert_eq!(U16::parse(input, 2), Ok((&input[4..],U16::Tagged{a:2})));
+    let input = b"\x00\x02\x00\x02";
+    assert_eq!(U16::parse(input, 2), Err(Err::Error(error_position!(&input[..], ErrorKind::Tag))));
+}
+
+#[test]
+fn test_enum_nom_err() {
+    let input = b"\x00\x01";
+    assert_eq!(U17::parse(input, 0), Ok((&input[2..],U17::Short(1))));
+    let res : IResult<&[u8],U17,EnumError> = U17::parse(input, 1);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(4))));
+    assert_eq!(U17::parse(input, 2), Err(Err::Error(error_position!(&input[..], ErrorKind::Switch))));
+}
+
+#[test]
+fn test_enum_selector_ranges() {
+    let input = b"\x00\x01";
+    assert_eq!(U18::parse(input, 0x10), Ok((&input[2..],U18::Extension(1))));
+    assert_eq!(U18::parse(input, 0x1f), Ok((&input[2..],U18::Extension(1))));
+    assert_eq!(U18::parse(input, 3), Ok((&input[1..],U18::Small(0))));
+    assert_eq!(U18::parse(input, 0x41), Ok((&input[2..],U18::Message(1))));
+    // values outside the range fall through to the default case
+    assert_eq!(U18::parse(input, 0x20), Ok((&input[1..],U18::Unknown(0))));
+    assert_eq!(U18::parse(input, 0), Ok((&input[1..],U18::Unknown(0))));
+}
+
+#[test]
+fn test_enum_selector_guard() {
+    let input = b"\x00\x01";
+    assert_eq!(U19::parse(input, 11), Ok((&input[2..],U19::Large(1))));
+    assert_eq!(U19::parse(input, 200), Ok((&input[2..],U19::Large(1))));
+    assert_eq!(U19::parse(input, 10), Ok((&input[1..],U19::Other{v:10})));
+    assert_eq!(U19::parse(input, 3), Ok((&input[1..],U19::Odd(0))));
+}
+
+#[test]
+fn test_enum_varint_tag() {
+    let input = b"\x01\xff";
+    assert_eq!(U20::parse(input), Ok((&input[1..],U20::Small)));
+    let input = b"\xac\x02\xff";
+    assert_eq!(U20::parse(input), Ok((&input[2..],U20::Medium)));
+    let input = b"\xf8\xac\xd1\x91\x01";
+    assert_eq!(U20::parse(input), Ok((&input[5..],U20::Large)));
+    let input = b"\xfe\xff\xff\xff\xff\xff\xff\xff\xff\x01";
+    assert_eq!(U20::parse(input), Ok((&input[10..],U20::Negative)));
+    // unknown value
+    assert!(U20::parse(b"\x02").is_err());
+    // truncated varint
+    assert_eq!(U20::parse(b"\xac"), Err(Err::Incomplete(Needed::Size(2))));
+    // value larger than 64 bits
+    let input = b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\x02";
+    assert_eq!(U20::parse(input), Err(Err::Error(error_position!(&input[..], ErrorKind::TooLarge))));
+}
+
+#[test]
+fn test_enum_selector_fn() {
+    let input = b"\x00\x00\x00\x02";
+    let (_, value) = U1b::parse(input, 0).expect("parsing failed");
+    assert_eq!(value, U1b::Field1(2));
+    assert_eq!(value.selector(), 0);
+    let (_, value) = U1b::parse(input, 1).expect("parsing failed");
+    assert_eq!(value.selector(), 1);
+    let (_, value) = U1::parse(input, MessageType(0)).expect("parsing failed");
+    assert_eq!(value.selector(), MessageType(0));
+    let (_, value) = U1::parse(input, MessageType(1)).expect("parsing failed");
+    assert_eq!(value.selector(), MessageType(1));
+}
+
+#[test]
+fn test_enum_tagged_messages() {
+    let input = b"\x01\x02\x02ab\x04\x03\x00\x05\x09";
+    let res : IResult<&[u8], Vec<Message>> = many0!(&input[..], complete!(Message::parse));
+    assert_eq!(res, Ok((&input[9..], vec![
+        Message{kind:MessageKind::Ping, body:MessageBody::Ping},
+        Message{kind:MessageKind::Data, body:MessageBody::Data{len:2, data:b"ab".to_vec()}},
+        Message{kind:MessageKind::Reserved, body:MessageBody::Unknown},
+        Message{kind:MessageKind::Close, body:MessageBody::Close(5)},
+    ])));
+}
+
+/// An enum with nom attributes before and after other attributes
+#[derive(Debug,PartialEq,Nom)]
+#[allow(clippy::all)]
+#[Selector="u8"]
+#[doc(alias = "mixed")]
+pub enum EnumWithForeignAttributes {
+    /// A variant with documentation first
+    #[Selector("0")]
+    #[allow(dead_code)]
+    A(#[cfg(all())] #[LittleEndian] #[doc(hidden)] u16),
+    #[cfg_attr(all(), Selector("1"))]
+    /// A variant with documentation last
+    B {
+        #[rustfmt::skip]
+        #[Verify="v != 0"]
+        v: u8,
+    },
+    #[doc = "The default case"]
+    #[Selector("_")]
+    #[cfg(not(any()))]
+    Other,
+}
+
+#[test]
+fn test_enum_with_foreign_attributes() {
+    let input = b"\x01\x02";
+    assert_eq!(EnumWithForeignAttributes::parse(input, 0), Ok((&input[2..], EnumWithForeignAttributes::A(0x201))));
+    assert_eq!(EnumWithForeignAttributes::parse(input, 1), Ok((&input[1..], EnumWithForeignAttributes::B{v:1})));
+    assert_eq!(EnumWithForeignAttributes::parse(input, 2), Ok((&input[..], EnumWithForeignAttributes::Other)));
+    assert!(EnumWithForeignAttributes::parse(b"\x00", 1).is_err());
+}
+
+/// A selector value given by a constant
+const EXTENDED: u8 = 0x40;
+
+/// An enum with lists of selectors
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+pub enum EnumWithSelectorLists {
+    #[Selector("1", "2", "3")]
+    Small(u8),
+    #[Selector(4, 5)]
+    Medium(u16),
+    #[Selector("0x10..=0x1f", "0x30")]
+    Range,
+    #[Selector("EXTENDED", "0x41")]
+    Extended(u8),
+    #[Selector("_")]
+    Other,
+}
+
+#[test]
+fn test_enum_with_selector_lists() {
+    let input = b"\x01\x02";
+    for sel in 1..=3 {
+        assert_eq!(EnumWithSelectorLists::parse(input, sel), Ok((&input[1..], EnumWithSelectorLists::Small(1))));
+    }
+    assert_eq!(EnumWithSelectorLists::parse(input, 4), Ok((&input[2..], EnumWithSelectorLists::Medium(0x102))));
+    assert_eq!(EnumWithSelectorLists::parse(input, 5), Ok((&input[2..], EnumWithSelectorLists::Medium(0x102))));
+    assert_eq!(EnumWithSelectorLists::parse(input, 0x12), Ok((&input[..], EnumWithSelectorLists::Range)));
+    assert_eq!(EnumWithSelectorLists::parse(input, 0x30), Ok((&input[..], EnumWithSelectorLists::Range)));
+    assert_eq!(EnumWithSelectorLists::parse(input, 0x40), Ok((&input[1..], EnumWithSelectorLists::Extended(1))));
+    assert_eq!(EnumWithSelectorLists::parse(input, 0x41), Ok((&input[1..], EnumWithSelectorLists::Extended(1))));
+    assert_eq!(EnumWithSelectorLists::parse(input, 6), Ok((&input[..], EnumWithSelectorLists::Other)));
+}
+
+#[test]
+fn test_enum_variant_selector() {
+    let input = b"\x00\x01";
+    assert_eq!(Request::parse(input, Opcode::Read), Ok((&input[1..],Request::Read(0))));
+    assert_eq!(Request::parse(input, Opcode::Write), Ok((&input[2..],Request::Write(1))));
+}
@@ -34,6 +34,60 @@ struct StructWithPhantomData<'a> {
     phantom: PhantomData<&'a u8>,
 }
 
+/// A structure with verifications on slices
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithSliceVerify<'a> {
+    pub hdr_len: u8,
+    #[Parse="take!(4)"]
+    #[Verify="reserved.iter().all(|b| *b == 0)"]
+    reserved: &'a[u8],
+    #[Parse="take!(hdr_len)"]
+    #[Verify="data.len() == hdr_len as usize"]
+    data: &'a[u8],
+}
+
+/// A structure with fields of variable lengths
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithTakeWhile<'a> {
+    #[TakeWhile="is_alphabetic"]
+    pub key: &'a[u8],
+    pub eq: u8,
+    #[TakeWhile="|c| c != b';'"]
+    pub value: Vec<u8>,
+    pub sep: u8,
+    #[TakeWhile="is_space"]
+    pub spaces: Option<&'a [u8]>,
+}
+
+/// A structure with fields terminated by delimiters
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithTakeUntil<'a> {
+    #[TakeUntil="b\": \""]
+    pub name: &'a[u8],
+    #[Tag=b": "]
+    pub sep: (),
+    #[TakeUntil="b\"\\r\\n\""]
+    pub value: Vec<u8>,
+    #[TakeUntil="\"\\r\\n\""]
+    pub end: &'a[u8],
+}
+
+/// A structure with trailing data
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithRest<'a> {
+    pub kind: u8,
+    #[Rest]
+    pub data: &'a[u8],
+}
+
+/// An enum with trailing data in variants
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+enum EnumWithRest {
+    #[Selector("0")] Empty(u8),
+    #[Selector("1")] Data(u8, #[Rest] Vec<u8>),
+}
+
 #[test]
 fn test_struct_with_lifetime() {
     let input = b"\x00\x00\x00\x01";
@@ -58,22 +112,196 @@ fn test_struct_with_phantomdata() {
               );
 }
 
-// XXX generics are not supported
-
-// fn parse_generics<G>(i:&[u8]) -> IResult<&[u8],Option<G>> {
-//     Ok((i,None))
-// }
-//
-// use std::fmt::Debug;
-//
-// /// A structure with lifetimes and generics
-// #[derive(Debug,PartialEq,Nom)]
-// struct StructWithGenerics<'a,'b,G>
-//         where G: Debug + PartialEq {
-//     #[Parse="take!(4)"]
-//     s: &'a[u8],
-//     #[Parse="take!(4)"]
-//     t: &'b[u8],
-//     #[Parse="call!(parse_generics)"]
-//     g: Option<G>,
-// }
+#[test]
+fn test_struct_with_slice_verify() {
+    let input = b"\x02\x00\x00\x00\x00\x12\x34";
+    let res = StructWithSliceVerify::parse(input);
+    assert_eq!(res, Ok((&input[7..],StructWithSliceVerify{hdr_len:2, reserved:&input[1..5], data:&input[5..7]})));
+
+    let input = b"\x02\x00\x01\x00\x00\x12\x34";
+    let res = StructWithSliceVerify::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_with_take_while() {
+    let input = b"name=value 1; \t.";
+    let res = StructWithTakeWhile::parse(input);
+    assert_eq!(res, Ok((&input[15..],StructWithTakeWhile{
+        key: b"name",
+        eq: b'=',
+        value: b"value 1".to_vec(),
+        sep: b';',
+        spaces: Some(b" \t"),
+    })));
+    // all the remaining bytes match
+    let res = StructWithTakeWhile::parse(&input[..3]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+}
+
+#[test]
+fn test_struct_with_take_until() {
+    let input = b"Host: a:b\r\n\r\n";
+    let res = StructWithTakeUntil::parse(input);
+    // the delimiter is not consumed
+    assert_eq!(res, Ok((&input[9..],StructWithTakeUntil{
+        name: b"Host",
+        sep: (),
+        value: b"a:b".to_vec(),
+        end: b"",
+    })));
+    // the delimiter is not found
+    let res = StructWithTakeUntil::parse(&input[..10]);
+    assert!(matches!(res, Err(Err::Incomplete(_))));
+}
+
+#[test]
+fn test_struct_with_rest() {
+    let input = b"\x01\x02\x03";
+    let res = StructWithRest::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithRest{kind:1, data:&input[1..]})));
+    let res = StructWithRest::parse(&input[..1]);
+    assert_eq!(res, Ok((&input[1..1],StructWithRest{kind:1, data:b""})));
+    let res = EnumWithRest::parse(input, 1);
+    assert_eq!(res, Ok((&input[3..],EnumWithRest::Data(1, vec![2, 3]))));
+    let res = EnumWithRest::parse(input, 0);
+    assert_eq!(res, Ok((&input[1..],EnumWithRest::Empty(1))));
+}
+
+fn parse_generics<G>(i:&[u8]) -> IResult<&[u8],Option<G>> {
+    Ok((i,None))
+}
+
+use std::fmt::Debug;
+
+/// A structure with lifetimes and generics
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithGenerics<'a,'b,G>
+        where G: Debug + PartialEq {
+    #[Parse="take!(4)"]
+    s: &'a[u8],
+    #[Parse="take!(4)"]
+    t: &'b[u8],
+    #[ParseOuter="call!(parse_generics)"]
+    g: Option<G>,
+}
+
+#[test]
+fn test_struct_with_generics() {
+    let input = b"abcdefgh";
+    let res = StructWithGenerics::<u8>::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithGenerics{s:b"abcd", t:b"efgh", g:None})));
+}
+
+/// The parsers of the types used as type parameters
+trait SubParse: Sized {
+    fn parse(i: &[u8]) -> IResult<&[u8],Self>;
+}
+
+/// A point, which can be used as a type parameter
+#[derive(Debug,PartialEq,Nom)]
+struct GenericPoint {
+    x: u8,
+    y: u8,
+}
+
+impl SubParse for GenericPoint {
+    fn parse(i: &[u8]) -> IResult<&[u8],Self> {
+        GenericPoint::parse(i)
+    }
+}
+
+/// A pair of values, with a bound on the type parameter
+#[derive(Debug,PartialEq,Nom)]
+struct GenericPair<T: SubParse>(T, T);
+
+/// A list of values, with a where clause and a nested generic type
+#[derive(Debug,PartialEq,Nom)]
+struct GenericList<'a, T>
+        where T: SubParse + Debug {
+    n: u8,
+    #[Count="n"]
+    items: Vec<T>,
+    pair: GenericPair<T>,
+    #[Parse="take!(1)"]
+    tail: &'a [u8],
+}
+
+#[test]
+fn test_struct_with_bounded_generics() {
+    let input = b"\x01\x02\x03\x04";
+    let res = GenericPair::<GenericPoint>::parse(input);
+    assert_eq!(res, Ok((&input[4..],GenericPair(GenericPoint{x:1, y:2}, GenericPoint{x:3, y:4}))));
+    let input = b"\x02\x01\x02\x03\x04\x05\x06\x07\x08\x09\xff";
+    let res = GenericList::<GenericPoint>::parse(input);
+    assert_eq!(res, Ok((&input[10..],GenericList{
+        n: 2,
+        items: vec![GenericPoint{x:1, y:2}, GenericPoint{x:3, y:4}],
+        pair: GenericPair(GenericPoint{x:5, y:6}, GenericPoint{x:7, y:8}),
+        tail: b"\x09",
+    })));
+}
+
+/// A tuple structure with a borrowed slice
+#[derive(Debug,PartialEq,Nom)]
+struct TupleWithLifetime<'a>(#[Parse="take!(4)"] &'a [u8]);
+
+/// A structure with a borrowed slice and a type parameter, with additional parse functions
+#[derive(Debug,PartialEq,Nom)]
+#[NomBothEndian]
+#[ParseErased]
+struct BorrowedWithGenerics<'a, T: SubParse> {
+    len: u16,
+    #[Parse="take!(len)"]
+    data: &'a [u8],
+    value: T,
+}
+
+/// The signature of `parse_erased`
+type ParseBorrowed = fn(&[u8]) -> IResult<&[u8],BorrowedWithGenerics<GenericPoint>>;
+
+/// The result borrows the input, not the structure
+fn first_bytes(i: &[u8]) -> &[u8] {
+    match TupleWithLifetime::parse(i) {
+        Ok((_, TupleWithLifetime(b))) => b,
+        Err(_) => &[],
+    }
+}
+
+#[test]
+fn test_lifetime_in_return_type() {
+    let input = b"abcde".to_vec();
+    let res = TupleWithLifetime::parse(&input);
+    assert_eq!(res, Ok((&input[4..],TupleWithLifetime(b"abcd"))));
+    assert_eq!(first_bytes(&input), b"abcd");
+
+    let input = b"\x02\x00ab\x01\x02";
+    let expected = BorrowedWithGenerics{len:2, data:b"ab", value:GenericPoint{x:1, y:2}};
+    let res = BorrowedWithGenerics::<GenericPoint>::parse_le(input);
+    assert_eq!(res, Ok((&input[6..],expected)));
+    let parse_erased : ParseBorrowed = BorrowedWithGenerics::parse_erased;
+    assert!(parse_erased(input).is_err());
+    let input = b"\x00\x02ab\x01\x02";
+    let expected = BorrowedWithGenerics{len:2, data:b"ab", value:GenericPoint{x:1, y:2}};
+    assert_eq!(parse_erased(input), Ok((&input[6..],expected)));
+}
+
+/// A generic structure with a marker of the type of the payload
+#[derive(Debug,PartialEq,Nom)]
+struct Typed<T> {
+    pub len: u32,
+    _marker: PhantomData<T>,
+}
+
+/// A generic tuple structure, with the full path of the marker type
+#[derive(Debug,PartialEq,Nom)]
+struct TypedTuple<T>(u16, std::marker::PhantomData<T>);
+
+#[test]
+fn test_generic_struct_with_phantomdata() {
+    let input = b"\x00\x00\x00\x10\xff";
+    let res = Typed::<String>::parse(input);
+    assert_eq!(res, Ok((&input[4..],Typed{len:16, _marker:PhantomData})));
+    let res = TypedTuple::<Vec<u8>>::parse(input);
+    assert_eq!(res, Ok((&input[2..],TypedTuple(0, PhantomData))));
+}
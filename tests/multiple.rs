@@ -30,6 +30,76 @@ struct S2 {
 }
 
 
+/// A structure with a verification on a Vec
+#[derive(Debug,PartialEq,Nom)]
+struct S3 {
+    pub a: u8,
+    #[Count="a"]
+    #[Verify="b.iter().all(|x| *x < 0x80)"]
+    pub b: Vec<u8>,
+}
+
+/// A structure with a bounding length, and padding
+#[derive(Debug,PartialEq,Nom)]
+struct S4 {
+    pub kind: u8,
+    #[BoundingLength]
+    pub len: u16,
+    pub a: u16,
+    pub b: Vec<u16>,
+}
+
+/// A structure with a list parsed until an offset, in a bounded region
+#[derive(Debug,PartialEq,Nom)]
+struct BoundedUntilOffset {
+    #[BoundingLength]
+    pub len: u8,
+    pub end: u8,
+    #[UntilOffset="end"]
+    pub items: Vec<u8>,
+    pub tail: u8,
+}
+
+/// A structure with folded values
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithFold {
+    pub n: u16,
+    #[Parse="be_u16"]
+    #[Count="n"]
+    #[Fold="0u32, |acc, item| acc + u32::from(item)"]
+    pub sum: u32,
+    #[Parse="be_u8"]
+    #[Fold="(0, 0), |(count, total), item| (count + 1, total + item as usize)"]
+    pub stats: (usize, usize),
+}
+
+/// An IPv4 option
+#[derive(Debug,PartialEq,Nom)]
+struct IPv4Option {
+    pub kind: u8,
+    pub len: u8,
+    #[Count="len"]
+    pub data: Vec<u8>,
+}
+
+/// An IPv4-style header, with options until the end of the header
+#[derive(Debug,PartialEq,Nom)]
+struct IPv4Header {
+    pub version_ihl: u8,
+    pub tos: u8,
+    pub total_len: u16,
+    pub id: u16,
+    pub frag: u16,
+    pub ttl: u8,
+    pub proto: u8,
+    pub checksum: u16,
+    pub src: u32,
+    pub dst: u32,
+    #[UntilOffset="(version_ihl & 0xf) as usize * 4"]
+    pub options: Vec<IPv4Option>,
+    pub payload: u8,
+}
+
 
 #[test]
 fn test_struct_count() {
@@ -44,3 +114,74 @@ fn test_struct_count_newtype() {
     let res = S2::parse(input);
     assert_eq!(res, Ok((&input[3..],S2{a:2, b:vec![NewType(0x12),NewType(0x34)]})));
 }
+
+#[test]
+fn test_struct_count_verify() {
+    let input = b"\x02\x12\x34";
+    let res = S3::parse(input);
+    assert_eq!(res, Ok((&input[3..],S3{a:2, b:vec![0x12,0x34]})));
+
+    let input = b"\x02\x12\x84";
+    let res = S3::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_bounding_length() {
+    let input = b"\x01\x00\x07\x00\x01\x00\x02\x00\x03\x00\xff";
+    let res = S4::parse(input);
+    assert_eq!(res, Ok((&input[10..],S4{kind:1, len:7, a:1, b:vec![2,3]})));
+
+    let input = b"\x01\x00\x01\x00\x01";
+    let res = S4::parse(input);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_struct_until_offset() {
+    let input = b"\x46\x00\x00\x19\x00\x01\x00\x00\x40\x06\x00\x00\x0a\x00\x00\x01\x0a\x00\x00\x02\
+                  \x01\x02\xab\xcd\xff";
+    let res = IPv4Header::parse(input);
+    assert_eq!(res, Ok((&input[25..],IPv4Header{
+        version_ihl: 0x46, tos: 0, total_len: 25, id: 1, frag: 0, ttl: 64, proto: 6, checksum: 0,
+        src: 0x0a00_0001, dst: 0x0a00_0002,
+        options: vec![IPv4Option{kind:1, len:2, data:vec![0xab,0xcd]}],
+        payload: 0xff,
+    })));
+
+    // no options
+    let input = b"\x45\x00\x00\x15\x00\x01\x00\x00\x40\x06\x00\x00\x0a\x00\x00\x01\x0a\x00\x00\x02\xff";
+    let res = IPv4Header::parse(input);
+    assert_eq!(res.map(|(rem,hdr)| (rem.len(),hdr.options,hdr.payload)), Ok((0,vec![],0xff)));
+
+    // IHL pointing backwards
+    let input = b"\x44\x00\x00\x15\x00\x01\x00\x00\x40\x06\x00\x00\x0a\x00\x00\x01\x0a\x00\x00\x02\xff";
+    assert!(IPv4Header::parse(input).is_err());
+
+    // IHL pointing past the end of the input
+    let input = b"\x4f\x00\x00\x15\x00\x01\x00\x00\x40\x06\x00\x00\x0a\x00\x00\x01\x0a\x00\x00\x02\xff";
+    assert!(IPv4Header::parse(input).is_err());
+}
+
+#[test]
+fn test_struct_fold() {
+    let input = b"\x00\x03\xff\xff\x00\x01\x00\x02\x01\x02\x03";
+    let res = StructWithFold::parse(input);
+    assert_eq!(res, Ok((&input[11..],StructWithFold{n:3, sum:0x10002, stats:(3, 6)})));
+
+    // not enough items
+    let input = b"\x00\x03\xff\xff\x00\x01";
+    assert!(StructWithFold::parse(input).is_err());
+}
+
+#[test]
+fn test_struct_bounded_until_offset() {
+    // the offset is relative to the start of the input, not of the bounded region
+    let input = b"\x04\x04\x01\x02\x09\xff";
+    let res = BoundedUntilOffset::parse(input);
+    assert_eq!(res, Ok((&input[5..],BoundedUntilOffset{len:4, end:4, items:vec![1, 2], tail:9})));
+    // offset after the end of the bounded region
+    let input = b"\x02\x05\x01\x02\x09";
+    let res = BoundedUntilOffset::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[2..3], ErrorKind::Eof))));
+}
@@ -0,0 +1,86 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate nom_derive;
+
+extern crate nom;
+
+use nom::*;
+
+/// A structure which can be parsed in several steps
+#[derive(Debug,PartialEq,Nom)]
+#[NomResumable]
+struct StructResumable {
+    pub a: u16,
+    pub len: u8,
+    #[Count="len"]
+    pub data: Vec<u8>,
+    #[Cond="a == 1"]
+    pub b: Option<u32>,
+}
+
+/// A tuple struct which can be parsed in several steps
+#[derive(Debug,PartialEq,Nom)]
+#[NomResumable]
+#[LittleEndian]
+struct TupleResumable(u16, #[Default="7"] u8, u32);
+
+/// A structure with a field named like the input of the parse function
+#[derive(Debug,PartialEq,Nom)]
+#[NomResumable]
+struct ResumableNamedI {
+    pub i: u8,
+    #[UntilOffset="3"]
+    pub items: Vec<u8>,
+}
+
+#[test]
+fn test_resumable_two_chunks() {
+    let input = b"\x00\x01\x02\xaa\xbb\x00\x00\x00\x03";
+    let mut state = StructResumableResumeState::default();
+    // first chunk: a and len are complete, data is not
+    let res = StructResumable::parse_resumable(&input[..4], &mut state);
+    match res {
+        Err(Err::Incomplete(_)) => (),
+        _ => panic!("expected incomplete input, got {:?}", res),
+    }
+    assert_eq!(state.offset, 3);
+    assert_eq!(state.a, Some(1));
+    assert_eq!(state.len, Some(2));
+    assert!(state.data.is_none());
+    // second chunk: parsing resumes at data
+    let res = StructResumable::parse_resumable(input, &mut state);
+    assert_eq!(res, Ok((&input[9..],StructResumable{a:1, len:2, data:vec![0xaa,0xbb], b:Some(3)})));
+}
+
+#[test]
+fn test_resumable_single_chunk() {
+    let input = b"\x00\x02\x01\xaa\xff";
+    let mut state = StructResumableResumeState::default();
+    let res = StructResumable::parse_resumable(input, &mut state);
+    assert_eq!(res, Ok((&input[4..],StructResumable{a:2, len:1, data:vec![0xaa], b:None})));
+    assert_eq!(StructResumable::parse(input), res);
+}
+
+#[test]
+fn test_resumable_tuple_struct() {
+    let input = b"\x01\x00\x02\x00\x00\x00";
+    let mut state = TupleResumableResumeState::default();
+    assert!(TupleResumable::parse_resumable(&input[..1], &mut state).is_err());
+    assert_eq!(state.offset, 0);
+    assert!(TupleResumable::parse_resumable(&input[..3], &mut state).is_err());
+    assert_eq!(state.offset, 2);
+    let res = TupleResumable::parse_resumable(input, &mut state);
+    assert_eq!(res, Ok((&input[6..],TupleResumable(1, 7, 2))));
+}
+
+#[test]
+fn test_resumable_field_named_i() {
+    let input = b"\x01\x02\x03\x04";
+    let mut state = ResumableNamedIResumeState::default();
+    assert!(ResumableNamedI::parse_resumable(&input[..2], &mut state).is_err());
+    let res = ResumableNamedI::parse_resumable(input, &mut state);
+    assert_eq!(res, Ok((&input[3..],ResumableNamedI{i:1, items:vec![2, 3]})));
+}
@@ -8,6 +8,9 @@ extern crate nom_derive;
 extern crate nom;
 
 use nom::*;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::num::{NonZeroI16, NonZeroU8, NonZeroU16, NonZeroU32};
 
 /// A simple structure, deriving a trivial parser
 #[derive(Debug,PartialEq,Nom)]
@@ -26,7 +29,7 @@ struct StructWithParser {
 /// A simple structure, giving the parser explicitly
 #[derive(Debug,PartialEq,Nom)]
 struct StructWithParser2 {
-    #[Parse="opt!(le_u32)"]
+    #[ParseOuter="opt!(le_u32)"]
     pub a: Option<u32>,
 }
 
@@ -45,6 +48,22 @@ struct StructWithVerify {
     pub a: u32,
 }
 
+const BAD_VERSION: ErrorKind = ErrorKind::Custom(1);
+
+/// A simple structure with verifications returning custom errors
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithVerifyError {
+    #[Verify="version == 1"]
+    #[VerifyError="BAD_VERSION"]
+    pub version: u8,
+    #[Verify="len <= 8"]
+    #[VerifyError="ErrorKind::TooLarge"]
+    pub len: u8,
+    #[Count="len"]
+    #[Verify="data.iter().sum::<u8>() == version"]
+    pub data: Vec<u8>,
+}
+
 /// A simple structure with a condition
 #[derive(Debug,PartialEq,Nom)]
 struct StructWithCondition {
@@ -53,6 +72,496 @@ struct StructWithCondition {
     pub b: Option<u32>,
 }
 
+/// A structure with a condition and a verification on the same field
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithCondVerify {
+    pub flags: u8,
+    #[Verify="score <= 100"]
+    #[Cond="flags & 1 != 0"]
+    pub score: Option<u8>,
+    #[Cond="flags & 2 != 0"]
+    #[Verify="level != 0"]
+    #[VerifyError="ErrorKind::Custom(2)"]
+    pub level: Option<u8>,
+}
+
+/// A structure with nom attributes before and after other attributes
+#[derive(Debug,PartialEq,Nom)]
+#[doc(alias = "mixed")]
+#[LittleEndian]
+#[allow(clippy::all)]
+struct StructWithForeignAttributes {
+    /// Documentation before the nom attributes
+    #[cfg(all())]
+    #[Verify="a != 0"]
+    #[allow(clippy::all)]
+    pub a: u16,
+    #[Cond="a == 1"]
+    #[cfg(not(any()))]
+    /// Documentation between the nom attributes
+    #[BigEndian]
+    pub b: Option<u16>,
+    #[rustfmt::skip]
+    #[Count="a"]
+    #[doc(hidden)]
+    pub c: Vec<u8>,
+    #[cfg(any())]
+    #[Count="1"]
+    pub unused: Vec<u8>,
+    #[cfg_attr(all(), Skip="1")]
+    #[cfg_attr(any(), Skip="2")]
+    #[deprecated]
+    pub d: u8,
+    #[doc = "Not parsed"]
+    #[Default="7"]
+    #[doc(alias = "extra")]
+    pub e: u8,
+}
+
+/// The same structure, without the other attributes
+#[derive(Debug,PartialEq,Nom)]
+#[LittleEndian]
+struct StructWithoutForeignAttributes {
+    #[Verify="a != 0"]
+    pub a: u16,
+    #[Cond="a == 1"]
+    #[BigEndian]
+    pub b: Option<u16>,
+    #[Count="a"]
+    pub c: Vec<u8>,
+    #[Skip="1"]
+    pub d: u8,
+    #[Default="7"]
+    pub e: u8,
+}
+
+/// A simple structure with conversions
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithConversion {
+    #[Parse="be_u8"]
+    #[Into]
+    pub a: u32,
+    #[Parse="be_u16"]
+    #[TryInto]
+    pub b: u8,
+}
+
+/// A simple structure with transformed values
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithMap {
+    #[Map="|v| v & 0x0fff"]
+    pub a: u16,
+    #[Parse="be_u32"]
+    #[Map="|v| v as usize"]
+    #[Verify="b < 16"]
+    pub b: usize,
+    #[Cond="a == 1"]
+    #[Map="|v: u16| v * 2"]
+    pub c: Option<u16>,
+}
+
+/// A domain type, built from a byte
+#[derive(Debug,PartialEq)]
+enum Color {
+    Red,
+    Green,
+    Other(u8),
+}
+
+impl From<u8> for Color {
+    fn from(v: u8) -> Color {
+        match v {
+            0 => Color::Red,
+            1 => Color::Green,
+            v => Color::Other(v),
+        }
+    }
+}
+
+/// A simple structure with values mapped to other types
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithMapType {
+    #[Parse="be_u8"]
+    #[Map="Color::from"]
+    pub color: Color,
+    pub n: u8,
+    #[Count="n"]
+    #[Map="|mut v: Vec<u16>| { v.sort(); v }"]
+    #[Verify="!sorted.is_empty()"]
+    pub sorted: Vec<u16>,
+}
+
+/// A simple structure with skipped bytes
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithSkip {
+    pub a: u8,
+    #[Skip="3"]
+    pub b: u8,
+    #[Skip="a * 2"]
+    pub c: u8,
+}
+
+/// A simple structure with reserved bytes, skipped using literals
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithSkipLiteral {
+    pub a: u8,
+    #[Skip(3)]
+    pub b: u8,
+    #[Skip=1]
+    pub c: u8,
+    #[Skip("a + 1")]
+    pub d: u8,
+}
+
+/// A simple structure with an ignored field
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithIgnore {
+    pub a: u8,
+    #[Ignore]
+    pub cached_len: usize,
+    #[Skip="1"]
+    pub b: u8,
+}
+
+/// A simple structure with default values
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithDefault {
+    pub a: u32,
+    #[Default]
+    pub extra: Vec<u8>,
+    #[Default="a as usize * 2"]
+    pub double: usize,
+}
+
+/// A tuple struct with a default value
+#[derive(Debug,PartialEq,Nom)]
+struct TupleWithDefault(u8, #[Default="String::from(\"none\")"] String, u8);
+
+/// A simple structure with ASCII decimal numbers
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithAsciiInt {
+    #[AsciiInt="4"]
+    pub a: u32,
+    pub len: u8,
+    #[AsciiInt="len"]
+    pub b: u8,
+}
+
+/// A simple structure with length-prefixed strings
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithString {
+    pub name: String,
+    #[LengthType="u8"]
+    pub short_name: String,
+    #[LengthType="u16"]
+    #[LittleEndian]
+    pub le_name: String,
+}
+
+/// A simple structure with a renamed parse function
+#[derive(Debug,PartialEq,Nom)]
+#[NomParseName="parse_v4"]
+struct StructWithParseName {
+    pub a: [u8; 4],
+}
+
+impl StructWithParseName {
+    // would conflict with the generated function
+    fn parse(s: &str) -> Option<StructWithParseName> {
+        let v : Vec<u8> = s.split('.').filter_map(|b| b.parse().ok()).collect();
+        Some(StructWithParseName{ a: [*v.first()?, *v.get(1)?, *v.get(2)?, *v.get(3)?] })
+    }
+}
+
+/// A structure using a type with a renamed parse function
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithRenamedField {
+    #[Parse="call!(StructWithParseName::parse_v4)"]
+    pub addr: StructWithParseName,
+}
+
+/// A simple structure with strings of a given length
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithStringCount {
+    pub len: u8,
+    #[Count="len"]
+    pub name: String,
+    #[Count="2"]
+    pub code: String,
+}
+
+/// A simple structure with NUL-terminated strings
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithCString {
+    pub a: CString,
+    pub b: CString,
+    pub c: u8,
+}
+
+/// A simple structure with constant bytes
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithTag<'a> {
+    #[Tag=b"\xCA\xFE\xBA\xBE"]
+    pub magic: [u8; 4],
+    #[Tag="PK"]
+    pub signature: &'a [u8],
+    #[Tag="b\"\\x00\\x01\""]
+    pub version: (),
+    #[Tag="\x7fELF"]
+    pub elf: PhantomData<u8>,
+    pub a: u8,
+}
+
+/// A simple structure starting with constant bytes
+#[derive(Debug,PartialEq,Nom)]
+#[Tag="\x7fELF"]
+struct StructWithStructTag {
+    pub class: u8,
+    #[Tag=b"\x01"]
+    pub data: [u8; 1],
+}
+
+/// A simple structure with a limit on the input length
+#[derive(Debug,PartialEq,Nom)]
+#[NomMaxLen="0xffff"]
+struct StructWithMaxLen {
+    pub len: u16,
+    #[Count="len"]
+    pub data: Vec<u8>,
+}
+
+/// A simple structure starting with a magic number
+#[derive(Debug,PartialEq,Nom)]
+#[Magic(b"\x89PNG\r\n\x1a\n")]
+struct StructWithMagic {
+    pub chunk_len: u32,
+}
+
+/// A structure with boolean fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithBool {
+    pub a: bool,
+    #[Strict]
+    pub b: bool,
+    pub c: Option<bool>,
+    #[Count="2"]
+    #[Strict]
+    pub d: Vec<bool>,
+}
+
+/// A structure with run-length encoded data
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithRunLength {
+    #[RunLength]
+    pub pixels: Vec<u8>,
+    pub len: u16,
+    #[RunLength="len"]
+    pub mask: Vec<u8>,
+}
+
+/// An error type for the parsers of the application
+#[derive(Debug,PartialEq)]
+enum AppError {
+    Magic,
+    Nom(u32),
+}
+
+impl From<u32> for AppError {
+    fn from(n: u32) -> AppError {
+        match n {
+            1 => AppError::Magic,
+            n => AppError::Nom(n),
+        }
+    }
+}
+
+/// A structure parsed with a custom error type
+#[derive(Debug,PartialEq,Nom)]
+#[NomErr="AppError"]
+struct StructWithNomErr {
+    #[Verify="magic == 0xcafe"]
+    #[VerifyError="ErrorKind::Custom(1)"]
+    pub magic: u16,
+    pub a: u8,
+}
+
+/// A structure with parsers given for Option fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithOptionParsers {
+    #[Parse="le_u16"]
+    pub a: Option<u16>,
+    pub n: u8,
+    #[Count="n"]
+    pub b: Option<Vec<u16>>,
+    #[ParseOuter="cond!(n > 1, be_u8)"]
+    pub c: Option<u8>,
+}
+
+/// A structure with boolean fields stored in larger integers
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithBoolWidth {
+    #[BoolWidth="u32"]
+    pub a: bool,
+    #[BoolWidth="u16"]
+    #[LittleEndian]
+    #[Strict]
+    pub b: Vec<bool>,
+}
+
+/// A structure with character fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithChar {
+    pub a: char,
+    #[Utf8]
+    pub b: char,
+    #[LittleEndian]
+    pub c: Option<char>,
+}
+
+/// A structure with fields stored as nibbles
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithNibbles {
+    #[Nibbles]
+    pub high: u8,
+    #[Nibbles]
+    #[Verify="low != 0"]
+    pub low: u8,
+    #[Nibbles]
+    pub a: u8,
+    #[Nibbles]
+    pub b: u8,
+}
+
+/// A structure with fields of platform-dependent sizes
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithWidth {
+    #[Width=4]
+    pub a: usize,
+    #[Width="8"]
+    #[LittleEndian]
+    pub b: isize,
+    #[Width=8]
+    pub c: Option<usize>,
+}
+
+/// A structure with fields added and removed in versions of the format
+#[derive(Debug,PartialEq,Nom)]
+#[NomVersionField="version"]
+struct StructWithVersions {
+    pub version: u8,
+    pub a: u8,
+    #[Since(3)]
+    pub b: Option<u16>,
+    #[Until(5)]
+    pub c: u8,
+    #[Since(3)]
+    #[Until(5)]
+    pub d: Option<u8>,
+    #[Since(3)]
+    #[Cond="a != 0"]
+    pub e: Option<u8>,
+}
+
+/// A structure with non-zero integers
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithNonZero {
+    pub id: NonZeroU32,
+    pub delta: NonZeroI16,
+    #[LittleEndian]
+    pub port: Option<NonZeroU16>,
+    #[Count="2"]
+    pub flags: Vec<NonZeroU8>,
+}
+
+/// A structure with sorted lists
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithMonotonic {
+    pub n: u8,
+    #[Count="n"]
+    #[Monotonic]
+    pub offsets: Vec<u32>,
+    #[Monotonic]
+    #[VerifyError="ErrorKind::Custom(3)"]
+    pub keys: [i8; 3],
+}
+
+/// A structure with aligned fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithAlign {
+    pub a: u8,
+    #[Align=4]
+    pub b: u16,
+    #[Align(2)]
+    pub c: u16,
+    pub d: u8,
+    #[Align("a")]
+    pub e: u8,
+}
+
+/// A structure with aligned fields in a bounded region
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithBoundedAlign {
+    #[BoundingLength]
+    pub len: u8,
+    pub a: u8,
+    #[Align=4]
+    pub b: u8,
+}
+
+/// A structure with a field named like the input of the parse function, and parsers using
+/// offsets relative to the input
+#[derive(Debug,PartialEq,Nom)]
+#[NomParseTraced]
+struct StructWithFieldNamedI {
+    pub i: u8,
+    #[Align=4]
+    pub b: u8,
+    #[UntilOffset="8"]
+    pub items: Vec<u8>,
+    #[SelfExcludingChecksum="sum8"]
+    pub sum: u8,
+}
+
+/// A structure running statements around the parsers of the fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithExec<'a> {
+    #[PreExec="let start = i_;"]
+    pub len: u8,
+    #[Parse="take!(len)"]
+    #[PostExec="let header_len = start.len() - i_.len();"]
+    pub name: &'a [u8],
+    #[Cond="header_len > 2"]
+    pub extra: Option<u8>,
+    #[Default="header_len"]
+    pub header_len: usize,
+    #[PostExec="count = count.wrapping_add(1);"]
+    pub count: u8,
+}
+
+/// A simple structure with floating-point fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithFloat {
+    pub a: f32,
+    pub b: f64,
+}
+
+/// A simple structure with floating-point values in containers
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithFloatContainers {
+    pub n: u8,
+    #[Count="n"]
+    pub a: Vec<f32>,
+    #[LittleEndian]
+    pub b: Option<f64>,
+}
+
+/// A simple structure with 128-bit integers
+#[derive(Debug,PartialEq,Nom)]
+struct StructWith128 {
+    pub a: u128,
+    pub b: i128,
+}
+
 /// A tuple struct with one field (newtype)
 #[derive(Debug,PartialEq,Nom)]
 struct NewType(pub u32);
@@ -75,6 +584,13 @@ fn test_struct_parse() {
     assert_eq!(res, Ok((&input[4..],StructWithParser{a:0x01000000})));
 }
 
+#[test]
+fn test_struct_parse_opt() {
+    let input = b"\x00\x00\x00\x01";
+    let res = StructWithParser2::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithParser2{a:Some(0x01000000)})));
+}
+
 #[test]
 fn test_struct_parse_substruct() {
     let input = b"\x00\x00\x00\x01\x12\x34\x56\x78\x12\x34\x56\x78\x00\x00\x00\x01";
@@ -92,6 +608,26 @@ fn test_struct_with_verify() {
     assert_eq!(res, Err(Err::Error(error_position!(&input[4..], ErrorKind::Verify))));
 }
 
+#[test]
+fn test_struct_with_verify_error() {
+    let input = b"\x01\x02\x00\x01";
+    let res = StructWithVerifyError::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithVerifyError{version:1, len:2, data:vec![0,1]})));
+
+    let input = b"\x02\x02\x00\x01";
+    let res = StructWithVerifyError::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], BAD_VERSION))));
+
+    let input = b"\x01\x09\x00\x01";
+    let res = StructWithVerifyError::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::TooLarge))));
+
+    // default error kind
+    let input = b"\x01\x02\x01\x01";
+    let res = StructWithVerifyError::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[2..], ErrorKind::Verify))));
+}
+
 #[test]
 fn test_struct_with_condition() {
     let input = b"\x00\x00\x00\x01\x12\x34\x56\x78\x12\x34\x56\x78\x00\x00\x00\x01";
@@ -102,6 +638,38 @@ fn test_struct_with_condition() {
     assert_eq!(res, Ok((&input[8..],StructWithCondition{a:0x12345678,b:None})));
 }
 
+#[test]
+fn test_struct_with_cond_verify() {
+    // absent
+    let input = b"\x00\xff";
+    let res = StructWithCondVerify::parse(input);
+    assert_eq!(res, Ok((&input[1..],StructWithCondVerify{flags:0, score:None, level:None})));
+    // present and valid
+    let input = b"\x03\x64\x01";
+    let res = StructWithCondVerify::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithCondVerify{flags:3, score:Some(100), level:Some(1)})));
+    // present and invalid
+    let input = b"\x01\x65";
+    let res = StructWithCondVerify::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+    let input = b"\x02\x00";
+    let res = StructWithCondVerify::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Custom(2)))));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_struct_with_foreign_attributes() {
+    let input = b"\x01\x00\x00\x02\x03\xff\x04";
+    let res = StructWithForeignAttributes::parse(input);
+    assert_eq!(res, Ok((&input[7..],StructWithForeignAttributes{a:1, b:Some(2), c:vec![3], d:4, e:7})));
+    let res = StructWithoutForeignAttributes::parse(input);
+    assert_eq!(res, Ok((&input[7..],StructWithoutForeignAttributes{a:1, b:Some(2), c:vec![3], d:4, e:7})));
+    let input = b"\x00\x00";
+    let res = StructWithForeignAttributes::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::Verify))));
+}
+
 #[test]
 fn test_tuple_struct_1() {
     let input = b"\x00\x00\x00\x01";
@@ -115,3 +683,444 @@ fn test_tuple_struct_2() {
     let res = NewType2::parse(input);
     assert_eq!(res, Ok((&input[6..],NewType2(1,0xffff))));
 }
+
+#[test]
+fn test_struct_with_conversion() {
+    let input = b"\x01\x00\xff";
+    let res = StructWithConversion::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithConversion{a:1,b:0xff})));
+
+    let input = b"\x01\x01\x00";
+    let res = StructWithConversion::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::MapRes))));
+}
+
+#[test]
+fn test_struct_with_map() {
+    let input = b"\xf0\x01\x00\x00\x00\x02\x00\x03";
+    let res = StructWithMap::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithMap{a:1,b:2,c:Some(6)})));
+
+    let input = b"\xf0\x02\x00\x00\x00\x02\x03";
+    let res = StructWithMap::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructWithMap{a:2,b:2,c:None})));
+
+    let input = b"\xf0\x02\x00\x00\x00\x10";
+    let res = StructWithMap::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[2..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_with_map_type() {
+    let input = b"\x01\x02\x00\x02\x00\x01";
+    let res = StructWithMapType::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructWithMapType{color:Color::Green, n:2, sorted:vec![1,2]})));
+
+    let input = b"\x07\x00";
+    let res = StructWithMapType::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[2..], ErrorKind::Verify))));
+    // other values of the mapped field
+    let input = b"\x00\x01\x00\x05";
+    let res = StructWithMapType::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithMapType{color:Color::Red, n:1, sorted:vec![5]})));
+    let input = b"\x09\x01\x00\x05";
+    let res = StructWithMapType::parse(input);
+    assert_eq!(res.map(|(_, s)| s.color), Ok(Color::Other(9)));
+}
+
+#[test]
+fn test_struct_with_skip_literal() {
+    let input = b"\x01\xff\xff\xff\x02\xff\x03\xff\xff\x04";
+    let res = StructWithSkipLiteral::parse(input);
+    assert_eq!(res, Ok((&input[10..],StructWithSkipLiteral{a:1,b:2,c:3,d:4})));
+}
+
+#[test]
+fn test_struct_with_skip() {
+    let input = b"\x01\xff\xff\xff\x02\xff\xff\x03";
+    let res = StructWithSkip::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithSkip{a:1,b:2,c:3})));
+}
+
+#[test]
+fn test_struct_with_ignore() {
+    let input = b"\x01\xff\x02";
+    let res = StructWithIgnore::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithIgnore{a:1,cached_len:0,b:2})));
+}
+
+#[test]
+fn test_struct_with_float() {
+    let input = b"\x3f\xc0\x00\x00\xc0\x02\x00\x00\x00\x00\x00\x00";
+    let res = StructWithFloat::parse(input);
+    assert_eq!(res, Ok((&input[12..],StructWithFloat{a:1.5, b:-2.25})));
+}
+
+#[test]
+fn test_struct_with_float_containers() {
+    let input = b"\x02\x3f\xc0\x00\x00\xc0\x10\x00\x00\x00\x00\x00\x00\x00\x00\x02\xc0";
+    let res = StructWithFloatContainers::parse(input);
+    assert_eq!(res, Ok((&input[17..],StructWithFloatContainers{n:2, a:vec![1.5, -2.25], b:Some(-2.25)})));
+    let input = b"\x00";
+    let res = StructWithFloatContainers::parse(input);
+    assert_eq!(res, Ok((&input[1..],StructWithFloatContainers{n:0, a:vec![], b:None})));
+}
+
+#[test]
+fn test_struct_with_128() {
+    let input = b"\x00\x11\x22\x33\x44\x55\x66\x77\x88\x99\xaa\xbb\xcc\xdd\xee\xff\
+                  \xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xfe";
+    let res = StructWith128::parse(input);
+    assert_eq!(res, Ok((&input[32..],StructWith128{a:0x0011_2233_4455_6677_8899_aabb_ccdd_eeff, b:-2})));
+}
+
+#[test]
+fn test_struct_with_default() {
+    let input = b"\x00\x00\x00\x02\xff";
+    let res = StructWithDefault::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithDefault{a:2, extra:vec![], double:4})));
+
+    let res = TupleWithDefault::parse(&input[3..]);
+    assert_eq!(res, Ok((&input[5..],TupleWithDefault(2, "none".to_owned(), 0xff))));
+}
+
+#[test]
+fn test_struct_with_ascii_int() {
+    let input = b"0042\x03255";
+    let res = StructWithAsciiInt::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithAsciiInt{a:42, len:3, b:255})));
+
+    // non-digit
+    let input = b"00x2\x01\x31";
+    let res = StructWithAsciiInt::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::MapRes))));
+
+    // overflow
+    let input = b"0042\x03256";
+    let res = StructWithAsciiInt::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[5..], ErrorKind::MapRes))));
+}
+
+#[test]
+fn test_struct_with_string() {
+    let input = b"\x00\x00\x00\x05hello\x02hi\x03\x00abc";
+    let res = StructWithString::parse(input);
+    assert_eq!(res, Ok((&input[17..],StructWithString{
+        name: "hello".to_string(),
+        short_name: "hi".to_string(),
+        le_name: "abc".to_string(),
+    })));
+
+    // invalid UTF-8 is replaced
+    let input = b"\x00\x00\x00\x01\xff\x00\x00\x00";
+    let res = StructWithString::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithString{
+        name: "\u{fffd}".to_string(),
+        short_name: String::new(),
+        le_name: String::new(),
+    })));
+
+    // truncated string
+    let input = b"\x00\x00\x00\x05hel";
+    let res = StructWithString::parse(input);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(5))));
+}
+
+#[test]
+fn test_struct_with_parse_name() {
+    let input = b"\x7f\x00\x00\x01\x02";
+    let res = StructWithParseName::parse_v4(input);
+    assert_eq!(res, Ok((&input[4..],StructWithParseName{a:[127,0,0,1]})));
+    assert_eq!(StructWithParseName::parse("127.0.0.1"), Some(StructWithParseName{a:[127,0,0,1]}));
+    let res = StructWithRenamedField::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithRenamedField{addr:StructWithParseName{a:[127,0,0,1]}})));
+}
+
+#[test]
+fn test_struct_with_cstring() {
+    let input = b"abc\x00\x00\x01";
+    let res = StructWithCString::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructWithCString{
+        a: CString::new("abc").unwrap(),
+        b: CString::new("").unwrap(),
+        c: 1,
+    })));
+
+    // missing terminator
+    let input = b"abc\x00def";
+    let res = StructWithCString::parse(input);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+}
+
+#[test]
+fn test_struct_with_string_count() {
+    let input = b"\x05helloFR";
+    let res = StructWithStringCount::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithStringCount{len:5, name:"hello".to_string(), code:"FR".to_string()})));
+
+    // empty string
+    let input = b"\x00FR";
+    let res = StructWithStringCount::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithStringCount{len:0, name:String::new(), code:"FR".to_string()})));
+
+    // invalid UTF-8
+    let input = b"\x01\xffFR";
+    let res = StructWithStringCount::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::MapRes))));
+}
+
+#[test]
+fn test_struct_with_tag() {
+    let input = b"\xca\xfe\xba\xbePK\x00\x01\x7fELF\x02";
+    let res = StructWithTag::parse(input);
+    assert_eq!(res, Ok((&input[13..],StructWithTag{
+        magic: [0xca, 0xfe, 0xba, 0xbe],
+        signature: b"PK",
+        version: (),
+        elf: PhantomData,
+        a: 2,
+    })));
+
+    // wrong magic
+    let input = b"\xca\xfe\xba\xbfPK\x00\x01\x7fELF\x02";
+    let res = StructWithTag::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::Tag))));
+
+    // wrong version
+    let input = b"\xca\xfe\xba\xbePK\x00\x02\x7fELF\x02";
+    let res = StructWithTag::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[6..], ErrorKind::Tag))));
+}
+
+#[test]
+fn test_struct_with_struct_tag() {
+    let input = b"\x7fELF\x02\x01";
+    let res = StructWithStructTag::parse(input);
+    assert_eq!(res, Ok((&input[6..],StructWithStructTag{class:2, data:[1]})));
+    let input = b"MZ\x90\x00";
+    let res = StructWithStructTag::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::Tag))));
+}
+
+#[test]
+fn test_struct_with_max_len() {
+    let input = b"\x00\x02\x01\x02";
+    let res = StructWithMaxLen::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithMaxLen{len:2, data:vec![1,2]})));
+
+    let input = vec![0; 0x10000];
+    let res = StructWithMaxLen::parse(&input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::TooLarge))));
+}
+
+#[test]
+fn test_struct_with_magic() {
+    let input = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0d";
+    let res = StructWithMagic::parse(input);
+    assert_eq!(res, Ok((&input[12..],StructWithMagic{chunk_len:13})));
+    let input = b"\x89PNG\r\n\x1a\r\x00\x00\x00\x0d";
+    let res = StructWithMagic::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::Tag))));
+}
+
+#[test]
+fn test_struct_with_bool() {
+    let input = b"\x02\x01\x00\x00\x01";
+    let res = StructWithBool::parse(input);
+    assert_eq!(res, Ok((&input[5..],StructWithBool{a:true, b:true, c:Some(false), d:vec![false,true]})));
+    // strict values must be 0 or 1
+    let input = b"\x02\x01\x00\x00\x02";
+    let res = StructWithBool::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[4..], ErrorKind::Verify))));
+    let input = b"\x00\x02\x00\x00\x01";
+    let res = StructWithBool::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_with_run_length() {
+    let input = b"\x04\x20\x01\x41\x02\x42\x00\x00\x05\x03\x00\x02\xff";
+    let res = StructWithRunLength::parse(input);
+    assert_eq!(res, Ok((&input[13..],StructWithRunLength{
+        pixels: b"    ABB".to_vec(),
+        len: 5,
+        mask: vec![0, 0, 0, 0xff, 0xff],
+    })));
+    // missing terminator
+    let res = StructWithRunLength::parse(&input[..6]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+    // the last run is longer than the declared length
+    let input = b"\x00\x00\x04\x03\x00\x02\xff";
+    let res = StructWithRunLength::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[5..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_with_nom_err() {
+    let input = b"\xca\xfe\x01";
+    let res : IResult<&[u8],StructWithNomErr,AppError> = StructWithNomErr::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithNomErr{magic:0xcafe, a:1})));
+    let input = b"\xca\xfd\x01";
+    let res = StructWithNomErr::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::Custom(AppError::Magic)))));
+    let res = StructWithNomErr::parse(b"\xca\xfe");
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+}
+
+#[test]
+fn test_struct_with_option_parsers() {
+    let input = b"\x01\x00\x02\x00\x01\x00\x02\x03";
+    let res = StructWithOptionParsers::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithOptionParsers{a:Some(1), n:2, b:Some(vec![1,2]), c:Some(3)})));
+    // the list is truncated
+    let res = StructWithOptionParsers::parse(&input[..5]);
+    assert_eq!(res, Ok((&input[4..5],StructWithOptionParsers{a:Some(1), n:2, b:None, c:Some(0)})));
+    // the outer parser does not read the value
+    let input = b"\x01\x00\x01\x00\x01";
+    let res = StructWithOptionParsers::parse(input);
+    assert_eq!(res, Ok((&input[5..],StructWithOptionParsers{a:Some(1), n:1, b:Some(vec![1]), c:None})));
+}
+
+#[test]
+fn test_struct_with_bool_width() {
+    let input = b"\x00\x01\x00\x00\x01\x00\x00\x00";
+    let res = StructWithBoolWidth::parse(input);
+    assert_eq!(res, Ok((&input[8..],StructWithBoolWidth{a:true, b:vec![true, false]})));
+    let input = b"\x00\x00\x00\x00\x00\x01";
+    let res = StructWithBoolWidth::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithBoolWidth{a:false, b:vec![]})));
+}
+
+#[test]
+fn test_struct_with_char() {
+    let input = b"\x00\x00\x00\x41\xe2\x82\xac\x00\xf6\x01\x00";
+    let res = StructWithChar::parse(input);
+    assert_eq!(res, Ok((&input[11..],StructWithChar{a:'A', b:'\u{20ac}', c:Some('\u{1f600}')})));
+    let input = b"\x00\x00\x00\x41z";
+    let res = StructWithChar::parse(input);
+    assert_eq!(res, Ok((&input[5..],StructWithChar{a:'A', b:'z', c:None})));
+    // invalid code point (surrogate)
+    let input = b"\x00\x00\xd8\x00z";
+    let res = StructWithChar::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[..], ErrorKind::MapOpt))));
+    // invalid UTF-8 sequence
+    let input = b"\x00\x00\x00\x41\xe2\x41\x41";
+    let res = StructWithChar::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[4..], ErrorKind::Char))));
+    // truncated UTF-8 sequence
+    let res = StructWithChar::parse(&input[..6]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(3))));
+}
+
+#[test]
+fn test_struct_with_nibbles() {
+    let input = b"\xa5\x0f";
+    let res = StructWithNibbles::parse(input);
+    assert_eq!(res, Ok((&input[2..],StructWithNibbles{high:0xa, low:0x5, a:0, b:0xf})));
+    let input = b"\x50\x0f";
+    let res = StructWithNibbles::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_with_width() {
+    let input = b"\x00\x01\x00\x00\xfe\xff\xff\xff\xff\xff\xff\xff\x00\x00\x00\x00\x00\x00\x00\x2a";
+    let res = StructWithWidth::parse(input);
+    assert_eq!(res, Ok((&input[20..],StructWithWidth{a:0x10000, b:-2, c:Some(42)})));
+    let res = StructWithWidth::parse(&input[..12]);
+    assert_eq!(res, Ok((&input[12..12],StructWithWidth{a:0x10000, b:-2, c:None})));
+}
+
+#[test]
+fn test_struct_with_versions() {
+    let input = b"\x02\x01\x07";
+    let res = StructWithVersions::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithVersions{version:2, a:1, b:None, c:7, d:None, e:None})));
+    let input = b"\x03\x01\x00\x10\x07\x08\x09";
+    let res = StructWithVersions::parse(input);
+    assert_eq!(res, Ok((&input[7..],StructWithVersions{version:3, a:1, b:Some(0x10), c:7, d:Some(8), e:Some(9)})));
+    let input = b"\x06\x00\x00\x10";
+    let res = StructWithVersions::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithVersions{version:6, a:0, b:Some(0x10), c:0, d:None, e:None})));
+}
+
+#[test]
+fn test_struct_with_non_zero() {
+    let input = b"\x00\x00\x01\x00\xff\xfe\x50\x00\x01\x02";
+    let res = StructWithNonZero::parse(input);
+    assert_eq!(res, Ok((&input[10..],StructWithNonZero{
+        id: NonZeroU32::new(256).unwrap(),
+        delta: NonZeroI16::new(-2).unwrap(),
+        port: NonZeroU16::new(80),
+        flags: vec![NonZeroU8::new(1).unwrap(), NonZeroU8::new(2).unwrap()],
+    })));
+    // zero values are errors
+    let input = b"\x00\x00\x01\x00\x00\x00";
+    let res = StructWithNonZero::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[4..], ErrorKind::MapOpt))));
+    let input = b"\x00\x00\x01\x00\xff\xfe\x50\x00\x01\x00";
+    let res = StructWithNonZero::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[9..], ErrorKind::MapOpt))));
+}
+
+#[test]
+fn test_struct_with_monotonic() {
+    let input = b"\x03\x00\x00\x00\x01\x00\x00\x00\x01\x00\x00\x01\x00\xfe\x00\x01";
+    let res = StructWithMonotonic::parse(input);
+    assert_eq!(res, Ok((&input[16..],StructWithMonotonic{n:3, offsets:vec![1, 1, 256], keys:[-2, 0, 1]})));
+    // out of order items
+    let input = b"\x02\x00\x00\x00\x02\x00\x00\x00\x01\xfe\x00\x01";
+    let res = StructWithMonotonic::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+    let input = b"\x00\x01\x00\xff";
+    let res = StructWithMonotonic::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Custom(3)))));
+    // empty lists are sorted
+    let input = b"\x00\x01\x01\x01";
+    let res = StructWithMonotonic::parse(input);
+    assert_eq!(res, Ok((&input[4..],StructWithMonotonic{n:0, offsets:vec![], keys:[1, 1, 1]})));
+}
+
+#[test]
+fn test_struct_with_align() {
+    let input = b"\x08\xff\xff\xff\x00\x01\x00\x02\x03\xff\xff\xff\xff\xff\xff\xff\x04";
+    let res = StructWithAlign::parse(input);
+    assert_eq!(res, Ok((&input[17..],StructWithAlign{a:8, b:1, c:2, d:3, e:4})));
+    // the offset of the last field is already aligned
+    let input = b"\x01\xff\xff\xff\x00\x01\x00\x02\x03\x04";
+    let res = StructWithAlign::parse(input);
+    assert_eq!(res, Ok((&input[10..],StructWithAlign{a:1, b:1, c:2, d:3, e:4})));
+    // missing padding bytes
+    let res = StructWithAlign::parse(&input[..2]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(3))));
+}
+
+#[test]
+fn test_struct_with_exec() {
+    let input = b"\x02ab\x07\xff";
+    let res = StructWithExec::parse(input);
+    assert_eq!(res, Ok((&input[5..],StructWithExec{len:2, name:b"ab", extra:Some(7), header_len:3, count:0})));
+    let input = b"\x00\x04";
+    let res = StructWithExec::parse(input);
+    assert_eq!(res, Ok((&input[2..],StructWithExec{len:0, name:b"", extra:None, header_len:1, count:5})));
+}
+
+#[test]
+fn test_struct_with_bounded_align() {
+    // the offset of the fields in the bounded region is relative to the start of the input
+    let input = b"\x04\x01\xff\xff\x02\xff";
+    let res = StructWithBoundedAlign::parse(input);
+    assert_eq!(res, Ok((&input[5..],StructWithBoundedAlign{len:4, a:1, b:2})));
+    // the padding bytes must be in the bounded region
+    let input = b"\x02\x01\xff\xff\x02";
+    let res = StructWithBoundedAlign::parse(input);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(2))));
+}
+
+#[test]
+fn test_struct_with_field_named_i() {
+    let input = b"\x01\xff\xff\xff\x02\x03\x04\x05\x0c";
+    let res = StructWithFieldNamedI::parse(input);
+    assert_eq!(res, Ok((&input[9..],StructWithFieldNamedI{i:1, b:2, items:vec![3, 4, 5], sum:0x0c})));
+    assert_eq!(StructWithFieldNamedI::parse_traced(input, &mut |_, _, _| ()), res);
+}
@@ -8,6 +8,9 @@ extern crate nom_derive;
 extern crate nom;
 
 use nom::*;
+use std::collections::{BTreeMap,HashMap};
+use std::rc::Rc;
+use std::sync::Arc;
 
 /// A simple structure with an Option type
 #[derive(Debug,PartialEq,Nom)]
@@ -30,6 +33,366 @@ struct StructWithVec {
     b: Vec<u32>,
 }
 
+/// A simple structure with arrays
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithArray {
+    pub a: [u8; 4],
+    b: [u32; 2],
+}
+
+/// A key/value pair, used as value of a map
+#[derive(Debug,PartialEq,Nom)]
+struct Attribute {
+    pub kind: u8,
+    pub value: u16,
+}
+
+/// A structure with maps
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithHashMap {
+    pub n: u8,
+    #[Count="n"]
+    pub attrs: HashMap<u16, Attribute>,
+    #[LittleEndian]
+    #[Count="1"]
+    pub flags: HashMap<u8, u32>,
+}
+
+/// A structure with a sorted map
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithBTreeMap {
+    pub n: u8,
+    #[Count="n"]
+    pub attrs: BTreeMap<u8, u16>,
+}
+
+/// A structure with a 16-bytes array
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithArray16 {
+    pub data: [u8; 16],
+    #[LittleEndian]
+    pub words: [u16; 4],
+}
+
+/// A structure with arrays of structures, and of nested arrays
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithStructArray {
+    pub attrs: [Attribute; 3],
+    #[LittleEndian]
+    pub values: [i32; 2],
+    #[LittleEndian]
+    pub matrix: [[u16; 2]; 2],
+}
+
+/// A structure with tuple fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithTuples {
+    pub a: (u8, u16),
+    #[LittleEndian]
+    pub b: ((u8, u16), u32),
+    pub c: (u8,),
+    pub n: u8,
+    #[Count="n"]
+    pub d: Vec<(u8, u16)>,
+    #[Count="2"]
+    pub e: (u8, Vec<u8>, Option<u8>),
+}
+
+/// A structure with boxed fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithBoxes {
+    pub a: Box<u16>,
+    pub header: Box<CountHeader>,
+    #[Cond="a.is_power_of_two()"]
+    pub b: Option<Box<u8>>,
+    pub c: Box<(u8, [u8; 2])>,
+    #[allow(clippy::box_collection)]
+    pub d: Box<Vec<u8>>,
+}
+
+/// A structure with shared fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithSharedFields {
+    pub header: Rc<CountHeader>,
+    pub n: u8,
+    #[Count="n"]
+    pub values: Arc<Vec<u16>>,
+    #[LittleEndian]
+    pub a: Arc<u32>,
+    #[Cond="n > 1"]
+    pub b: Option<Rc<u8>>,
+    #[Count="2"]
+    #[allow(clippy::box_collection)]
+    pub c: Box<Vec<u8>>,
+    pub items: Vec<Rc<u8>>,
+}
+
+/// A recursive structure (a linked list)
+#[derive(Debug,PartialEq,Nom)]
+struct BoxedList {
+    pub value: u8,
+    #[Cond="value != 0"]
+    pub next: Option<Box<BoxedList>>,
+}
+
+/// A tuple structure with byte arrays
+#[derive(Debug,PartialEq,Nom)]
+struct TupleWithByteArrays([u8; 4], u8, [u8; 40]);
+
+/// An enum with byte arrays in variants
+#[derive(Debug,PartialEq,Nom)]
+#[Selector="u8"]
+enum HashValue {
+    #[Selector("1")] Md5([u8; 16]),
+    #[Selector("2")] Sha1{ digest: [u8; 20] },
+}
+
+/// The kind of a record
+#[derive(Debug,PartialEq,Eq,Clone,Copy,Nom)]
+#[repr(u8)]
+enum RecordKind {
+    Short = 1,
+    Empty = 2,
+    Counter = 3,
+}
+
+/// A structure with a field selected by a previous field
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithMatch {
+    pub kind: RecordKind,
+    #[Match("kind",
+            "RecordKind::Short => map!(be_u16, Some)",
+            "RecordKind::Empty => value!(None)",
+            "RecordKind::Counter => map!(be_u8, |n| Some(u16::from(n) * 10))")]
+    pub value: Option<u16>,
+    #[Match("value", "Some(v) if v > 100 => be_u8", "None => value!(0)")]
+    pub extra: u8,
+}
+
+/// A header giving the sizes of the lists
+#[derive(Debug,PartialEq,Nom)]
+struct CountHeader {
+    pub rows: u8,
+    pub cols: u8,
+}
+
+/// A structure with lists whose lengths are computed from previous fields
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithCountExpressions {
+    pub header: CountHeader,
+    pub a: u8,
+    #[Count="(a as usize) * 2"]
+    pub doubled: Vec<u8>,
+    #[Count="a.checked_sub(1).unwrap_or(0)"]
+    pub minus_one: Vec<u8>,
+    #[Count="header.rows as usize * header.cols as usize - 1"]
+    pub cells: Vec<u8>,
+    #[Count="usize::from(header.rows) + usize::from(a)"]
+    pub name: String,
+}
+
+/// A structure with counts given as expressions without quotes
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithCountTokens {
+    pub a: u8,
+    #[Count(a * 2 + 1)]
+    pub odd: Vec<u8>,
+    #[Count(a)]
+    pub same: Vec<u16>,
+    #[Count("a - 1")]
+    pub less: Vec<u8>,
+}
+
+/// The state negotiated before parsing records
+#[derive(Debug,Default)]
+pub struct Session {
+    pub version: u8,
+    pub tag_len: usize,
+}
+
+/// A structure parsed using the session state
+#[derive(Debug,PartialEq,Nom)]
+#[NomContext="Session"]
+struct RecordWithContext<'a> {
+    pub kind: u8,
+    #[ParseOuter="cond!(ctx.version > 1, be_u16)"]
+    pub flags: Option<u16>,
+    #[Parse="take!(ctx.tag_len)"]
+    pub tag: &'a [u8],
+    #[Count="ctx.version"]
+    pub values: Vec<u8>,
+}
+
+/// A structure with context, and a field named `ctx`
+#[derive(Debug,PartialEq,Nom)]
+#[NomContext="Session"]
+struct RecordWithCtxField {
+    #[Count="ctx.version"]
+    pub values: Vec<u8>,
+    pub ctx: u8,
+}
+
+/// A nested structure reporting its field boundaries
+#[derive(Debug,PartialEq,Nom)]
+#[NomParseTraced]
+struct BoundsInner(u16, u8);
+
+/// A structure reporting its field boundaries
+#[derive(Debug,PartialEq,Nom)]
+#[NomParseTraced]
+struct BoundsOuter {
+    pub len: u8,
+    pub inner: BoundsInner,
+    #[Count="len"]
+    pub items: Vec<BoundsInner>,
+    #[Skip=1]
+    pub end: u8,
+}
+
+/// A structure reporting the boundaries of fields in a bounded region
+#[derive(Debug,PartialEq,Nom)]
+#[NomParseTraced]
+struct BoundsBounded {
+    #[BoundingLength]
+    pub len: u8,
+    pub a: u8,
+    pub inner: BoundsInner,
+}
+
+/// A structure reporting its field boundaries, with constant and padding bytes
+#[derive(Debug,PartialEq,Nom)]
+#[NomParseTraced]
+#[Magic(b"TR")]
+struct BoundsPadded {
+    pub a: u8,
+    #[Align=4]
+    pub b: u16,
+    #[Tag=b"\xff"]
+    pub marker: (),
+    #[Skip=1]
+    pub c: u8,
+}
+
+/// A structure with two lists of the same length
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithParallelLists {
+    pub n: u8,
+    #[Count="n"]
+    pub names: Vec<String>,
+    #[Count="names.len()"]
+    pub values: Vec<u32>,
+}
+
+/// An item stored by columns
+#[derive(Debug,PartialEq,Nom)]
+#[Columnar]
+struct Point {
+    pub x: u16,
+    pub y: u16,
+    #[LittleEndian]
+    pub z: u32,
+    #[Default="true"]
+    pub visible: bool,
+}
+
+/// A tuple struct stored by columns
+#[derive(Debug,PartialEq,Nom)]
+#[Columnar]
+struct Sample(u8);
+
+/// An item stored by columns, with a verified field
+#[derive(Debug,PartialEq,Nom)]
+#[Columnar]
+struct Reading {
+    #[Verify="level <= 100"]
+    pub level: u8,
+    pub unit: u8,
+}
+
+/// A structure with lists of items stored by columns
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithColumns {
+    pub n: u8,
+    #[Columnar(count="n")]
+    pub points: Vec<Point>,
+    #[Columnar(count="2")]
+    pub samples: Vec<Sample>,
+}
+
+/// An entry of a table, with a verified field
+#[derive(Debug,PartialEq,Nom)]
+struct TableEntry {
+    #[Verify="id < 0x80"]
+    pub id: u8,
+    pub len: u8,
+}
+
+/// A table with a fixed number of entries
+#[derive(Debug,PartialEq,Nom)]
+struct StructWithTable {
+    #[Count=16]
+    pub entries: Vec<TableEntry>,
+}
+
+/// The innermost structure of a traced hierarchy
+#[derive(Debug,PartialEq,Nom)]
+#[NomTraceErrors]
+struct TracedLeaf {
+    #[Verify="v < 0x80"]
+    pub v: u8,
+}
+
+/// A traced structure, containing a leaf
+#[derive(Debug,PartialEq,Nom)]
+#[NomTraceErrors]
+struct TracedMiddle {
+    pub opt: Option<TracedLeaf>,
+    pub leaf: TracedLeaf,
+}
+
+/// The outer structure of a traced hierarchy
+#[derive(Debug,PartialEq,Nom)]
+#[NomTraceErrors]
+struct TracedOuter(u8, TracedMiddle);
+
+/// A traced structure, with groups of fields
+#[derive(Debug,PartialEq,Nom)]
+#[NomTraceErrors]
+struct TracedGroups {
+    pub kind: u8,
+    #[NomGroup="options"]
+    #[Skip=1]
+    pub len: u8,
+    #[NomGroup="options"]
+    pub leaf: TracedLeaf,
+    #[NomGroup="trailer"]
+    pub end: u8,
+}
+
+/// A traced structure, with constant and skipped bytes, and a checksum
+#[derive(Debug,PartialEq,Nom)]
+#[NomTraceErrors]
+#[Magic(b"TT")]
+struct TracedTagged {
+    #[SelfExcludingChecksum="sum8"]
+    pub sum: u8,
+    #[Tag=b"\xff"]
+    pub marker: (),
+    #[Skip=1]
+    pub end: u8,
+}
+
+/// The same structure, without groups
+#[derive(Debug,PartialEq,Nom)]
+#[NomTraceErrors]
+struct TracedNoGroups {
+    pub kind: u8,
+    #[Skip=1]
+    pub len: u8,
+    pub leaf: TracedLeaf,
+    pub end: u8,
+}
+
 #[test]
 fn test_struct_with_option() {
     let input = b"\x00\x00\x00\x01\x12\x34\x56\x78\x12\x34\x56\x78\x00\x00\x00\x01";
@@ -56,3 +419,439 @@ fn test_struct_with_vec() {
         vec![0x12345678,0x12345678,0x1]
         })));
 }
+
+#[test]
+fn test_struct_with_array() {
+    let input = b"\x01\x02\x03\x04\x12\x34\x56\x78\x00\x00\x00\x01";
+    let res = StructWithArray::parse(input);
+    assert_eq!(res, Ok((&input[12..],StructWithArray{a:[1,2,3,4], b:[0x12345678,1]})));
+
+    let res = StructWithArray::parse(&input[..8]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_struct_with_array16() {
+    let input = b"\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\
+                  \x01\x00\x02\x00\x03\x00\x04\x00";
+    let res = StructWithArray16::parse(input);
+    assert_eq!(res, Ok((&input[24..],StructWithArray16{
+        data: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+        words: [1, 2, 3, 4],
+    })));
+}
+
+#[test]
+fn test_struct_array() {
+    let input = b"\x01\x00\x02\x03\x00\x04\x05\x00\x06\
+                  \xff\xff\xff\xff\x01\x00\x00\x00\
+                  \x01\x00\x02\x00\x03\x00\x04\x00";
+    let res = StructWithStructArray::parse(input);
+    assert_eq!(res, Ok((&input[25..], StructWithStructArray{
+        attrs: [
+            Attribute{kind:1, value:2},
+            Attribute{kind:3, value:4},
+            Attribute{kind:5, value:6},
+        ],
+        values: [-1, 1],
+        matrix: [[1, 2], [3, 4]],
+    })));
+    // partial input is an error
+    for len in 0..input.len() {
+        assert!(StructWithStructArray::parse(&input[..len]).is_err());
+    }
+}
+
+#[test]
+fn test_byte_arrays() {
+    let input : Vec<u8> = (0..50).collect();
+    let res = TupleWithByteArrays::parse(&input);
+    let mut blob = [0u8; 40];
+    blob.copy_from_slice(&input[5..45]);
+    assert_eq!(res, Ok((&input[45..], TupleWithByteArrays([0, 1, 2, 3], 4, blob))));
+    let res = TupleWithByteArrays::parse(&input[..44]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(40))));
+
+    let res = HashValue::parse(&input, 1);
+    let mut md5 = [0u8; 16];
+    md5.copy_from_slice(&input[..16]);
+    assert_eq!(res, Ok((&input[16..], HashValue::Md5(md5))));
+    let res = HashValue::parse(&input, 2);
+    let mut digest = [0u8; 20];
+    digest.copy_from_slice(&input[..20]);
+    assert_eq!(res, Ok((&input[20..], HashValue::Sha1{digest})));
+    let res = HashValue::parse(&input[..19], 2);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(20))));
+}
+
+#[test]
+fn test_struct_with_tuples() {
+    let input = b"\x01\x00\x02\x03\x04\x00\x05\x00\x00\x00\x06\x02\x07\x00\x08\x09\x00\x0a\x0b\x0c\x0d\x0e";
+    let res = StructWithTuples::parse(input);
+    let expected = StructWithTuples{
+        a: (1, 2),
+        b: ((3, 4), 5),
+        c: (6,),
+        n: 2,
+        d: vec![(7, 8), (9, 10)],
+        e: (11, vec![12, 13], Some(14)),
+    };
+    assert_eq!(res, Ok((&input[22..], expected)));
+    let res = StructWithTuples::parse(&input[..2]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(2))));
+}
+
+#[test]
+fn test_struct_with_boxes() {
+    let input = b"\x00\x02\x03\x04\x05\x06\x07\x08\x09\x0a";
+    let res = StructWithBoxes::parse(input);
+    assert_eq!(res, Ok((&input[10..], StructWithBoxes{
+        a: Box::new(2),
+        header: Box::new(CountHeader{rows:3, cols:4}),
+        b: Some(Box::new(5)),
+        c: Box::new((6, [7, 8])),
+        d: Box::new(vec![9, 10]),
+    })));
+    let res = StructWithBoxes::parse(&input[..3]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+
+    let input = b"\x01\x02\x03\x00\xff";
+    let res = BoxedList::parse(input);
+    let list = BoxedList{value:1, next:Some(Box::new(BoxedList{value:2, next:Some(Box::new(BoxedList{value:3,
+        next:Some(Box::new(BoxedList{value:0, next:None}))}))}))};
+    assert_eq!(res, Ok((&input[4..], list)));
+    let res = BoxedList::parse(&input[..0]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+}
+
+#[test]
+fn test_struct_with_shared_fields() {
+    let input = b"\x01\x02\x02\x00\x03\x00\x04\x05\x00\x00\x00\x06\x07\x08\x09\x0a";
+    let res = StructWithSharedFields::parse(input);
+    assert_eq!(res, Ok((&input[16..], StructWithSharedFields{
+        header: Rc::new(CountHeader{rows:1, cols:2}),
+        n: 2,
+        values: Arc::new(vec![3, 4]),
+        a: Arc::new(5),
+        b: Some(Rc::new(6)),
+        c: Box::new(vec![7, 8]),
+        items: vec![Rc::new(9), Rc::new(10)],
+    })));
+    let res = StructWithSharedFields::parse(&input[..5]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(2))));
+}
+
+#[test]
+fn test_struct_with_count_expressions() {
+    let input = b"\x02\x03\x01\x0a\x0b\x01\x02\x03\x04\x05abc";
+    let res = StructWithCountExpressions::parse(input);
+    assert_eq!(res, Ok((&input[13..], StructWithCountExpressions{
+        header: CountHeader{rows:2, cols:3},
+        a: 1,
+        doubled: vec![10, 11],
+        minus_one: vec![],
+        cells: vec![1, 2, 3, 4, 5],
+        name: "abc".to_string(),
+    })));
+    // counts of zero
+    let input = b"\x01\x01\x00x";
+    let res = StructWithCountExpressions::parse(input);
+    assert_eq!(res, Ok((&input[4..], StructWithCountExpressions{
+        header: CountHeader{rows:1, cols:1},
+        a: 0,
+        doubled: vec![],
+        minus_one: vec![],
+        cells: vec![],
+        name: "x".to_string(),
+    })));
+}
+
+#[test]
+fn test_struct_with_count_tokens() {
+    let input = b"\x02\x01\x02\x03\x04\x05\x00\x06\x00\x07\x08\xff";
+    let res = StructWithCountTokens::parse(input);
+    assert_eq!(res, Ok((&input[11..], StructWithCountTokens{
+        a: 2,
+        odd: vec![1, 2, 3, 4, 5],
+        same: vec![6, 7],
+        less: vec![8],
+    })));
+    let res = StructWithCountTokens::parse(&input[..4]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(1))));
+}
+
+#[test]
+fn test_struct_with_context() {
+    let input = b"\x01\x00\x02\xaa\xbb\x03\x04";
+    let session = Session{version: 2, tag_len: 2};
+    let res = RecordWithContext::parse_with_context(input, &session);
+    assert_eq!(res, Ok((&input[7..], RecordWithContext{kind:1, flags:Some(2), tag:b"\xaa\xbb", values:vec![3, 4]})));
+    // parse uses the default context
+    let res = RecordWithContext::parse(input);
+    assert_eq!(res, Ok((&input[1..], RecordWithContext{kind:1, flags:None, tag:b"", values:vec![]})));
+    let session = Session{version: 1, tag_len: 0};
+    let res = RecordWithContext::parse_with_context(input, &session);
+    assert_eq!(res, Ok((&input[2..], RecordWithContext{kind:1, flags:None, tag:b"", values:vec![0]})));
+}
+
+#[test]
+fn test_struct_with_context_field_named_ctx() {
+    let input = b"\x01\x02\x03";
+    let session = Session{version: 2, tag_len: 0};
+    let res = RecordWithCtxField::parse_with_context(input, &session);
+    assert_eq!(res, Ok((&input[3..], RecordWithCtxField{values:vec![1, 2], ctx:3})));
+    let res = RecordWithCtxField::parse(input);
+    assert_eq!(res, Ok((&input[1..], RecordWithCtxField{values:vec![], ctx:1})));
+}
+
+#[test]
+fn test_struct_parse_traced() {
+    let input = b"\x02\x00\x01\x02\x00\x03\x04\x00\x05\x06\xff\x07";
+    let mut bounds = Vec::new();
+    let res = BoundsOuter::parse_traced(input, &mut |name, start, end| bounds.push((name, start, end)));
+    let expected = BoundsOuter{
+        len: 2,
+        inner: BoundsInner(1, 2),
+        items: vec![BoundsInner(3, 4), BoundsInner(5, 6)],
+        end: 7,
+    };
+    assert_eq!(res, Ok((&input[12..], expected)));
+    assert_eq!(res, BoundsOuter::parse(input));
+    assert_eq!(bounds, vec![
+        ("BoundsOuter.len", 0, 1),
+        ("BoundsInner.0", 1, 3),
+        ("BoundsInner.1", 3, 4),
+        ("BoundsOuter.inner", 1, 4),
+        ("BoundsInner.0", 4, 6),
+        ("BoundsInner.1", 6, 7),
+        ("BoundsInner.0", 7, 9),
+        ("BoundsInner.1", 9, 10),
+        ("BoundsOuter.items", 4, 10),
+        ("BoundsOuter.end", 11, 12),
+    ]);
+    // the fields of the outer structure are in order (the skipped bytes are not reported)
+    let outer : Vec<_> = bounds.iter().filter(|(name, _, _)| name.starts_with("BoundsOuter.")).collect();
+    assert_eq!(outer.first().map(|b| b.1), Some(0));
+    assert_eq!(outer.last().map(|b| b.2), Some(input.len()));
+    assert!(outer.windows(2).all(|w| w[0].2 <= w[1].1));
+    // the fields parsed before an error are reported
+    bounds.clear();
+    let res = BoundsOuter::parse_traced(&input[..5], &mut |name, start, end| bounds.push((name, start, end)));
+    assert!(res.is_err());
+    assert_eq!(bounds.len(), 4);
+}
+
+#[test]
+fn test_struct_parse_traced_bounded() {
+    // the offsets in the bounded region are relative to the start of the input
+    let input = b"\x04\x01\x00\x02\x03\xff\xff";
+    let mut bounds = Vec::new();
+    let res = BoundsBounded::parse_traced(input, &mut |name, start, end| bounds.push((name, start, end)));
+    assert_eq!(res, Ok((&input[5..], BoundsBounded{len:4, a:1, inner:BoundsInner(2, 3)})));
+    assert_eq!(bounds, vec![
+        ("BoundsBounded.len", 0, 1),
+        ("BoundsBounded.a", 1, 2),
+        ("BoundsInner.0", 2, 4),
+        ("BoundsInner.1", 4, 5),
+        ("BoundsBounded.inner", 2, 5),
+    ]);
+}
+
+#[test]
+fn test_struct_parse_traced_padding() {
+    // the magic, padding and skipped bytes are not reported
+    let input = b"TR\x01\x00\x00\x02\xff\x00\x03";
+    let mut bounds = Vec::new();
+    let res = BoundsPadded::parse_traced(input, &mut |name, start, end| bounds.push((name, start, end)));
+    assert_eq!(res, Ok((&input[9..], BoundsPadded{a:1, b:2, marker:(), c:3})));
+    assert_eq!(bounds, vec![
+        ("BoundsPadded.a", 2, 3),
+        ("BoundsPadded.b", 4, 6),
+        ("BoundsPadded.marker", 6, 7),
+        ("BoundsPadded.c", 8, 9),
+    ]);
+}
+
+#[test]
+fn test_struct_with_hashmap() {
+    let input = b"\x02\x00\x01\x0a\x00\x10\x00\x02\x0b\x00\x20\x07\x01\x00\x00\x00";
+    let (rem, s) = StructWithHashMap::parse(input).expect("parsing failed");
+    assert!(rem.is_empty());
+    assert_eq!(s.n, 2);
+    assert_eq!(s.attrs.len(), 2);
+    assert_eq!(s.attrs.get(&1), Some(&Attribute{kind:0x0a, value:0x10}));
+    assert_eq!(s.attrs.get(&2), Some(&Attribute{kind:0x0b, value:0x20}));
+    assert_eq!(s.flags.get(&7), Some(&1));
+
+    let res = StructWithHashMap::parse(&input[..8]);
+    assert!(res.is_err());
+}
+
+#[test]
+fn test_struct_with_btreemap() {
+    let input = b"\x03\x02\x00\x20\x01\x00\x10\x03\x00\x30";
+    let res = StructWithBTreeMap::parse(input);
+    let mut attrs = BTreeMap::new();
+    attrs.insert(1, 0x10);
+    attrs.insert(2, 0x20);
+    attrs.insert(3, 0x30);
+    assert_eq!(res, Ok((&input[10..],StructWithBTreeMap{n:3, attrs})));
+    let (_, s) = res.unwrap();
+    assert_eq!(s.attrs.keys().cloned().collect::<Vec<_>>(), vec![1,2,3]);
+}
+
+#[test]
+fn test_struct_with_parallel_lists() {
+    let input = b"\x02\x00\x00\x00\x01a\x00\x00\x00\x01b\x00\x00\x00\x01\x00\x00\x00\x02";
+    let res = StructWithParallelLists::parse(input);
+    assert_eq!(res, Ok((&input[19..],StructWithParallelLists{
+        n: 2,
+        names: vec!["a".to_string(), "b".to_string()],
+        values: vec![1, 2],
+    })));
+    // second list truncated
+    let res = StructWithParallelLists::parse(&input[..15]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(4))));
+}
+
+#[test]
+fn test_struct_with_columns() {
+    let input = b"\x02\x00\x01\x00\x02\x00\x03\x00\x04\x05\x00\x00\x00\x06\x00\x00\x00\x07\x08";
+    let res = StructWithColumns::parse(input);
+    assert_eq!(res, Ok((&input[input.len()..],StructWithColumns{
+        n: 2,
+        points: vec![
+            Point{x:1, y:3, z:5, visible:true},
+            Point{x:2, y:4, z:6, visible:true},
+        ],
+        samples: vec![Sample(7), Sample(8)],
+    })));
+    // the last column is truncated
+    let res = StructWithColumns::parse(&input[..14]);
+    assert_eq!(res, Err(Err::Incomplete(Needed::Size(4))));
+    // no items
+    let input = b"\x00\x07\x08";
+    let res = StructWithColumns::parse(input);
+    assert_eq!(res, Ok((&input[3..],StructWithColumns{n:0, points:vec![], samples:vec![Sample(7), Sample(8)]})));
+}
+
+#[test]
+fn test_columnar_item_errors() {
+    let input = b"\x10\x20\x01\x02";
+    let res = Reading::parse_columnar(input, 2);
+    assert_eq!(res, Ok((&input[4..],vec![Reading{level:0x10, unit:1}, Reading{level:0x20, unit:2}])));
+    // the error is returned at the position of the invalid item, like `parse`
+    let input = b"\x10\xff\x01\x02";
+    let res = Reading::parse_columnar(input, 2);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+    assert_eq!(Reading::parse(&input[1..]), Err(Err::Error(error_position!(&input[1..], ErrorKind::Verify))));
+}
+
+#[test]
+fn test_struct_with_table() {
+    let mut input = Vec::new();
+    for id in 0..16 {
+        input.extend_from_slice(&[id, 4]);
+    }
+    let res = StructWithTable::parse(&input);
+    let entries : Vec<_> = (0..16).map(|id| TableEntry{id, len:4}).collect();
+    assert_eq!(res, Ok((&input[32..],StructWithTable{entries})));
+    // entry 7 is malformed: the error points to its input, not to the start of the table
+    input[7 * 2] = 0x80;
+    let res = StructWithTable::parse(&input);
+    match res {
+        Err(Err::Error(Context::Code(rem, ErrorKind::Verify))) => assert_eq!(rem.len(), input.len() - 7 * 2),
+        e => panic!("unexpected result {:?}", e),
+    }
+}
+
+#[test]
+fn test_struct_trace_errors() {
+    let input = b"\x01\x02\x03";
+    let expected = TracedOuter(1, TracedMiddle{opt:Some(TracedLeaf{v:2}), leaf:TracedLeaf{v:3}});
+    assert_eq!(TracedOuter::parse_trace(input), Ok((&input[3..], expected)));
+    assert_eq!(TracedOuter::parse(input), TracedOuter::parse_trace(input).map_err(|(e, _)| e));
+    // the error of the optional leaf is recovered, the second leaf fails
+    let input = b"\x01\x82\x83";
+    let res = TracedOuter::parse_trace(input);
+    assert_eq!(res, Err((Err::Error(error_position!(&input[1..], ErrorKind::Verify)),
+                         vec!["TracedOuter.1", "TracedMiddle.leaf", "TracedLeaf.v"])));
+    // failure in the outer structure
+    let res = TracedOuter::parse_trace(b"");
+    assert_eq!(res, Err((Err::Incomplete(Needed::Size(1)), vec!["TracedOuter.0"])));
+}
+
+#[test]
+fn test_struct_trace_errors_tags() {
+    let input = b"TT\xa8\xff\x00\x01";
+    assert_eq!(TracedTagged::parse_trace(input), Ok((&input[6..], TracedTagged{sum:0xa8, marker:(), end:1})));
+    // the errors of the constant and skipped bytes are reported under the next field
+    let bad = b"TX\xa8\xff\x00\x01";
+    let res = TracedTagged::parse_trace(bad);
+    assert_eq!(res, Err((Err::Error(error_position!(&bad[..], ErrorKind::Tag)), vec!["TracedTagged.sum"])));
+    let bad = b"TT\xa8\xfe\x00\x01";
+    let res = TracedTagged::parse_trace(bad);
+    assert_eq!(res, Err((Err::Error(error_position!(&bad[3..], ErrorKind::Tag)), vec!["TracedTagged.marker"])));
+    let res = TracedTagged::parse_trace(&input[..4]);
+    assert_eq!(res, Err((Err::Incomplete(Needed::Size(1)), vec!["TracedTagged.end"])));
+    // the checksum is verified after the last field
+    let input = b"TT\xa8\xff\x00\x02";
+    let res = TracedTagged::parse_trace(input);
+    assert_eq!(res, Err((Err::Error(error_position!(&input[2..], ErrorKind::Verify)), vec!["TracedTagged"])));
+}
+
+#[test]
+fn test_struct_groups() {
+    let input = b"\x01\xff\x02\x03\x04";
+    let res = TracedGroups::parse(input);
+    assert_eq!(res, Ok((&input[5..], TracedGroups{kind:1, len:2, leaf:TracedLeaf{v:3}, end:4})));
+    // groups do not change the parser
+    for len in 0..input.len() {
+        let grouped = TracedGroups::parse(&input[..len]).map(|(rem, _)| rem);
+        let flat = TracedNoGroups::parse(&input[..len]).map(|(rem, _)| rem);
+        assert_eq!(grouped, flat);
+    }
+    // the label of the group is added to the errors
+    let input = b"\x01\xff\x02\x83\x04";
+    let res = TracedGroups::parse(input);
+    let code = match res {
+        Err(Err::Error(Context::List(ref errors))) => match errors.as_slice() {
+            [(i1, ErrorKind::Verify), (i2, ErrorKind::Custom(code))] if *i1 == &input[3..] && *i2 == &input[3..] => *code,
+            _ => panic!("unexpected errors {:?}", errors),
+        },
+        e => panic!("unexpected result {:?}", e),
+    };
+    assert_eq!(TracedGroups::group_label(code), Some("options"));
+    assert_eq!(TracedGroups::group_label(code + 1), None);
+    let res = TracedNoGroups::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[3..], ErrorKind::Verify))));
+    // the label of the group is in the path
+    let res = TracedGroups::parse_trace(input);
+    assert_eq!(res, Err((TracedGroups::parse(input).unwrap_err(),
+                         vec!["TracedGroups.options.leaf", "TracedLeaf.v"])));
+    let res = TracedNoGroups::parse_trace(input);
+    assert_eq!(res, Err((Err::Error(error_position!(&input[3..], ErrorKind::Verify)),
+                         vec!["TracedNoGroups.leaf", "TracedLeaf.v"])));
+    // skipped bytes are part of the group of the field
+    let res = TracedGroups::parse_trace(&input[..1]);
+    assert_eq!(res, Err((Err::Incomplete(Needed::Size(1)), vec!["TracedGroups.options.len"])));
+    let res = TracedGroups::parse_trace(b"\x01\xff\x02\x03");
+    assert_eq!(res, Err((Err::Incomplete(Needed::Size(1)), vec!["TracedGroups.trailer.end"])));
+}
+
+#[test]
+fn test_struct_with_match() {
+    let input = b"\x01\x01\x00\x07";
+    let res = StructWithMatch::parse(input);
+    assert_eq!(res, Ok((&input[4..], StructWithMatch{kind:RecordKind::Short, value:Some(256), extra:7})));
+    let input = b"\x03\x0b\x07";
+    let res = StructWithMatch::parse(input);
+    assert_eq!(res, Ok((&input[3..], StructWithMatch{kind:RecordKind::Counter, value:Some(110), extra:7})));
+    let input = b"\x02\x07";
+    let res = StructWithMatch::parse(input);
+    assert_eq!(res, Ok((&input[1..], StructWithMatch{kind:RecordKind::Empty, value:None, extra:0})));
+    // no arm matches
+    let input = b"\x03\x01\x07";
+    let res = StructWithMatch::parse(input);
+    assert_eq!(res, Err(Err::Error(error_position!(&input[2..], ErrorKind::Switch))));
+}
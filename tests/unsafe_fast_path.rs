@@ -0,0 +1,78 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate pretty_assertions;
+
+#[macro_use]
+extern crate nom_derive;
+
+extern crate nom;
+
+use nom::*;
+
+/// A structure with a fixed size, parsed using unchecked reads
+#[derive(Debug,PartialEq,Nom)]
+#[NomUnsafeFastPath]
+struct FastHeader {
+    pub a: u8,
+    pub b: u16,
+    #[LittleEndian]
+    pub c: u32,
+    pub d: f64,
+    pub e: i128,
+}
+
+/// The same structure, parsed with the default (safe) parser
+#[derive(Debug,PartialEq,Nom)]
+struct SafeHeader {
+    pub a: u8,
+    pub b: u16,
+    #[LittleEndian]
+    pub c: u32,
+    pub d: f64,
+    pub e: i128,
+}
+
+/// A tuple struct parsed in both byte orders, using unchecked reads
+#[derive(Debug,PartialEq,NomLE)]
+#[NomUnsafeFastPath]
+#[NomBothEndian]
+#[NomMaxLen=8]
+struct FastPair(u16, i32);
+
+/// A structure with a field named like the input of the parse function
+#[derive(Debug,PartialEq,Nom)]
+#[NomUnsafeFastPath]
+struct FastNamedI {
+    pub i: u16,
+    pub j: u8,
+}
+
+#[test]
+fn test_unsafe_fast_path() {
+    let input = b"\x01\x00\x02\x03\x00\x00\x00\x3f\xf8\x00\x00\x00\x00\x00\x00\
+                  \xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xfe\x55";
+    let res = FastHeader::parse(input);
+    assert_eq!(res, Ok((&input[31..], FastHeader{a:1, b:2, c:3, d:1.5, e:-2})));
+    let (rem, safe) = SafeHeader::parse(input).expect("parsing failed");
+    assert_eq!(rem, &input[31..]);
+    assert_eq!(safe, SafeHeader{a:1, b:2, c:3, d:1.5, e:-2});
+    // truncated input
+    for len in 0..31 {
+        assert_eq!(FastHeader::parse(&input[..len]), Err(Err::Incomplete(Needed::Size(31))));
+    }
+}
+
+#[test]
+fn test_unsafe_fast_path_both_endian() {
+    let input = b"\x01\x00\xfe\xff\xff\xff";
+    assert_eq!(FastPair::parse(input), Ok((&input[6..], FastPair(1, -2))));
+    assert_eq!(FastPair::parse_be(input), Ok((&input[6..], FastPair(0x100, -16777217))));
+    let input = [0; 9];
+    assert_eq!(FastPair::parse(&input), Err(Err::Error(error_position!(&input[..], ErrorKind::TooLarge))));
+}
+
+#[test]
+fn test_unsafe_fast_path_field_named_i() {
+    let input = b"\x00\x01\x02\x03";
+    assert_eq!(FastNamedI::parse(input), Ok((&input[3..], FastNamedI{i:1, j:2})));
+}